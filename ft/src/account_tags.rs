@@ -0,0 +1,152 @@
+/*!
+Owner-assigned tags on accounts (e.g. "vip", "partner") for loyalty tiers and segmentation, which
+fee tiers or limits can key off. Each account has at most `MAX_TAGS_PER_ACCOUNT` tags of at most
+`MAX_TAG_LEN` bytes, and the owner pays for their storage.
+
+For reporting, `total_balance_with_tag` sums the balances of all tagged holders, after pending
+demurrage like `ft_balance_of`. It iterates over the whole holder set, so for a large token
+`total_balance_with_tag_paged` sums one page of the holder set at a time instead.
+*/
+use crate::*;
+
+/// Most tags a single account can have.
+pub const MAX_TAGS_PER_ACCOUNT: usize = 10;
+/// Longest accepted tag, in bytes.
+pub const MAX_TAG_LEN: usize = 32;
+
+#[near_bindgen]
+impl Contract {
+    /// Tags a registered account. Attached deposit must cover the added storage, the rest is
+    /// refunded.
+    #[payable]
+    pub fn add_account_tag(&mut self, account_id: AccountId, tag: String) {
+        self.assert_owner_action();
+        assert!(
+            !tag.is_empty() && tag.len() <= MAX_TAG_LEN,
+            "Tags should be between 1 and {} bytes long",
+            MAX_TAG_LEN
+        );
+        self.token.internal_unwrap_balance_of(&account_id);
+        let initial_storage_usage = env::storage_usage();
+        let mut tags = self.account_tags.get(&account_id).unwrap_or_default();
+        if tags.contains(&tag) {
+            refund_deposit(initial_storage_usage);
+            return;
+        }
+        assert!(tags.len() < MAX_TAGS_PER_ACCOUNT, "@{} has too many tags", account_id);
+        tags.push(tag);
+        self.account_tags.insert(&account_id, &tags);
+        refund_deposit(initial_storage_usage);
+    }
+
+    pub fn remove_account_tag(&mut self, account_id: AccountId, tag: String) {
+        self.assert_owner_action();
+        let mut tags = self.account_tags.get(&account_id).unwrap_or_default();
+        tags.retain(|existing| existing != &tag);
+        if tags.is_empty() {
+            self.account_tags.remove(&account_id);
+        } else {
+            self.account_tags.insert(&account_id, &tags);
+        }
+    }
+
+    pub fn get_account_tags(&self, account_id: AccountId) -> Vec<String> {
+        self.account_tags.get(&account_id).unwrap_or_default()
+    }
+
+    /// Sums the balances of all holders tagged `tag`.
+    pub fn total_balance_with_tag(&self, tag: String) -> U128 {
+        self.total_balance_with_tag_paged(tag, None, None).0
+    }
+
+    /// Sums the balances of the holders tagged `tag` among up to `limit` holders from
+    /// `from_index`. Returns the partial sum and the index of the next page, zero once all
+    /// holders were covered.
+    pub fn total_balance_with_tag_paged(
+        &self,
+        tag: String,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> (U128, u64) {
+        let from_index = from_index.unwrap_or(0);
+        let limit = limit.unwrap_or(u64::MAX);
+        let page: Vec<AccountId> =
+            self.holders.iter().skip(from_index as usize).take(limit as usize).collect();
+        let total: Balance = page
+            .iter()
+            .filter(|account_id| {
+                self.account_tags.get(account_id).is_some_and(|tags| tags.contains(&tag))
+            })
+            .map(|account_id| self.internal_decayed_balance(account_id))
+            .sum();
+        let next_index = from_index + page.len() as u64;
+        let next_index = if next_index >= self.holders.len() { 0 } else { next_index };
+        (total.into(), next_index)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::demurrage::Demurrage;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_tags() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(ONE_NEAR / 100).build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.add_account_tag(accounts(1), "vip".to_string());
+        contract.add_account_tag(accounts(1), "partner".to_string());
+        contract.add_account_tag(accounts(1), "vip".to_string());
+        assert_eq!(contract.get_account_tags(accounts(1)), vec!["vip", "partner"]);
+        contract.remove_account_tag(accounts(1), "vip".to_string());
+        assert_eq!(contract.get_account_tags(accounts(1)), vec!["partner"]);
+    }
+
+    #[test]
+    fn test_total_balance_with_tag() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        for account_id in [accounts(2), accounts(3)] {
+            testing_env!(context
+                .attached_deposit(contract.storage_balance_bounds().min.into())
+                .predecessor_account_id(account_id)
+                .build());
+            contract.storage_deposit(None, None);
+        }
+        testing_env!(context
+            .attached_deposit(ONE_NEAR / 100)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.add_account_tag(accounts(2), "kyc".to_string());
+        contract.add_account_tag(accounts(3), "kyc".to_string());
+        testing_env!(context.attached_deposit(1).build());
+        contract.ft_transfer(accounts(2), 10.into(), None);
+        contract.ft_transfer(accounts(3), 20.into(), None);
+        let mut total = 0;
+        let mut next_index = 0;
+        loop {
+            let (partial, next) =
+                contract.total_balance_with_tag_paged("kyc".to_string(), Some(next_index), Some(1));
+            total += partial.0;
+            next_index = next;
+            if next_index == 0 {
+                break;
+            }
+        }
+        assert_eq!(total, 30);
+        assert_eq!(contract.total_balance_with_tag("kyc".to_string()).0, 30);
+        contract.set_demurrage(Some(Demurrage {
+            rate_bps: 5_000,
+            period_seconds: 10,
+            redistribute: false,
+        }));
+        testing_env!(context.block_timestamp(10 * NANOS).build());
+        assert_eq!(contract.total_balance_with_tag("kyc".to_string()).0, 15);
+    }
+}
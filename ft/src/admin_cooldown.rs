@@ -0,0 +1,83 @@
+/*!
+Minimum time between privileged owner actions, so a compromised owner key can only change one
+setting at a time and the guardian gets a chance to pause the contract in between. The emergency
+actions, `pause` and `freeze_supply`, are exempt.
+*/
+use crate::*;
+
+/// Longest allowed cooldown, so the owner can't lock themselves out for good.
+pub const MAX_ADMIN_COOLDOWN_SEC: u64 = 7 * 24 * 60 * 60;
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the cooldown in seconds. Zero disables it.
+    pub fn set_admin_cooldown(&mut self, admin_cooldown_seconds: u64) {
+        self.assert_owner_action();
+        assert!(
+            admin_cooldown_seconds <= MAX_ADMIN_COOLDOWN_SEC,
+            "The cooldown can't exceed {} seconds",
+            MAX_ADMIN_COOLDOWN_SEC
+        );
+        self.admin_cooldown = admin_cooldown_seconds;
+    }
+
+    pub fn get_admin_cooldown(&self) -> u64 {
+        self.admin_cooldown
+    }
+
+    /// Timestamp (in seconds) of the last privileged owner action.
+    pub fn get_last_admin_action(&self) -> u64 {
+        self.last_admin_action_ts
+    }
+}
+
+impl Contract {
+    /// Checks the caller is the owner and that the cooldown since the previous privileged action
+    /// has passed, then starts a new cooldown.
+    pub(crate) fn assert_owner_action(&mut self) {
+        self.assert_owner();
+        let now = current_timestamp_sec();
+        if self.admin_cooldown > 0 {
+            assert!(
+                now >= self.last_admin_action_ts + self.admin_cooldown,
+                "The admin cooldown hasn't passed yet"
+            );
+        }
+        self.last_admin_action_ts = now;
+        self.internal_record_change("config".to_string());
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_admin_cooldown(60);
+        (context, contract)
+    }
+
+    #[test]
+    fn test_actions_after_cooldown() {
+        let (mut context, mut contract) = setup();
+        contract.pause();
+        testing_env!(context.block_timestamp(60 * NANOS).build());
+        contract.unpause();
+        assert!(!contract.is_paused());
+    }
+
+    #[test]
+    #[should_panic(expected = "The admin cooldown hasn't passed yet")]
+    fn test_action_within_cooldown() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.block_timestamp(59 * NANOS).build());
+        contract.add_minter(accounts(2));
+    }
+}
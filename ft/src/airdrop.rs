@@ -0,0 +1,341 @@
+/*!
+Merkle airdrops with sponsored claims. Anyone can fund the pool, and the owner publishes the root
+of a Merkle tree whose leaves are `sha256("<account_id>:<amount>")`. Pairs of nodes are hashed in
+sorted order, so proofs don't need to say which side each sibling is on. Every leaf can be claimed
+once per root.
+
+An account can claim for itself with `claim_airdrop`. With `claim_airdrop_for`, a relayer claims
+and pays the gas on the recipient's behalf. The recipient authorizes the claim by signing
+`"<token contract>:airdrop:<account_id>:<amount>"`. The contract can't read the access keys of an
+account, so the signing key must be the one behind an implicit account ID. Named accounts claim
+for themselves. The tokens always go to the account in the leaf, so a relayer can't redirect them.
+
+While a personhood gate is configured, the account in the leaf must be confirmed as a unique human
+first. Claims resolve to whether the tokens were paid out.
+*/
+use crate::signatures::{ed25519_key_bytes, verify_ed25519};
+use crate::*;
+use near_sdk::json_types::Base64VecU8;
+use near_sdk::{PromiseError, PublicKey};
+
+/// Deepest accepted proof, enough for trees of 2^32 leaves.
+const MAX_PROOF_LEN: usize = 32;
+const GAS_FOR_ON_AIRDROP_PERSONHOOD_CHECKED: Gas = Gas(20_000_000_000_000);
+
+#[near_bindgen]
+impl Contract {
+    /// Publishes the Merkle root of a new airdrop, or stops claims when `None`. Leaves claimed
+    /// under an earlier root can be claimed again under the new one.
+    pub fn set_airdrop_root(&mut self, root: Option<Base64VecU8>) {
+        self.assert_owner_action();
+        if let Some(root) = root.as_ref() {
+            assert!(root.0.len() == 32, "The root should be a 32-byte hash");
+        }
+        self.airdrop_root = root.map(|root| root.0);
+    }
+
+    pub fn get_airdrop_root(&self) -> Option<Base64VecU8> {
+        self.airdrop_root.clone().map(Base64VecU8)
+    }
+
+    /// Moves `amount` of the caller's tokens to the airdrop pool. Anyone can fund it, which also
+    /// works once the owner is the multisig contract account.
+    #[payable]
+    pub fn fund_airdrop(&mut self, amount: U128) {
+        assert_one_yocto();
+        assert!(amount.0 > 0, "The amount should be a positive number");
+        self.internal_register_contract_account();
+        let sender_id = env::predecessor_account_id();
+        self.internal_record_activity(&sender_id);
+        self.internal_move_balance(
+            &sender_id,
+            &env::current_account_id(),
+            amount.0,
+            Some("airdrop funding".to_string()),
+        );
+        self.airdrop_pool += amount.0;
+    }
+
+    /// Tokens left in the airdrop pool.
+    pub fn get_airdrop_pool(&self) -> U128 {
+        self.airdrop_pool.into()
+    }
+
+    pub fn is_airdrop_claimed(&self, account_id: AccountId, amount: U128) -> bool {
+        self.airdrop_root.as_ref().is_some_and(|root| {
+            self.airdrop_claims.contains(&claim_key(root, &airdrop_leaf(&account_id, amount.0)))
+        })
+    }
+
+    /// Claims the caller's airdrop. Attached deposit must cover the storage of the claim record,
+    /// the rest is refunded.
+    #[payable]
+    pub fn claim_airdrop(&mut self, amount: U128, proof: Vec<Base64VecU8>) -> PromiseOrValue<bool> {
+        self.internal_record_activity(&env::predecessor_account_id());
+        self.internal_claim_airdrop_as_human(env::predecessor_account_id(), amount.0, proof)
+    }
+
+    /// Claims the airdrop of `account_id` on its behalf, authorized by its signature with the key
+    /// of its implicit account ID. The caller pays for the storage of the claim record.
+    #[payable]
+    pub fn claim_airdrop_for(
+        &mut self,
+        account_id: AccountId,
+        amount: U128,
+        proof: Vec<Base64VecU8>,
+        public_key: PublicKey,
+        signature: Base64VecU8,
+    ) -> PromiseOrValue<bool> {
+        let key = ed25519_key_bytes(&public_key);
+        let implicit_account_id: String = key.iter().map(|byte| format!("{:02x}", byte)).collect();
+        assert_eq!(
+            account_id.as_str(),
+            implicit_account_id,
+            "The key doesn't belong to the account"
+        );
+        let message = format!("{}:airdrop:{}:{}", env::current_account_id(), account_id, amount.0);
+        assert!(
+            verify_ed25519(&key, message.as_bytes(), &signature.0),
+            "The signature doesn't match the account"
+        );
+        self.internal_claim_airdrop_as_human(account_id, amount.0, proof)
+    }
+
+    /// Completes a claim once the registry answered, refunding the deposit to `payer_id` if
+    /// `account_id` isn't confirmed as a unique human.
+    #[private]
+    #[payable]
+    pub fn on_airdrop_personhood_checked(
+        &mut self,
+        account_id: AccountId,
+        amount: U128,
+        proof: Vec<Base64VecU8>,
+        payer_id: AccountId,
+        #[callback_result] is_human: Result<bool, PromiseError>,
+    ) -> bool {
+        if !self.internal_record_personhood(&account_id, is_human == Ok(true)) {
+            Promise::new(payer_id).transfer(env::attached_deposit());
+            return false;
+        }
+        let initial_storage_usage = env::storage_usage();
+        self.internal_claim_airdrop(&account_id, amount.0, proof);
+        refund_deposit_to(&payer_id, initial_storage_usage, 0);
+        true
+    }
+}
+
+impl Contract {
+    /// Claims right away if there's no personhood gate or `account_id` is already confirmed,
+    /// otherwise asks the registry and claims in the callback.
+    fn internal_claim_airdrop_as_human(
+        &mut self,
+        account_id: AccountId,
+        amount: Balance,
+        proof: Vec<Base64VecU8>,
+    ) -> PromiseOrValue<bool> {
+        match self.internal_personhood_check(&account_id) {
+            Some(check) => {
+                // Fail early rather than in the callback, which would keep the deposit.
+                self.internal_assert_airdrop_claimable(&account_id, amount, &proof);
+                check
+                    .then(
+                        Self::ext(env::current_account_id())
+                            .with_attached_deposit(env::attached_deposit())
+                            .with_static_gas(GAS_FOR_ON_AIRDROP_PERSONHOOD_CHECKED)
+                            .on_airdrop_personhood_checked(
+                                account_id,
+                                amount.into(),
+                                proof,
+                                env::predecessor_account_id(),
+                            ),
+                    )
+                    .into()
+            }
+            None => {
+                let initial_storage_usage = env::storage_usage();
+                self.internal_claim_airdrop(&account_id, amount, proof);
+                refund_deposit(initial_storage_usage);
+                PromiseOrValue::Value(true)
+            }
+        }
+    }
+
+    /// Checks the proof and the pool. Returns the root and the leaf of the claim.
+    fn internal_assert_airdrop_claimable(
+        &self,
+        account_id: &AccountId,
+        amount: Balance,
+        proof: &[Base64VecU8],
+    ) -> (Vec<u8>, Vec<u8>) {
+        let root = self.airdrop_root.clone().expect("No airdrop is running");
+        self.token.internal_unwrap_balance_of(account_id);
+        assert!(proof.len() <= MAX_PROOF_LEN, "The proof is too long");
+        let leaf = airdrop_leaf(account_id, amount);
+        let computed_root = proof.iter().fold(leaf.clone(), |node, sibling| {
+            assert!(sibling.0.len() == 32, "Proof entries should be 32-byte hashes");
+            if node <= sibling.0 {
+                env::sha256(&[&node[..], &sibling.0].concat())
+            } else {
+                env::sha256(&[&sibling.0[..], &node].concat())
+            }
+        });
+        assert!(computed_root == root, "Invalid proof");
+        assert!(amount <= self.airdrop_pool, "The airdrop pool is too low");
+        assert!(
+            !self.airdrop_claims.contains(&claim_key(&root, &leaf)),
+            "The airdrop was already claimed"
+        );
+        (root, leaf)
+    }
+
+    fn internal_claim_airdrop(
+        &mut self,
+        account_id: &AccountId,
+        amount: Balance,
+        proof: Vec<Base64VecU8>,
+    ) {
+        let (root, leaf) = self.internal_assert_airdrop_claimable(account_id, amount, &proof);
+        assert!(
+            self.airdrop_claims.insert(&claim_key(&root, &leaf)),
+            "The airdrop was already claimed"
+        );
+        self.airdrop_pool -= amount;
+        self.internal_move_balance(
+            &env::current_account_id(),
+            account_id,
+            amount,
+            Some("airdrop".to_string()),
+        );
+        self.internal_lock_received(account_id, amount);
+    }
+}
+
+fn airdrop_leaf(account_id: &AccountId, amount: Balance) -> Vec<u8> {
+    env::sha256(format!("{}:{}", account_id, amount).as_bytes())
+}
+
+/// Claims are recorded per root, so a new airdrop starts with no claims.
+fn claim_key(root: &[u8], leaf: &[u8]) -> Vec<u8> {
+    env::sha256(&[root, leaf].concat())
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use ed25519_dalek::{Keypair, SecretKey, Signer};
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+    use std::convert::TryFrom;
+
+    use super::*;
+    use crate::personhood::PersonhoodGate;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn keypair() -> Keypair {
+        let secret = SecretKey::from_bytes(&[1; 32]).unwrap();
+        let public = (&secret).into();
+        Keypair { secret, public }
+    }
+
+    fn implicit_account_id() -> AccountId {
+        let hex: String =
+            keypair().public.to_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+        AccountId::new_unchecked(hex)
+    }
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        let (a, b) = (airdrop_leaf(&implicit_account_id(), 100), airdrop_leaf(&accounts(2), 200));
+        let root = if a <= b { [a, b].concat() } else { [b, a].concat() };
+        contract.set_airdrop_root(Some(env::sha256(&root).into()));
+        testing_env!(context.attached_deposit(1).build());
+        contract.fund_airdrop(300.into());
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.storage_deposit(Some(implicit_account_id()), None);
+        testing_env!(context.attached_deposit(ONE_NEAR / 100).build());
+        (context, contract)
+    }
+
+    fn claim_for(contract: &mut Contract, amount: Balance) {
+        let message =
+            format!("{}:airdrop:{}:{}", env::current_account_id(), implicit_account_id(), amount);
+        let signature = keypair().sign(message.as_bytes()).to_bytes().to_vec();
+        let public_key = [&[0u8][..], &keypair().public.to_bytes()].concat();
+        contract.claim_airdrop_for(
+            implicit_account_id(),
+            amount.into(),
+            vec![airdrop_leaf(&accounts(2), 200).into()],
+            PublicKey::try_from(public_key).unwrap(),
+            signature.into(),
+        );
+    }
+
+    #[test]
+    fn test_sponsored_claim() {
+        let (_, mut contract) = setup();
+        claim_for(&mut contract, 100);
+        assert_eq!(contract.ft_balance_of(implicit_account_id()).0, 100);
+        assert_eq!(contract.get_airdrop_pool().0, 200);
+        assert!(contract.is_airdrop_claimed(implicit_account_id(), 100.into()));
+    }
+
+    #[test]
+    fn test_claim_after_personhood_check() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.attached_deposit(0).predecessor_account_id(accounts(1)).build());
+        contract.set_personhood_gate(Some(PersonhoodGate {
+            registry_id: accounts(4),
+            ttl_seconds: 3600,
+        }));
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context.attached_deposit(ONE_NEAR / 100).build());
+        let proof = vec![airdrop_leaf(&implicit_account_id(), 100).into()];
+        assert!(matches!(
+            contract.claim_airdrop(200.into(), proof.clone()),
+            PromiseOrValue::Promise(_)
+        ));
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 0);
+        assert!(contract.on_airdrop_personhood_checked(
+            accounts(2),
+            200.into(),
+            proof,
+            accounts(2),
+            Ok(true)
+        ));
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 200);
+        assert!(contract.is_verified_human(accounts(2)));
+    }
+
+    #[test]
+    #[should_panic(expected = "The signature doesn't match the account")]
+    fn test_claim_with_other_amount() {
+        let (_, mut contract) = setup();
+        let message =
+            format!("{}:airdrop:{}:{}", env::current_account_id(), implicit_account_id(), 100);
+        let signature = keypair().sign(message.as_bytes()).to_bytes().to_vec();
+        let public_key = [&[0u8][..], &keypair().public.to_bytes()].concat();
+        contract.claim_airdrop_for(
+            implicit_account_id(),
+            200.into(),
+            vec![],
+            PublicKey::try_from(public_key).unwrap(),
+            signature.into(),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The airdrop was already claimed")]
+    fn test_double_claim() {
+        let (_, mut contract) = setup();
+        claim_for(&mut contract, 100);
+        claim_for(&mut contract, 100);
+    }
+}
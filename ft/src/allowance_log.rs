@@ -0,0 +1,133 @@
+/*!
+Audit log of allowance spends. Each owner gets a bounded log of who spent their allowances, how
+much and when. Once the log holds `allowance_log_capacity` entries, every new spend evicts the
+oldest one, so the storage per owner stays bounded.
+*/
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+
+pub const DEFAULT_ALLOWANCE_LOG_CAPACITY: u64 = 20;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AllowanceSpend {
+    pub spender_id: AccountId,
+    pub amount: U128,
+    /// Timestamp of the spend in seconds.
+    pub timestamp: u64,
+}
+
+/// Sequence numbers of the oldest entry and of the next entry in an owner's log.
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct AllowanceLogRange {
+    pub first: u64,
+    pub next: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the number of spends kept per owner. Zero stops logging. Lowering it evicts the extra
+    /// entries on the owner's next spend.
+    pub fn set_allowance_log_capacity(&mut self, capacity: u64) {
+        self.assert_owner_action();
+        self.allowance_log_capacity = capacity;
+    }
+
+    pub fn get_allowance_log_capacity(&self) -> u64 {
+        self.allowance_log_capacity
+    }
+
+    /// Returns the logged spends of `owner`'s allowances, oldest first.
+    pub fn allowance_spend_history(
+        &self,
+        owner: AccountId,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<AllowanceSpend> {
+        let range = self.allowance_log_ranges.get(&owner).unwrap_or_default();
+        let start = range.first + from_index.unwrap_or(0);
+        let end = limit.map_or(range.next, |limit| range.next.min(start + limit));
+        (start..end).filter_map(|seq| self.allowance_log.get(&(owner.clone(), seq))).collect()
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_log_allowance_spend(
+        &mut self,
+        owner_id: &AccountId,
+        spender_id: AccountId,
+        amount: Balance,
+    ) {
+        if self.allowance_log_capacity == 0 {
+            return;
+        }
+        let mut range = self.allowance_log_ranges.get(owner_id).unwrap_or_default();
+        let spend = AllowanceSpend {
+            spender_id,
+            amount: amount.into(),
+            timestamp: current_timestamp_sec(),
+        };
+        self.allowance_log.insert(&(owner_id.clone(), range.next), &spend);
+        range.next += 1;
+        while range.next - range.first > self.allowance_log_capacity {
+            self.allowance_log.remove(&(owner_id.clone(), range.first));
+            range.first += 1;
+        }
+        self.allowance_log_ranges.insert(owner_id, &range);
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_allowance_log_capacity(2);
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context
+            .attached_deposit(ONE_NEAR / 100)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.ft_approve(accounts(2), 100.into());
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_spends_are_logged_and_evicted() {
+        let (mut context, mut contract) = setup();
+        for (second, amount) in [(1, 10), (2, 20), (3, 30)] {
+            testing_env!(context.block_timestamp(second * NANOS).build());
+            contract.ft_transfer_from(accounts(1), accounts(3), amount.into(), None);
+        }
+        assert_eq!(contract.ft_allowance(accounts(1), accounts(2)).0, 40);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 60);
+        let history = contract.allowance_spend_history(accounts(1), None, None);
+        assert_eq!(
+            history,
+            vec![
+                AllowanceSpend { spender_id: accounts(2), amount: 20.into(), timestamp: 2 },
+                AllowanceSpend { spender_id: accounts(2), amount: 30.into(), timestamp: 3 },
+            ]
+        );
+        assert_eq!(contract.allowance_spend_history(accounts(1), Some(1), Some(5)).len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "The allowance is too low")]
+    fn test_spend_above_allowance() {
+        let (_, mut contract) = setup();
+        contract.ft_transfer_from(accounts(1), accounts(3), 101.into(), None);
+    }
+}
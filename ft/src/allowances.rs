@@ -0,0 +1,300 @@
+/*!
+Allowances on top of NEP-141: an owner approves a spender for an amount, and the spender can then
+transfer up to that amount out of the owner's account with `ft_transfer_from`.
+
+To limit the damage of a phishing approval, an owner can cap the allowance any single spender can
+hold. Approvals above the cap are rejected, and allowances given before the cap was lowered can
+only be spent up to it.
+
+An allowance can also be restricted to a window of block heights with `ft_approve_within`, for
+scheduled or one-time pulls. Plain approvals are open-ended. Rate-limited allowances are in
+`streaming_allowances`.
+
+Allowances whose window has ended can never be spent again, and anyone can prune them with
+`prune_allowances`. The storage they free is refunded to the owner, who paid for it.
+*/
+use crate::*;
+use std::collections::HashSet;
+
+/// Most spenders `ft_approve_batch` accepts in one call.
+pub const MAX_APPROVE_BATCH: usize = 20;
+/// Most spenders `prune_allowances` checks in one call.
+pub const MAX_PRUNE_BATCH: usize = 50;
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the amount `spender_id` can transfer out of the caller's account, replacing any
+    /// previous allowance. Requires at least 1 yoctoNEAR attached, which must also cover the added
+    /// storage, the rest is refunded.
+    #[payable]
+    pub fn ft_approve(&mut self, spender_id: AccountId, amount: U128) {
+        assert_at_least_one_yocto();
+        let owner_id = env::predecessor_account_id();
+        self.internal_record_activity(&owner_id);
+        self.token.internal_unwrap_balance_of(&owner_id);
+        let initial_storage_usage = env::storage_usage();
+        self.internal_set_allowance(&owner_id, spender_id, amount.0);
+        refund_deposit(initial_storage_usage);
+    }
+
+    /// Same as `ft_approve`, but the allowance can only be spent from block `valid_from` up to
+    /// and including block `valid_until`. Either bound can be left open.
+    #[payable]
+    pub fn ft_approve_within(
+        &mut self,
+        spender_id: AccountId,
+        amount: U128,
+        valid_from: Option<u64>,
+        valid_until: Option<u64>,
+    ) {
+        assert_at_least_one_yocto();
+        let owner_id = env::predecessor_account_id();
+        self.internal_record_activity(&owner_id);
+        self.token.internal_unwrap_balance_of(&owner_id);
+        let window = (valid_from.unwrap_or(0), valid_until.unwrap_or(u64::MAX));
+        assert!(window.0 <= window.1, "The window should end after it starts");
+        let initial_storage_usage = env::storage_usage();
+        self.internal_set_allowance(&owner_id, spender_id.clone(), amount.0);
+        if amount.0 > 0 && window != (0, u64::MAX) {
+            self.allowance_windows.insert(&(owner_id, spender_id), &window);
+        }
+        refund_deposit(initial_storage_usage);
+    }
+
+    /// Same as `ft_approve` for up to `MAX_APPROVE_BATCH` spenders at once. Each spender can only
+    /// appear once.
+    #[payable]
+    pub fn ft_approve_batch(&mut self, approvals: Vec<(AccountId, U128)>) {
+        assert_at_least_one_yocto();
+        assert!(approvals.len() <= MAX_APPROVE_BATCH, "Too many approvals in one batch");
+        let owner_id = env::predecessor_account_id();
+        self.internal_record_activity(&owner_id);
+        self.token.internal_unwrap_balance_of(&owner_id);
+        let mut spenders = HashSet::new();
+        for (spender_id, _) in &approvals {
+            assert!(spenders.insert(spender_id), "Duplicate spender {}", spender_id);
+        }
+        let initial_storage_usage = env::storage_usage();
+        for (spender_id, amount) in approvals {
+            self.internal_set_allowance(&owner_id, spender_id, amount.0);
+        }
+        refund_deposit(initial_storage_usage);
+    }
+
+    /// Block heights `(valid_from, valid_until)` between which the allowance of `spender_id` can
+    /// be spent, both included.
+    pub fn ft_allowance_window(&self, owner_id: AccountId, spender_id: AccountId) -> (u64, u64) {
+        self.allowance_windows.get(&(owner_id, spender_id)).unwrap_or((0, u64::MAX))
+    }
+
+    /// Removes the allowances of `spenders` on `owner_id`'s account whose window has ended, and
+    /// refunds the freed storage to the owner. Other allowances are left alone. Returns the number
+    /// of allowances removed.
+    pub fn prune_allowances(&mut self, owner_id: AccountId, spenders: Vec<AccountId>) -> u64 {
+        assert!(spenders.len() <= MAX_PRUNE_BATCH, "Too many spenders in one call");
+        let initial_storage_usage = env::storage_usage();
+        let block_height = env::block_height();
+        let mut pruned = 0;
+        for spender_id in spenders {
+            let key = (owner_id.clone(), spender_id);
+            match self.allowance_windows.get(&key) {
+                Some((_, valid_until)) if valid_until < block_height => {
+                    self.allowance_windows.remove(&key);
+                    self.allowances.remove(&key);
+                    pruned += 1;
+                }
+                _ => {}
+            }
+        }
+        let freed = initial_storage_usage - env::storage_usage();
+        if freed > 0 {
+            Promise::new(owner_id).transfer(freed as Balance * env::storage_byte_cost());
+        }
+        pruned
+    }
+
+    pub fn ft_allowance(&self, owner_id: AccountId, spender_id: AccountId) -> U128 {
+        let cap = self.internal_max_allowance(&owner_id);
+        self.allowances.get(&(owner_id, spender_id)).unwrap_or(0).min(cap).into()
+    }
+
+    /// Caps the allowance of each spender on the caller's account, or removes the cap when
+    /// `None`. Requires at least 1 yoctoNEAR attached, which must also cover the added storage,
+    /// the rest is refunded.
+    #[payable]
+    pub fn set_max_allowance(&mut self, cap: Option<U128>) {
+        assert_at_least_one_yocto();
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        self.token.internal_unwrap_balance_of(&account_id);
+        let initial_storage_usage = env::storage_usage();
+        match cap {
+            Some(cap) => self.max_allowances.insert(&account_id, &cap.0),
+            None => self.max_allowances.remove(&account_id),
+        };
+        refund_deposit(initial_storage_usage);
+    }
+
+    pub fn get_max_allowance(&self, account_id: AccountId) -> Option<U128> {
+        self.max_allowances.get(&account_id).map(U128)
+    }
+
+    /// Transfers `amount` from `owner_id` to `receiver_id` out of the caller's allowance. Requires
+    /// at least 1 yoctoNEAR attached, plus the priority fee and the storage of the spend log entry
+    /// until the owner's log is full.
+    #[payable]
+    pub fn ft_transfer_from(
+        &mut self,
+        owner_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+    ) {
+        assert_at_least_one_yocto();
+        let priority_fee = self.internal_priority_fee(&[amount.0]);
+        self.internal_pay_priority_fee(priority_fee);
+        self.assert_no_transfer_validator();
+        self.assert_no_attestation_gate();
+        let spender_id = env::predecessor_account_id();
+        let key = (owner_id.clone(), spender_id.clone());
+        let initial_storage_usage = env::storage_usage();
+        match self.streaming_allowances.get(&key) {
+            Some(allowance) => self.internal_spend_streaming_allowance(&key, allowance, amount.0),
+            None => self.internal_spend_allowance(&key, amount.0),
+        }
+        self.internal_transfer(&owner_id, &receiver_id, amount.0, memo);
+        self.internal_log_allowance_spend(&owner_id, spender_id, amount.0);
+        refund_deposit_after_fee(initial_storage_usage, priority_fee);
+    }
+}
+
+impl Contract {
+    /// Replaces the allowance of `spender_id` on `owner_id`'s account. Zero removes it.
+    pub(crate) fn internal_set_allowance(
+        &mut self,
+        owner_id: &AccountId,
+        spender_id: AccountId,
+        amount: Balance,
+    ) {
+        assert_ne!(owner_id, &spender_id, "Can't approve yourself");
+        let cap = self.internal_max_allowance(owner_id);
+        assert!(amount <= cap, "The allowance exceeds the cap of {}", cap);
+        self.allowance_windows.remove(&(owner_id.clone(), spender_id.clone()));
+        self.streaming_allowances.remove(&(owner_id.clone(), spender_id.clone()));
+        if amount == 0 {
+            self.allowances.remove(&(owner_id.clone(), spender_id));
+        } else {
+            self.allowances.insert(&(owner_id.clone(), spender_id), &amount);
+        }
+    }
+
+    /// Spends `amount` of the plain allowance `(owner_id, spender_id)`.
+    fn internal_spend_allowance(&mut self, key: &(AccountId, AccountId), amount: Balance) {
+        let allowance = self.allowances.get(key).unwrap_or(0);
+        let allowance = allowance.min(self.internal_max_allowance(&key.0));
+        assert!(amount <= allowance, "The allowance is too low");
+        if let Some((valid_from, valid_until)) = self.allowance_windows.get(key) {
+            let block_height = env::block_height();
+            assert!(
+                block_height >= valid_from && block_height <= valid_until,
+                "The allowance can only be spent from block {} to {}",
+                valid_from,
+                valid_until
+            );
+        }
+        if allowance == amount {
+            self.allowances.remove(key);
+            self.allowance_windows.remove(key);
+        } else {
+            self.allowances.insert(key, &(allowance - amount));
+        }
+    }
+
+    pub(crate) fn internal_max_allowance(&self, owner_id: &AccountId) -> Balance {
+        self.max_allowances.get(owner_id).unwrap_or(Balance::MAX)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> Contract {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(ONE_NEAR / 100).build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.ft_approve(accounts(2), 5.into());
+        contract
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of at least 1 yoctoNEAR")]
+    fn test_raising_allowance_requires_deposit() {
+        let mut contract = setup();
+        testing_env!(get_context(accounts(1)).attached_deposit(0).build());
+        contract.ft_approve(accounts(2), TOTAL_SUPPLY.into());
+    }
+
+    #[test]
+    fn test_approve_batch() {
+        let mut contract = setup();
+        contract.ft_approve_batch(vec![(accounts(2), 0.into()), (accounts(3), 30.into())]);
+        assert_eq!(contract.ft_allowance(accounts(1), accounts(2)).0, 0);
+        assert_eq!(contract.ft_allowance(accounts(1), accounts(3)).0, 30);
+    }
+
+    #[test]
+    fn test_lowered_cap_limits_existing_allowance() {
+        let mut contract = setup();
+        contract.set_max_allowance(Some(3.into()));
+        assert_eq!(contract.get_max_allowance(accounts(1)), Some(3.into()));
+        assert_eq!(contract.ft_allowance(accounts(1), accounts(2)).0, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "The allowance exceeds the cap of 10")]
+    fn test_approve_above_cap() {
+        let mut contract = setup();
+        contract.set_max_allowance(Some(10.into()));
+        contract.ft_approve(accounts(3), 11.into());
+    }
+
+    #[test]
+    #[should_panic(expected = "The allowance can only be spent from block 10 to 20")]
+    fn test_allowance_window() {
+        let mut contract = setup();
+        contract.ft_approve_within(accounts(2), 10.into(), Some(10), Some(20));
+        assert_eq!(contract.ft_allowance_window(accounts(1), accounts(2)), (10, 20));
+        let mut context = get_context(accounts(2));
+        testing_env!(context.attached_deposit(ONE_NEAR / 100).block_index(10).build());
+        contract.storage_deposit(None, None);
+        contract.ft_transfer_from(accounts(1), accounts(2), 5.into(), None);
+        testing_env!(context.block_index(21).build());
+        contract.ft_transfer_from(accounts(1), accounts(2), 5.into(), None);
+    }
+
+    #[test]
+    fn test_prune_ended_allowances() {
+        let mut contract = setup();
+        contract.ft_approve_within(accounts(3), 10.into(), None, Some(20));
+        let mut context = get_context(accounts(4));
+        testing_env!(context.block_index(20).build());
+        assert_eq!(contract.prune_allowances(accounts(1), vec![accounts(2), accounts(3)]), 0);
+        testing_env!(context.block_index(21).build());
+        assert_eq!(contract.prune_allowances(accounts(1), vec![accounts(2), accounts(3)]), 1);
+        assert_eq!(contract.ft_allowance(accounts(1), accounts(2)).0, 5);
+        assert_eq!(contract.ft_allowance(accounts(1), accounts(3)).0, 0);
+        assert_eq!(contract.ft_allowance_window(accounts(1), accounts(3)), (0, u64::MAX));
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate spender")]
+    fn test_approve_batch_duplicates() {
+        let mut contract = setup();
+        contract.ft_approve_batch(vec![(accounts(3), 10.into()), (accounts(3), 30.into())]);
+    }
+}
@@ -0,0 +1,215 @@
+/*!
+KYC gating through attestation NFTs. While a gate is configured, both sides of a transfer must
+hold at least one token of the attestation NFT contract, checked with its NEP-181
+`nft_supply_for_owner`. Transfers have to go through `ft_transfer_gated`, and `ft_transfer`,
+`ft_transfer_call` and the other transfer methods are rejected in the meantime.
+
+Positive checks are cached per account for `ttl_seconds`, so a transfer between accounts with
+fresh attestations completes right away. Otherwise the missing checks run in parallel and the
+transfer completes in the callback. Nothing is locked in between, so the transfer is aborted if
+the sender no longer has the balance by then.
+*/
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{ext_contract, PromiseResult};
+
+const GAS_FOR_NFT_SUPPLY_FOR_OWNER: Gas = Gas(5_000_000_000_000);
+const GAS_FOR_ON_ATTESTATIONS_CHECKED: Gas = Gas(20_000_000_000_000);
+
+/// Part of the NEP-181 enumeration interface of the attestation NFT contract.
+#[ext_contract(ext_attestation_nft)]
+#[allow(dead_code)]
+pub trait AttestationNft {
+    fn nft_supply_for_owner(&self, account_id: AccountId) -> U128;
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AttestationGate {
+    pub nft_contract_id: AccountId,
+    /// How long (in seconds) a positive check stays valid.
+    pub ttl_seconds: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Enables gating, or disables it when `gate` is `None`. Changing the gate drops the cache.
+    pub fn set_attestation_gate(&mut self, gate: Option<AttestationGate>) {
+        self.assert_owner_action();
+        self.attestation_epoch += 1;
+        self.attestation_gate = gate;
+    }
+
+    pub fn get_attestation_gate(&self) -> Option<AttestationGate> {
+        self.attestation_gate.clone()
+    }
+
+    /// Whether `account_id` has a cached attestation that hasn't expired yet.
+    pub fn has_fresh_attestation(&self, account_id: AccountId) -> bool {
+        matches!(
+            self.attested_until.get(&account_id),
+            Some((epoch, until)) if epoch == self.attestation_epoch && until > current_timestamp_sec()
+        )
+    }
+
+    /// Transfers once both sides' attestations are confirmed. Resolves to whether the transfer
+    /// happened.
+    #[payable]
+    pub fn ft_transfer_gated(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+    ) -> PromiseOrValue<bool> {
+        self.assert_priority_fee_attached(&[amount.0]);
+        assert_ok(check_memo(&memo));
+        let gate = self.attestation_gate.clone().expect("Transfers are not gated");
+        let sender_id = env::predecessor_account_id();
+        let unchecked: Vec<AccountId> = [&sender_id, &receiver_id]
+            .iter()
+            .filter(|account_id| !self.has_fresh_attestation((**account_id).clone()))
+            .map(|account_id| (*account_id).clone())
+            .collect();
+        let checks = unchecked.iter().map(|account_id| {
+            ext_attestation_nft::ext(gate.nft_contract_id.clone())
+                .with_static_gas(GAS_FOR_NFT_SUPPLY_FOR_OWNER)
+                .nft_supply_for_owner(account_id.clone())
+        });
+        match checks.reduce(|all, check| all.and(check)) {
+            Some(all) => all
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(GAS_FOR_ON_ATTESTATIONS_CHECKED)
+                        .on_attestations_checked(
+                            sender_id,
+                            receiver_id,
+                            amount,
+                            memo,
+                            unchecked.clone(),
+                        ),
+                )
+                .into(),
+            None => {
+                self.internal_transfer(&sender_id, &receiver_id, amount.0, memo);
+                PromiseOrValue::Value(true)
+            }
+        }
+    }
+
+    /// Caches the positive checks and completes the transfer if both sides are attested.
+    #[private]
+    pub fn on_attestations_checked(
+        &mut self,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+        checked: Vec<AccountId>,
+    ) -> bool {
+        let ttl_seconds = match self.attestation_gate.as_ref() {
+            Some(gate) => gate.ttl_seconds,
+            None => return false,
+        };
+        let until = current_timestamp_sec() + ttl_seconds;
+        let mut attested = true;
+        for (index, account_id) in checked.iter().enumerate() {
+            let supply = match env::promise_result(index as u64) {
+                PromiseResult::Successful(value) => {
+                    near_sdk::serde_json::from_slice::<U128>(&value).map_or(0, |supply| supply.0)
+                }
+                _ => 0,
+            };
+            if supply > 0 {
+                self.attested_until.insert(account_id, &(self.attestation_epoch, until));
+            } else {
+                log!("@{} doesn't hold an attestation", account_id);
+                attested = false;
+            }
+        }
+        if !attested {
+            return false;
+        }
+        if self.token.accounts.get(&sender_id).unwrap_or(0) < amount.0 {
+            log!("The transfer from @{} was aborted, the balance is too low", sender_id);
+            return false;
+        }
+        self.internal_transfer(&sender_id, &receiver_id, amount.0, memo);
+        true
+    }
+}
+
+impl Contract {
+    pub(crate) fn assert_no_attestation_gate(&self) {
+        assert!(self.attestation_gate.is_none(), "Transfers are gated, use ft_transfer_gated");
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_attestation_gate(Some(AttestationGate {
+            nft_contract_id: accounts(4),
+            ttl_seconds: 100,
+        }));
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        (context, contract)
+    }
+
+    fn checked(context: &mut near_sdk::test_utils::VMContextBuilder, results: Vec<&[u8]>) {
+        testing_env!(
+            context.attached_deposit(0).predecessor_account_id(env::current_account_id()).build(),
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            results.into_iter().map(|r| PromiseResult::Successful(r.to_vec())).collect(),
+        );
+    }
+
+    #[test]
+    fn test_attested_transfer_is_cached() {
+        let (mut context, mut contract) = setup();
+        checked(&mut context, vec![b"\"1\"", b"\"2\""]);
+        assert!(contract.on_attestations_checked(
+            accounts(1),
+            accounts(2),
+            10.into(),
+            None,
+            vec![accounts(1), accounts(2)],
+        ));
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 10);
+
+        // Both are cached now, so the next transfer completes right away.
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer_gated(accounts(2), 10.into(), None);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 20);
+    }
+
+    #[test]
+    fn test_unattested_receiver() {
+        let (mut context, mut contract) = setup();
+        checked(&mut context, vec![b"\"1\"", b"\"0\""]);
+        assert!(!contract.on_attestations_checked(
+            accounts(1),
+            accounts(2),
+            10.into(),
+            None,
+            vec![accounts(1), accounts(2)],
+        ));
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 0);
+        assert!(contract.has_fresh_attestation(accounts(1)));
+        assert!(!contract.has_fresh_attestation(accounts(2)));
+    }
+}
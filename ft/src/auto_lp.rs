@@ -0,0 +1,238 @@
+/*!
+Automatic liquidity provisioning. A share of every transfer is taken as a fee and accumulated on
+the contract's own account. Once the accumulated fees reach the threshold, the transfer that
+crossed it also sends them to the DEX together with the NEAR reserve and calls
+`add_liquidity(token_id, token_amount)` on it with the NEAR attached. If the insurance fund is
+configured, its share of each fee goes to the fund instead, and likewise for the treasury. Senders
+can also pay the fee in a separate fee token, see `fee_token`.
+
+The DEX call runs asynchronously and never blocks the transfer, but the transfer that triggers it
+needs `GAS_FOR_ADD_LIQUIDITY + GAS_FOR_ON_LIQUIDITY_ADDED` of extra gas. Only one call is in
+flight at a time. If it fails, the tokens are moved back from the DEX and both the accumulator
+and the NEAR reserve are restored, so the next transfer over the threshold retries.
+*/
+use crate::math::{bps_of, BPS_DENOMINATOR};
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{ext_contract, is_promise_success};
+
+const GAS_FOR_ADD_LIQUIDITY: Gas = Gas(20_000_000_000_000);
+const GAS_FOR_ON_LIQUIDITY_ADDED: Gas = Gas(10_000_000_000_000);
+
+/// Interface the DEX is expected to implement. The tokens are already on the DEX's account when
+/// it's called, and the NEAR is attached.
+#[ext_contract(ext_auto_lp_dex)]
+#[allow(dead_code)]
+pub trait AutoLpDex {
+    fn add_liquidity(&mut self, token_id: AccountId, token_amount: U128);
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AutoLp {
+    pub dex_id: AccountId,
+    /// Accumulated fees that trigger adding liquidity.
+    pub threshold: U128,
+    /// Share of each transfer taken as the fee, in basis points.
+    pub lp_bps: u16,
+    /// Fees held by the contract account and not yet added as liquidity.
+    pub accumulated: U128,
+    /// NEAR paired with the tokens when adding liquidity.
+    pub near_reserve: U128,
+    /// Whether an `add_liquidity` call is in flight.
+    pub pending: bool,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Configures auto-LP. Setting `lp_bps` to zero stops taking fees, while the accumulator and
+    /// the NEAR reserve are kept.
+    pub fn set_auto_lp(&mut self, dex: AccountId, threshold: U128, lp_bps: u16) {
+        self.assert_owner_action();
+        assert!(lp_bps <= BPS_DENOMINATOR, "The fee can't exceed {} bps", BPS_DENOMINATOR);
+        assert!(threshold.0 > 0, "The threshold should be a positive number");
+        self.internal_register_contract_account();
+        match self.auto_lp.as_mut() {
+            Some(auto_lp) => {
+                auto_lp.dex_id = dex;
+                auto_lp.threshold = threshold;
+                auto_lp.lp_bps = lp_bps;
+            }
+            None => {
+                self.auto_lp = Some(AutoLp {
+                    dex_id: dex,
+                    threshold,
+                    lp_bps,
+                    accumulated: 0.into(),
+                    near_reserve: 0.into(),
+                    pending: false,
+                })
+            }
+        }
+    }
+
+    pub fn get_auto_lp(&self) -> Option<AutoLp> {
+        self.auto_lp.clone()
+    }
+
+    /// Adds the attached NEAR to the reserve paired with the tokens.
+    #[payable]
+    pub fn fund_auto_lp(&mut self) {
+        let amount = env::attached_deposit();
+        assert!(amount > 0, "Attach NEAR to fund the reserve");
+        let auto_lp = self.auto_lp.as_mut().expect("Auto-LP is not configured");
+        auto_lp.near_reserve = (auto_lp.near_reserve.0 + amount).into();
+    }
+
+    /// Restores the accumulator and the reserve if adding liquidity failed.
+    #[private]
+    pub fn on_liquidity_added(&mut self, token_amount: U128, near_amount: U128) -> bool {
+        let auto_lp = self.auto_lp.as_mut().expect("Auto-LP is not configured");
+        auto_lp.pending = false;
+        if is_promise_success() {
+            return true;
+        }
+        let dex_id = auto_lp.dex_id.clone();
+        auto_lp.near_reserve = (auto_lp.near_reserve.0 + near_amount.0).into();
+        let dex_balance = self.token.accounts.get(&dex_id).unwrap_or(0);
+        let amount = token_amount.0.min(dex_balance);
+        if amount > 0 {
+            auto_lp.accumulated = (auto_lp.accumulated.0 + amount).into();
+            self.internal_move_balance(
+                &dex_id,
+                &env::current_account_id(),
+                amount,
+                Some("auto-lp refund".to_string()),
+            );
+        }
+        false
+    }
+}
+
+impl Contract {
+    /// Takes the auto-LP fee on a transfer of `amount` and returns it. Transfers to and from the
+    /// contract account itself are exempt.
+    pub(crate) fn internal_take_lp_fee(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Balance,
+    ) -> Balance {
+        let current_account_id = env::current_account_id();
+        let auto_lp = match self.auto_lp.as_mut() {
+            Some(auto_lp) => auto_lp,
+            None => return 0,
+        };
+        if sender_id == &current_account_id || receiver_id == &current_account_id {
+            return 0;
+        }
+        let fee = bps_of(amount, auto_lp.lp_bps);
+        if fee == 0 || self.internal_charge_fee_token(sender_id, fee) {
+            return 0;
+        }
+        self.internal_move_balance(
+            sender_id,
+            &current_account_id,
+            fee,
+            Some("auto-lp".to_string()),
+        );
+        let liquidity =
+            fee - self.internal_take_insurance_share(fee) - self.internal_take_treasury_share(fee);
+        let auto_lp = self.auto_lp.as_mut().unwrap();
+        auto_lp.accumulated = (auto_lp.accumulated.0 + liquidity).into();
+        self.internal_maybe_add_liquidity();
+        fee
+    }
+
+    fn internal_maybe_add_liquidity(&mut self) {
+        let current_account_id = env::current_account_id();
+        let auto_lp = match self.auto_lp.as_mut() {
+            Some(auto_lp) if !auto_lp.pending && auto_lp.accumulated.0 >= auto_lp.threshold.0 => {
+                auto_lp
+            }
+            _ => return,
+        };
+        let token_amount = auto_lp.accumulated;
+        let near_amount = auto_lp.near_reserve;
+        let dex_id = auto_lp.dex_id.clone();
+        auto_lp.accumulated = 0.into();
+        auto_lp.near_reserve = 0.into();
+        auto_lp.pending = true;
+        if self.token.accounts.get(&dex_id).is_none() {
+            self.internal_register_account(&dex_id);
+        }
+        self.internal_move_balance(
+            &current_account_id,
+            &dex_id,
+            token_amount.0,
+            Some("auto-lp".to_string()),
+        );
+        ext_auto_lp_dex::ext(dex_id)
+            .with_attached_deposit(near_amount.0)
+            .with_static_gas(GAS_FOR_ADD_LIQUIDITY)
+            .add_liquidity(current_account_id.clone(), token_amount)
+            .then(
+                Self::ext(current_account_id)
+                    .with_static_gas(GAS_FOR_ON_LIQUIDITY_ADDED)
+                    .on_liquidity_added(token_amount, near_amount),
+            );
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_auto_lp(accounts(4), 15.into(), 100);
+        testing_env!(context.attached_deposit(ONE_NEAR).build());
+        contract.fund_auto_lp();
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_fees_sent_to_dex_over_threshold() {
+        let (_, mut contract) = setup();
+        contract.ft_transfer(accounts(2), 1_000.into(), None);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 990);
+        assert_eq!(contract.get_auto_lp().unwrap().accumulated.0, 10);
+        contract.ft_transfer(accounts(2), 1_000.into(), None);
+        let auto_lp = contract.get_auto_lp().unwrap();
+        assert!(auto_lp.pending);
+        assert_eq!(auto_lp.accumulated.0, 0);
+        assert_eq!(auto_lp.near_reserve.0, 0);
+        assert_eq!(contract.ft_balance_of(accounts(4)).0, 20);
+    }
+
+    #[test]
+    fn test_failed_add_liquidity_is_rolled_back() {
+        let (mut context, mut contract) = setup();
+        contract.ft_transfer(accounts(2), 2_000.into(), None);
+        testing_env!(
+            context.predecessor_account_id(env::current_account_id()).build(),
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![near_sdk::PromiseResult::Failed],
+        );
+        assert!(!contract.on_liquidity_added(20.into(), ONE_NEAR.into()));
+        let auto_lp = contract.get_auto_lp().unwrap();
+        assert!(!auto_lp.pending);
+        assert_eq!(auto_lp.accumulated.0, 20);
+        assert_eq!(auto_lp.near_reserve.0, ONE_NEAR);
+        assert_eq!(contract.ft_balance_of(accounts(4)).0, 0);
+    }
+}
@@ -0,0 +1,137 @@
+/*!
+Balance alerts for automated treasury management. An account subscribes a contract and method to
+a threshold, and the method is called with `{ "account_id", "threshold", "balance", "above" }`
+whenever the account's balance crosses the threshold, in either direction. Reaching the threshold
+counts as being above it.
+
+Like the incoming hooks, each call is fire-and-forget in its own receipt, so a failing subscriber
+never reverts the balance change. Whoever changes the balance of a subscribed account needs to
+attach `GAS_FOR_BALANCE_ALERT` on top of the usual gas for every alert that fires.
+*/
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+
+pub const GAS_FOR_BALANCE_ALERT: Gas = Gas(5_000_000_000_000);
+/// Most alerts an account can subscribe.
+pub const MAX_BALANCE_ALERTS: usize = 5;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BalanceAlert {
+    pub threshold: U128,
+    pub contract_id: AccountId,
+    pub method_name: String,
+    /// Whether the balance was at or above the threshold at the last change.
+    pub above: bool,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Subscribes `contract_id.method_name` to the caller's balance crossing `threshold`.
+    /// Attached deposit must cover the added storage, the rest is refunded.
+    #[payable]
+    pub fn subscribe_balance_alert(
+        &mut self,
+        threshold: U128,
+        contract_id: AccountId,
+        method_name: String,
+    ) {
+        assert!(!method_name.is_empty(), "The method name can't be empty");
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        let balance = self.token.internal_unwrap_balance_of(&account_id);
+        let mut alerts = self.balance_alerts.get(&account_id).unwrap_or_default();
+        assert!(
+            alerts.len() < MAX_BALANCE_ALERTS,
+            "An account can have at most {} balance alerts",
+            MAX_BALANCE_ALERTS
+        );
+        let initial_storage_usage = env::storage_usage();
+        alerts.push(BalanceAlert {
+            threshold,
+            contract_id,
+            method_name,
+            above: balance >= threshold.0,
+        });
+        self.balance_alerts.insert(&account_id, &alerts);
+        refund_deposit(initial_storage_usage);
+    }
+
+    /// Removes all balance alerts of the caller.
+    #[payable]
+    pub fn clear_balance_alerts(&mut self) {
+        assert_one_yocto();
+        self.internal_record_activity(&env::predecessor_account_id());
+        self.balance_alerts.remove(&env::predecessor_account_id());
+    }
+
+    pub fn get_balance_alerts(&self, account_id: AccountId) -> Vec<BalanceAlert> {
+        self.balance_alerts.get(&account_id).unwrap_or_default()
+    }
+}
+
+impl Contract {
+    /// Fires the alerts of `account_id` whose threshold its balance crossed.
+    pub(crate) fn internal_check_balance_alerts(&mut self, account_id: &AccountId) {
+        let mut alerts = match self.balance_alerts.get(account_id) {
+            Some(alerts) => alerts,
+            None => return,
+        };
+        let balance = self.token.accounts.get(account_id).unwrap_or(0);
+        let mut crossed = false;
+        for alert in alerts.iter_mut() {
+            let above = balance >= alert.threshold.0;
+            if above == alert.above {
+                continue;
+            }
+            alert.above = above;
+            crossed = true;
+            let args = json!({
+                "account_id": account_id,
+                "threshold": alert.threshold,
+                "balance": U128(balance),
+                "above": above,
+            });
+            Promise::new(alert.contract_id.clone()).function_call(
+                alert.method_name.clone(),
+                args.to_string().into_bytes(),
+                0,
+                GAS_FOR_BALANCE_ALERT,
+            );
+        }
+        if crossed {
+            self.balance_alerts.insert(account_id, &alerts);
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, get_created_receipts};
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_alert_fires_on_crossing() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        testing_env!(context
+            .attached_deposit(ONE_NEAR / 100)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        contract.subscribe_balance_alert(100.into(), accounts(3), "on_alert".to_string());
+        assert!(!contract.get_balance_alerts(accounts(2))[0].above);
+
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), 99.into(), None);
+        assert!(!get_created_receipts().iter().any(|receipt| receipt.receiver_id == accounts(3)));
+        contract.ft_transfer(accounts(2), 1.into(), None);
+        assert!(get_created_receipts().iter().any(|receipt| receipt.receiver_id == accounts(3)));
+        assert!(contract.get_balance_alerts(accounts(2))[0].above);
+    }
+}
@@ -0,0 +1,121 @@
+/*!
+Opt-in balance privacy. An account in private mode reads as zero from `ft_balance_of`. Its real
+balance is only returned by `ft_balance_of_authorized`, for viewers the account authorized with an
+ed25519 key, including itself.
+
+View calls have no caller, so a viewer proves who it is by signing
+`"<token contract>:<account_id>:<viewer>"` with its key. The signature works as a standing
+authorization for that viewer until the account removes the viewer's key. Transfers, events and
+the raw contract state still reveal balances to anyone determined enough; this only hides them
+from generic views such as wallets and explorers.
+*/
+use crate::signatures::{ed25519_key_bytes, verify_ed25519};
+use crate::*;
+use near_sdk::json_types::Base64VecU8;
+use near_sdk::PublicKey;
+
+#[near_bindgen]
+impl Contract {
+    #[payable]
+    pub fn set_private_balance(&mut self, enabled: bool) {
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        let initial_storage_usage = env::storage_usage();
+        if enabled {
+            self.private_balances.insert(&account_id);
+        } else {
+            self.private_balances.remove(&account_id);
+        }
+        refund_deposit(initial_storage_usage);
+    }
+
+    pub fn is_private_balance(&self, account_id: AccountId) -> bool {
+        self.private_balances.contains(&account_id)
+    }
+
+    /// Authorizes `viewer_id` to read the caller's balance with signatures by `public_key`.
+    /// Attached deposit must cover the added storage, the rest is refunded.
+    #[payable]
+    pub fn add_balance_viewer(&mut self, viewer_id: AccountId, public_key: PublicKey) {
+        let key = ed25519_key_bytes(&public_key);
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        let initial_storage_usage = env::storage_usage();
+        self.balance_viewers.insert(&(account_id, viewer_id), &key);
+        refund_deposit(initial_storage_usage);
+    }
+
+    #[payable]
+    pub fn remove_balance_viewer(&mut self, viewer_id: AccountId) {
+        assert_one_yocto();
+        self.internal_record_activity(&env::predecessor_account_id());
+        self.balance_viewers.remove(&(env::predecessor_account_id(), viewer_id));
+    }
+
+    /// Returns the real balance of `account_id` if `signature` proves the request comes from an
+    /// authorized viewer.
+    pub fn ft_balance_of_authorized(
+        &self,
+        account_id: AccountId,
+        viewer: AccountId,
+        signature: Base64VecU8,
+    ) -> U128 {
+        let key = self
+            .balance_viewers
+            .get(&(account_id.clone(), viewer.clone()))
+            .expect("The viewer is not authorized");
+        let message = format!("{}:{}:{}", env::current_account_id(), account_id, viewer);
+        assert!(
+            verify_ed25519(&key, message.as_bytes(), &signature.0),
+            "The signature doesn't match the viewer"
+        );
+        self.token.ft_balance_of(account_id)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use ed25519_dalek::{Keypair, SecretKey, Signer};
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+    use std::convert::TryFrom;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn keypair(seed: u8) -> Keypair {
+        let secret = SecretKey::from_bytes(&[seed; 32]).unwrap();
+        let public = (&secret).into();
+        Keypair { secret, public }
+    }
+
+    fn setup() -> Contract {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(ONE_NEAR / 100).build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_private_balance(true);
+        let public_key = [&[0u8][..], &keypair(1).public.to_bytes()].concat();
+        contract.add_balance_viewer(accounts(2), PublicKey::try_from(public_key).unwrap());
+        contract
+    }
+
+    fn sign(seed: u8) -> Base64VecU8 {
+        let message = format!("{}:{}:{}", env::current_account_id(), accounts(1), accounts(2));
+        keypair(seed).sign(message.as_bytes()).to_bytes().to_vec().into()
+    }
+
+    #[test]
+    fn test_private_balance() {
+        let contract = setup();
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 0);
+        let balance = contract.ft_balance_of_authorized(accounts(1), accounts(2), sign(1));
+        assert_eq!(balance.0, TOTAL_SUPPLY);
+    }
+
+    #[test]
+    #[should_panic(expected = "The signature doesn't match the viewer")]
+    fn test_wrong_signature() {
+        let contract = setup();
+        contract.ft_balance_of_authorized(accounts(1), accounts(2), sign(2));
+    }
+}
@@ -0,0 +1,55 @@
+/*!
+Balance reads for light clients. A contract can't produce Merkle proofs of its own state, so
+`get_balance_with_proof` returns the raw storage key and value behind a balance instead, which is
+everything an off-chain prover needs. To verify a balance against a trusted state root:
+
+1. Fetch the proof for the key at the block of the state root, e.g. with the `view_state` RPC
+   query with `include_proof` set, using the key as the prefix.
+2. Check the proof leads from the state root to the value in the contract's storage under the key.
+3. Decode the value as a little-endian `u128`, which must equal the returned balance.
+
+The key is the prefix of the balance map (`"a"`) followed by the Borsh encoding of the account ID,
+i.e. its length as a little-endian `u32` and its bytes. Accounts that aren't registered have no
+entry, so their key is returned with an empty value and a balance of zero, and a proof of absence
+verifies it.
+*/
+use crate::*;
+use near_sdk::json_types::Base64VecU8;
+
+/// Storage prefix of the balance map of the token.
+pub const BALANCES_PREFIX: &[u8] = b"a";
+
+#[near_bindgen]
+impl Contract {
+    /// Returns the balance of `account_id` with the storage key and value it's read from.
+    pub fn get_balance_with_proof(&self, account_id: AccountId) -> (U128, Vec<Base64VecU8>) {
+        let key = [BALANCES_PREFIX, &account_id.try_to_vec().unwrap()].concat();
+        let value = env::storage_read(&key).unwrap_or_default();
+        let balance = self.token.accounts.get(&account_id).unwrap_or(0);
+        (balance.into(), vec![key.into(), value.into()])
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+    use std::convert::TryInto;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_balance_matches_storage() {
+        testing_env!(get_context(accounts(1)).build());
+        let contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        let (balance, proof) = contract.get_balance_with_proof(accounts(1));
+        assert_eq!(balance.0, TOTAL_SUPPLY);
+        assert_eq!(proof[0].0, [&b"a"[..], &[3, 0, 0, 0], b"bob"].concat());
+        assert_eq!(u128::from_le_bytes(proof[1].0.clone().try_into().unwrap()), TOTAL_SUPPLY);
+
+        let (balance, proof) = contract.get_balance_with_proof(accounts(2));
+        assert_eq!(balance.0, 0);
+        assert!(proof[1].0.is_empty());
+    }
+}
@@ -0,0 +1,145 @@
+/*!
+Batched burns for scheduled supply reductions, e.g. buyback-and-burn out of several treasury
+sub-accounts. The owner or a burner burns from many accounts in one call, and the total supply
+goes down once at the end.
+
+Only accounts that opted in with `set_batch_burnable` can be burned from, so the burner role can't
+touch anyone else's tokens. The whole batch is rejected if any burn exceeds the account's unlocked
+balance.
+*/
+use crate::*;
+use std::collections::BTreeMap;
+
+/// Most burns `ft_burn_batch` accepts in one call.
+pub const MAX_BURN_BATCH: usize = 50;
+
+#[near_bindgen]
+impl Contract {
+    pub fn add_burner(&mut self, account_id: AccountId) {
+        self.assert_owner_action();
+        self.burners.insert(&account_id);
+    }
+
+    pub fn remove_burner(&mut self, account_id: AccountId) {
+        self.assert_owner_action();
+        self.burners.remove(&account_id);
+    }
+
+    pub fn get_burners(&self) -> Vec<AccountId> {
+        self.burners.to_vec()
+    }
+
+    /// Allows or forbids the owner and burners to burn the caller's tokens with `ft_burn_batch`.
+    /// Attached deposit must cover the added storage, the rest is refunded.
+    #[payable]
+    pub fn set_batch_burnable(&mut self, enabled: bool) {
+        let account_id = env::predecessor_account_id();
+        self.token.internal_unwrap_balance_of(&account_id);
+        let initial_storage_usage = env::storage_usage();
+        if enabled {
+            self.batch_burnable.insert(&account_id);
+        } else {
+            self.batch_burnable.remove(&account_id);
+        }
+        refund_deposit(initial_storage_usage);
+    }
+
+    pub fn is_batch_burnable(&self, account_id: AccountId) -> bool {
+        self.batch_burnable.contains(&account_id)
+    }
+
+    /// Burns each `(account_id, amount)`. Only callable by the owner or a burner.
+    pub fn ft_burn_batch(&mut self, burns: Vec<(AccountId, U128)>) {
+        let caller_id = env::predecessor_account_id();
+        if !self.burners.contains(&caller_id) {
+            self.assert_owner_action();
+        }
+        assert!(!burns.is_empty(), "The batch is empty");
+        assert!(burns.len() <= MAX_BURN_BATCH, "Too many burns in one batch");
+        self.assert_not_paused();
+        self.assert_supply_not_frozen();
+        let mut totals: BTreeMap<&AccountId, Balance> = BTreeMap::new();
+        for (account_id, amount) in &burns {
+            assert!(amount.0 > 0, "The amount should be a positive number");
+            assert!(
+                self.batch_burnable.contains(account_id),
+                "@{} doesn't allow batch burns",
+                account_id
+            );
+            *totals.entry(account_id).or_default() += amount.0;
+        }
+        for (account_id, total) in totals {
+            self.internal_apply_demurrage(account_id);
+            let balance = self.token.internal_unwrap_balance_of(account_id);
+            assert!(total <= balance, "The balance of @{} is too low", account_id);
+            self.assert_unlocked(account_id, total);
+        }
+        let mut burned = 0;
+        for (account_id, amount) in burns {
+            self.internal_settle_rewards(&account_id);
+            let balance = self.token.internal_unwrap_balance_of(&account_id);
+            self.token.accounts.insert(&account_id, &(balance - amount.0));
+            self.internal_record_balance(&account_id);
+            self.internal_reset_hold_streak(&account_id);
+            FtBurn { owner_id: &account_id, amount: &amount, memo: Some("batch burn") }.emit();
+            burned += amount.0;
+            self.on_tokens_burned(account_id, amount.0);
+        }
+        self.token.total_supply -= burned;
+        self.internal_record_total_supply();
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(ONE_NEAR / 100).build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_batch_burnable(true);
+        for account_id in [accounts(2), accounts(3)] {
+            testing_env!(context
+                .attached_deposit(contract.storage_balance_bounds().min.into())
+                .predecessor_account_id(account_id.clone())
+                .build());
+            contract.storage_deposit(None, None);
+            testing_env!(context.attached_deposit(ONE_NEAR / 100).build());
+            contract.set_batch_burnable(true);
+        }
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), 100.into(), None);
+        testing_env!(context.attached_deposit(0).build());
+        contract.add_burner(accounts(4));
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_burn_batch() {
+        let (_, mut contract) = setup();
+        contract.ft_burn_batch(vec![(accounts(1), 50.into()), (accounts(2), 30.into())]);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, TOTAL_SUPPLY - 150);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 70);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY - 80);
+    }
+
+    #[test]
+    #[should_panic(expected = "The balance of @charlie is too low")]
+    fn test_burn_batch_above_balance() {
+        let (_, mut contract) = setup();
+        contract.ft_burn_batch(vec![(accounts(2), 60.into()), (accounts(2), 60.into())]);
+    }
+
+    #[test]
+    #[should_panic(expected = "@eugene doesn't allow batch burns")]
+    fn test_burn_batch_without_opt_in() {
+        let (_, mut contract) = setup();
+        contract.ft_burn_batch(vec![(accounts(4), 1.into())]);
+    }
+}
@@ -0,0 +1,224 @@
+/*!
+Batched transfers from the caller to several receivers in one call. Each transfer goes through the
+same checks, fees and bookkeeping as `ft_transfer`.
+
+By default every transfer logs its own NEP-141 `ft_transfer` event, like `ft_transfer` does. The
+owner can opt into consolidated events instead, where the whole batch logs a single `ft_transfer`
+event with one data entry per transfer. That's still a valid NEP-141 event, but indexers that
+expect one transfer per event would miss all but the first, so it's off by default. Events of
+fees, forwards and other side effects are logged as usual either way.
+
+`ft_transfer_batch` is all-or-nothing: if any transfer fails, the whole batch is reverted.
+`ft_transfer_batch_lenient` checks each transfer first instead and skips the ones that would fail
+because of the entry itself (amount, receiver, memo, limits, block gap, transfer policy, the
+forwarding destination or the sender's spendable balance), so the others still go through and only
+they log events. Both batches run the same checks, see `internal_check_transfer`. Failures that
+aren't specific to an entry, like a paused contract, still revert the whole batch, and the priority
+fee covers skipped transfers too.
+*/
+use crate::*;
+use near_contract_standards::fungible_token::events::FtTransfer;
+use near_sdk::serde::{Deserialize, Serialize};
+
+/// Most transfers `ft_transfer_batch` accepts in one call.
+pub const MAX_TRANSFER_BATCH: usize = 50;
+
+/// Sender, receiver, amount and memo of a transfer whose event is deferred.
+pub type DeferredTransferEvent = (AccountId, AccountId, U128, Option<String>);
+
+/// Outcome of one transfer of a lenient batch.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TransferResult {
+    pub success: bool,
+    /// Why the transfer was skipped.
+    pub error: Option<String>,
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn set_consolidated_batch_events(&mut self, enabled: bool) {
+        self.assert_owner_action();
+        self.consolidated_batch_events = enabled;
+    }
+
+    pub fn get_consolidated_batch_events(&self) -> bool {
+        self.consolidated_batch_events
+    }
+
+    /// Transfers each `(receiver_id, amount, memo)` from the caller, in order. Requires exactly
+    /// 1 yoctoNEAR for the whole batch, or the sum of the priority fees.
+    #[payable]
+    pub fn ft_transfer_batch(&mut self, transfers: Vec<(AccountId, U128, Option<String>)>) {
+        self.internal_start_batch(&transfers);
+        let sender_id = env::predecessor_account_id();
+        for (receiver_id, amount, memo) in transfers {
+            self.internal_transfer(&sender_id, &receiver_id, amount.0, memo);
+        }
+        self.internal_finish_batch();
+    }
+
+    /// Same as `ft_transfer_batch`, but transfers that would fail are skipped instead of
+    /// reverting the batch. Returns the outcome of each transfer, in order.
+    #[payable]
+    pub fn ft_transfer_batch_lenient(
+        &mut self,
+        transfers: Vec<(AccountId, U128, Option<String>)>,
+    ) -> Vec<TransferResult> {
+        self.internal_start_batch(&transfers);
+        let sender_id = env::predecessor_account_id();
+        let results = transfers
+            .into_iter()
+            .map(|(receiver_id, amount, memo)| {
+                self.internal_apply_demurrage(&sender_id);
+                if let Err(error) =
+                    self.internal_check_transfer(&sender_id, &receiver_id, amount.0, &memo, true)
+                {
+                    return TransferResult { success: false, error: Some(error) };
+                }
+                self.internal_transfer(&sender_id, &receiver_id, amount.0, memo);
+                TransferResult { success: true, error: None }
+            })
+            .collect();
+        self.internal_finish_batch();
+        results
+    }
+}
+
+impl Contract {
+    fn internal_start_batch(&mut self, transfers: &[(AccountId, U128, Option<String>)]) {
+        assert!(!transfers.is_empty(), "The batch is empty");
+        assert!(transfers.len() <= MAX_TRANSFER_BATCH, "Too many transfers in one batch");
+        let amounts: Vec<Balance> = transfers.iter().map(|(_, amount, _)| amount.0).collect();
+        self.assert_priority_fee_attached(&amounts);
+        self.assert_no_transfer_validator();
+        self.assert_no_attestation_gate();
+        if self.consolidated_batch_events {
+            self.deferred_transfer_events = Some(Vec::with_capacity(transfers.len()));
+        }
+    }
+
+    fn internal_finish_batch(&mut self) {
+        if let Some(events) = self.deferred_transfer_events.take() {
+            let events: Vec<FtTransfer> = events
+                .iter()
+                .map(|(old_owner_id, new_owner_id, amount, memo)| FtTransfer {
+                    old_owner_id,
+                    new_owner_id,
+                    amount,
+                    memo: memo.as_deref(),
+                })
+                .collect();
+            FtTransfer::emit_many(&events);
+        }
+    }
+
+    /// Moves `amount` like the standard's `internal_transfer`, but holds back the event while a
+    /// consolidated batch is running.
+    pub(crate) fn internal_transfer_deferring_event(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Balance,
+        memo: Option<String>,
+    ) {
+        match self.deferred_transfer_events.as_mut() {
+            Some(events) => {
+                assert_ne!(sender_id, receiver_id, "Sender and receiver should be different");
+                assert!(amount > 0, "The amount should be a positive number");
+                events.push((sender_id.clone(), receiver_id.clone(), amount.into(), memo));
+                self.token.internal_withdraw(sender_id, amount);
+                self.token.internal_deposit(receiver_id, amount);
+            }
+            None => self.token.internal_transfer(sender_id, receiver_id, amount, memo),
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, get_logs};
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        for account_id in [accounts(2), accounts(3)] {
+            testing_env!(context
+                .attached_deposit(contract.storage_balance_bounds().min.into())
+                .predecessor_account_id(account_id)
+                .build());
+            contract.storage_deposit(None, None);
+        }
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        (context, contract)
+    }
+
+    fn batch() -> Vec<(AccountId, U128, Option<String>)> {
+        (0..10).map(|i| (accounts(2 + i % 2), 10.into(), None)).collect()
+    }
+
+    #[test]
+    fn test_lenient_batch_skips_failures() {
+        let (_, mut contract) = setup();
+        let results = contract.ft_transfer_batch_lenient(vec![
+            (accounts(2), 10.into(), None),
+            (accounts(4), 10.into(), None),
+            (accounts(3), (TOTAL_SUPPLY).into(), None),
+            (accounts(3), 20.into(), None),
+        ]);
+        let successes: Vec<bool> = results.iter().map(|result| result.success).collect();
+        assert_eq!(successes, vec![true, false, false, true]);
+        assert_eq!(results[1].error.as_deref(), Some("The account eugene is not registered"));
+        assert_eq!(get_logs().len(), 2);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 20);
+    }
+
+    #[test]
+    fn test_lenient_batch_skips_block_gap() {
+        let (mut context, mut contract) = setup();
+        contract.set_min_block_gap(5);
+        contract.ft_transfer(accounts(2), 100.into(), None);
+        testing_env!(context.block_index(5).predecessor_account_id(accounts(2)).build());
+        contract.ft_transfer(accounts(3), 10.into(), None);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let results = contract.ft_transfer_batch_lenient(vec![
+            (accounts(2), 10.into(), None),
+            (accounts(3), 10.into(), None),
+        ]);
+        assert_eq!(results[0].error.as_deref(), Some("The receiver sent tokens too recently"));
+        assert!(results[1].success);
+    }
+
+    #[test]
+    fn test_consolidated_events_save_gas() {
+        let (context, mut contract) = setup();
+        let initial_gas = env::used_gas();
+        contract.ft_transfer_batch(batch());
+        let separate_gas = env::used_gas() - initial_gas;
+        assert_eq!(get_logs().len(), 10);
+
+        contract.set_consolidated_batch_events(true);
+        testing_env!(context.build());
+        let initial_gas = env::used_gas();
+        contract.ft_transfer_batch(batch());
+        let consolidated_gas = env::used_gas() - initial_gas;
+        assert_eq!(get_logs().len(), 1);
+        assert!(get_logs()[0].starts_with("EVENT_JSON:{\"standard\":\"nep141\""));
+
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 100);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 100);
+        // Logging costs a base fee per log plus a fee per byte, and the consolidated event saves
+        // the base fee and the envelope of all but one of the events.
+        assert!(
+            consolidated_gas.0 < separate_gas.0,
+            "{} should be below {}",
+            consolidated_gas.0,
+            separate_gas.0
+        );
+    }
+}
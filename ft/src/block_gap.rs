@@ -0,0 +1,149 @@
+/*!
+Protection against sandwich and wash patterns: when enabled, an account can't send tokens within
+`min_block_gap` blocks of receiving them, nor receive within `min_block_gap` blocks of sending.
+Market makers on the owner's list are exempt.
+*/
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the minimum number of blocks between a receive and a send for the same account.
+    /// `1` only forbids round trips within a single block, `0` disables the check.
+    pub fn set_min_block_gap(&mut self, min_block_gap: u64) {
+        self.assert_owner_action();
+        self.min_block_gap = min_block_gap;
+    }
+
+    pub fn get_min_block_gap(&self) -> u64 {
+        self.min_block_gap
+    }
+
+    pub fn add_market_maker(&mut self, account_id: AccountId) {
+        self.assert_owner_action();
+        self.market_makers.insert(&account_id);
+    }
+
+    pub fn remove_market_maker(&mut self, account_id: AccountId) {
+        self.assert_owner_action();
+        self.market_makers.remove(&account_id);
+    }
+
+    pub fn is_market_maker(&self, account_id: AccountId) -> bool {
+        self.market_makers.contains(&account_id)
+    }
+}
+
+impl Contract {
+    /// Checks the gap for both sides of a transfer.
+    pub(crate) fn check_block_gap(
+        &self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+    ) -> Result<(), String> {
+        if self.min_block_gap == 0 {
+            return Ok(());
+        }
+        let block_height = env::block_height();
+        if !self.market_makers.contains(sender_id) {
+            if let Some(last_receive) = self.last_receive_block.get(sender_id) {
+                if block_height < last_receive + self.min_block_gap {
+                    return Err("The sender received tokens too recently".to_string());
+                }
+            }
+        }
+        if !self.market_makers.contains(receiver_id) {
+            if let Some(last_send) = self.last_send_block.get(receiver_id) {
+                if block_height < last_send + self.min_block_gap {
+                    return Err("The receiver sent tokens too recently".to_string());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Records the current block for both sides of a transfer. Nothing is tracked while the gap
+    /// is disabled.
+    pub(crate) fn internal_record_block_gap(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+    ) {
+        if self.min_block_gap == 0 {
+            return;
+        }
+        let block_height = env::block_height();
+        if !self.market_makers.contains(sender_id) {
+            self.last_send_block.insert(sender_id, &block_height);
+        }
+        if !self.market_makers.contains(receiver_id) {
+            self.last_receive_block.insert(receiver_id, &block_height);
+        }
+    }
+
+    /// Adds the storage of the last send and receive blocks for the longest possible account ID
+    /// to the storage required to register an account.
+    pub(crate) fn measure_block_gap_storage_usage(&mut self) {
+        let initial_storage_usage = env::storage_usage();
+        let tmp_account_id = AccountId::new_unchecked("a".repeat(64));
+        self.last_send_block.insert(&tmp_account_id, &0);
+        self.last_receive_block.insert(&tmp_account_id, &0);
+        self.token.account_storage_usage += env::storage_usage() - initial_storage_usage;
+        self.last_send_block.remove(&tmp_account_id);
+        self.last_receive_block.remove(&tmp_account_id);
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_min_block_gap(2);
+        for account_id in [accounts(2), accounts(3)] {
+            testing_env!(context
+                .attached_deposit(contract.storage_balance_bounds().min.into())
+                .predecessor_account_id(account_id)
+                .build());
+            contract.storage_deposit(None, None);
+        }
+        testing_env!(context
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(1))
+            .block_index(10)
+            .build());
+        contract.ft_transfer(accounts(2), 100.into(), None);
+        (context, contract)
+    }
+
+    #[test]
+    #[should_panic(expected = "The sender received tokens too recently")]
+    fn test_send_right_after_receive() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.predecessor_account_id(accounts(2)).block_index(11).build());
+        contract.ft_transfer(accounts(3), 10.into(), None);
+    }
+
+    #[test]
+    fn test_send_after_gap() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.predecessor_account_id(accounts(2)).block_index(12).build());
+        contract.ft_transfer(accounts(3), 10.into(), None);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 10);
+    }
+
+    #[test]
+    fn test_market_maker_is_exempt() {
+        let (mut context, mut contract) = setup();
+        contract.add_market_maker(accounts(2));
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.ft_transfer(accounts(3), 10.into(), None);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 10);
+    }
+}
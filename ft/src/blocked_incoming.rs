@@ -0,0 +1,142 @@
+/*!
+Self-service opt-out of receiving the token, e.g. for exchanges that don't list it or accounts
+targeted by dusting. Transfers to an account that blocks incoming tokens fail, unless they come
+from the owner, who can still move tokens to it for forced or regulatory transfers.
+
+Accounts that still want the token can instead set a minimum they accept, so transfers below it
+fail, which makes spraying tiny amounts pointless. The owner bypasses the minimum as well.
+*/
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// Refuses or accepts incoming transfers to the caller. Attached deposit must cover the added
+    /// storage, the rest is refunded.
+    #[payable]
+    pub fn block_incoming(&mut self, blocked: bool) {
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        self.token.internal_unwrap_balance_of(&account_id);
+        let initial_storage_usage = env::storage_usage();
+        if blocked {
+            self.blocked_incoming.insert(&account_id);
+        } else {
+            self.blocked_incoming.remove(&account_id);
+        }
+        refund_deposit(initial_storage_usage);
+    }
+
+    pub fn is_incoming_blocked(&self, account_id: AccountId) -> bool {
+        self.blocked_incoming.contains(&account_id)
+    }
+
+    /// Refuses incoming transfers below `amount` to the caller. Zero accepts any amount. Attached
+    /// deposit must cover the added storage, the rest is refunded.
+    #[payable]
+    pub fn set_min_incoming(&mut self, amount: U128) {
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        self.token.internal_unwrap_balance_of(&account_id);
+        let initial_storage_usage = env::storage_usage();
+        if amount.0 == 0 {
+            self.min_incoming.remove(&account_id);
+        } else {
+            self.min_incoming.insert(&account_id, &amount.0);
+        }
+        refund_deposit(initial_storage_usage);
+    }
+
+    pub fn get_min_incoming(&self, account_id: AccountId) -> U128 {
+        self.min_incoming.get(&account_id).unwrap_or(0).into()
+    }
+}
+
+impl Contract {
+    pub(crate) fn check_incoming_allowed(
+        &self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+    ) -> Result<(), String> {
+        if sender_id != &self.owner_id && self.blocked_incoming.contains(receiver_id) {
+            return Err(format!("The receiver {} doesn't accept incoming transfers", receiver_id));
+        }
+        Ok(())
+    }
+
+    /// Fails if a transfer of `amount` to `receiver_id` is below the minimum it accepts.
+    pub(crate) fn check_min_incoming(
+        &self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Balance,
+    ) -> Result<(), String> {
+        if sender_id != &self.owner_id && amount < self.min_incoming.get(receiver_id).unwrap_or(0) {
+            return Err(format!(
+                "The receiver {} doesn't accept transfers below its minimum",
+                receiver_id
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        for account_id in [accounts(2), accounts(3)] {
+            testing_env!(context
+                .attached_deposit(contract.storage_balance_bounds().min.into())
+                .predecessor_account_id(account_id)
+                .build());
+            contract.storage_deposit(None, None);
+        }
+        testing_env!(context.attached_deposit(ONE_NEAR / 100).build());
+        contract.block_incoming(true);
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), 100.into(), None);
+        (context, contract)
+    }
+
+    #[test]
+    fn test_owner_overrides_block() {
+        let (_, mut contract) = setup();
+        assert!(contract.is_incoming_blocked(accounts(3)));
+        contract.ft_transfer(accounts(3), 50.into(), None);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 50);
+    }
+
+    #[test]
+    #[should_panic(expected = "The receiver danny doesn't accept incoming transfers")]
+    fn test_blocked_receiver() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.ft_transfer(accounts(3), 10.into(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "The receiver charlie doesn't accept transfers below its minimum")]
+    fn test_below_min_incoming() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .attached_deposit(ONE_NEAR / 100)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.set_min_incoming(10.into());
+        assert_eq!(contract.get_min_incoming(accounts(2)).0, 10);
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), 1.into(), None);
+        contract.ft_transfer(accounts(3), 50.into(), None);
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.ft_transfer(accounts(2), 10.into(), None);
+        contract.ft_transfer(accounts(2), 9.into(), None);
+    }
+}
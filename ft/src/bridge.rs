@@ -0,0 +1,150 @@
+/*!
+Contract side of a lock-and-mint bridge. `bridge_lock` moves the caller's tokens to the contract
+account and emits a `bridge_lock` event, from which relayers mint the same amount on the target
+chain. When tokens come back, the bridge account set by the owner releases them with
+`bridge_unlock`. Each source transaction can only be released once.
+
+Locked tokens stay in the total supply and are held by the contract account on behalf of the
+bridge, so bridging works even once the supply is frozen.
+*/
+use crate::events::emit_event;
+use crate::*;
+use near_sdk::serde_json::json;
+
+/// Longest accepted target chain name, target address or source transaction ID.
+pub const MAX_BRIDGE_FIELD_LEN: usize = 256;
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the account allowed to release bridged tokens. Bridging is disabled if not set.
+    pub fn set_bridge(&mut self, bridge_id: Option<AccountId>) {
+        self.assert_owner_action();
+        self.bridge_id = bridge_id;
+    }
+
+    pub fn get_bridge(&self) -> Option<AccountId> {
+        self.bridge_id.clone()
+    }
+
+    /// Total amount locked for the other chains.
+    pub fn get_bridge_locked(&self) -> U128 {
+        self.bridge_locked.into()
+    }
+
+    /// Locks `amount` of the caller's tokens to be minted to `target_address` on `target_chain`.
+    #[payable]
+    pub fn bridge_lock(&mut self, amount: U128, target_chain: String, target_address: String) {
+        assert_one_yocto();
+        self.assert_not_paused();
+        assert!(self.bridge_id.is_some(), "The bridge is not configured");
+        assert!(amount.0 > 0, "The amount should be a positive number");
+        assert_bridge_field(&target_chain);
+        assert_bridge_field(&target_address);
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        self.internal_register_contract_account();
+        self.internal_move_balance(
+            &account_id,
+            &env::current_account_id(),
+            amount.0,
+            Some("bridge lock".to_string()),
+        );
+        self.bridge_locked += amount.0;
+        emit_event(
+            "bridge_lock",
+            json!({
+                "account_id": account_id,
+                "amount": amount,
+                "target_chain": target_chain,
+                "target_address": target_address,
+            }),
+        );
+    }
+
+    /// Releases `amount` of the locked tokens to `account_id` for the transaction `source_tx` on
+    /// the other chain. Only callable by the bridge, which pays for the storage of the
+    /// processed transaction.
+    #[payable]
+    pub fn bridge_unlock(&mut self, account_id: AccountId, amount: U128, source_tx: String) {
+        self.assert_not_paused();
+        assert_eq!(
+            Some(env::predecessor_account_id()),
+            self.bridge_id,
+            "Only the bridge can call this method"
+        );
+        assert!(amount.0 > 0, "The amount should be a positive number");
+        assert!(amount.0 <= self.bridge_locked, "The amount exceeds the locked tokens");
+        assert_bridge_field(&source_tx);
+        let initial_storage_usage = env::storage_usage();
+        assert!(
+            self.processed_bridge_txs.insert(&source_tx),
+            "The source transaction was already processed"
+        );
+        self.bridge_locked -= amount.0;
+        self.internal_move_balance(
+            &env::current_account_id(),
+            &account_id,
+            amount.0,
+            Some("bridge unlock".to_string()),
+        );
+        emit_event(
+            "bridge_unlock",
+            json!({ "account_id": account_id, "amount": amount, "source_tx": source_tx }),
+        );
+        refund_deposit(initial_storage_usage);
+    }
+
+    pub fn is_bridge_tx_processed(&self, source_tx: String) -> bool {
+        self.processed_bridge_txs.contains(&source_tx)
+    }
+}
+
+fn assert_bridge_field(value: &str) {
+    assert!(
+        !value.is_empty() && value.len() <= MAX_BRIDGE_FIELD_LEN,
+        "Bridge fields should be between 1 and {} bytes long",
+        MAX_BRIDGE_FIELD_LEN
+    );
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_bridge(Some(accounts(3)));
+        testing_env!(context.attached_deposit(1).build());
+        contract.bridge_lock(1_000.into(), "ethereum".to_string(), "0xabc".to_string());
+        testing_env!(context
+            .attached_deposit(ONE_NEAR / 100)
+            .predecessor_account_id(accounts(3))
+            .build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_lock_and_unlock() {
+        let (_, mut contract) = setup();
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, TOTAL_SUPPLY - 1_000);
+        assert_eq!(contract.get_bridge_locked().0, 1_000);
+        contract.bridge_unlock(accounts(1), 400.into(), "0x01".to_string());
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, TOTAL_SUPPLY - 600);
+        assert_eq!(contract.get_bridge_locked().0, 600);
+        assert!(contract.is_bridge_tx_processed("0x01".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "The source transaction was already processed")]
+    fn test_replayed_unlock() {
+        let (_, mut contract) = setup();
+        contract.bridge_unlock(accounts(1), 400.into(), "0x01".to_string());
+        contract.bridge_unlock(accounts(1), 400.into(), "0x01".to_string());
+    }
+}
@@ -0,0 +1,127 @@
+/*!
+Resumable bulk operations over all holders, for operations too large for a single transaction's
+gas. The owner drives an operation in chunks: each chunk processes up to `limit` holders starting
+at `from_index` and returns the index the next chunk starts at, or zero once the whole set is done.
+
+The progress of every operation is kept in a cursor, and a chunk has to start exactly where the
+previous one stopped, so a chunk can't be applied twice or skipped. Unregistering holders reorders
+the set, so a run that overlaps unregistrations may miss a few accounts; they are picked up by the
+next run.
+
+`distribute_rewards_chunk` pays out the pending rewards of every holder.
+*/
+use crate::events::emit_event;
+use crate::*;
+use near_sdk::serde_json::json;
+
+/// Most holders a single chunk processes.
+pub const MAX_CHUNK_LIMIT: u64 = 100;
+
+const DISTRIBUTE_REWARDS: &str = "distribute_rewards";
+
+#[near_bindgen]
+impl Contract {
+    /// Pays out the pending rewards of up to `limit` holders from `from_index`. Returns the index
+    /// of the next chunk, zero once all holders were paid.
+    pub fn distribute_rewards_chunk(&mut self, from_index: u64, limit: u64) -> u64 {
+        self.assert_owner();
+        let account_ids = self.internal_bulk_chunk(DISTRIBUTE_REWARDS, from_index, limit);
+        for account_id in account_ids.iter() {
+            self.internal_claim_rewards(account_id, account_id);
+        }
+        self.internal_advance_bulk_cursor(DISTRIBUTE_REWARDS, from_index, account_ids.len() as u64)
+    }
+
+    /// Index the next chunk of `operation` has to start at.
+    pub fn get_bulk_cursor(&self, operation: String) -> u64 {
+        self.bulk_cursors.get(&operation).unwrap_or(0)
+    }
+}
+
+impl Contract {
+    /// The holders a chunk of `operation` from `from_index` processes.
+    pub(crate) fn internal_bulk_chunk(
+        &self,
+        operation: &str,
+        from_index: u64,
+        limit: u64,
+    ) -> Vec<AccountId> {
+        let cursor = self.bulk_cursors.get(&operation.to_string()).unwrap_or(0);
+        assert_eq!(from_index, cursor, "The next chunk of {} starts at {}", operation, cursor);
+        assert!(
+            limit > 0 && limit <= MAX_CHUNK_LIMIT,
+            "The limit should be between 1 and {}",
+            MAX_CHUNK_LIMIT
+        );
+        self.holders.iter().skip(from_index as usize).take(limit as usize).collect()
+    }
+
+    /// Moves the cursor of `operation` past the `processed` holders, or resets it once the set is
+    /// done. Returns the new cursor.
+    pub(crate) fn internal_advance_bulk_cursor(
+        &mut self,
+        operation: &str,
+        from_index: u64,
+        processed: u64,
+    ) -> u64 {
+        let operation = operation.to_string();
+        let next_index = from_index + processed;
+        if next_index >= self.holders.len() {
+            self.bulk_cursors.remove(&operation);
+            emit_event("bulk_operation_completed", json!({ "operation": operation }));
+            0
+        } else {
+            self.bulk_cursors.insert(&operation, &next_index);
+            next_index
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        for account_id in [accounts(2), accounts(3)] {
+            testing_env!(context
+                .attached_deposit(contract.storage_balance_bounds().min.into())
+                .predecessor_account_id(account_id)
+                .build());
+            contract.storage_deposit(None, None);
+        }
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), (TOTAL_SUPPLY / 2).into(), None);
+        contract.distribute_rewards(1_000.into());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_distribute_in_chunks() {
+        let (_, mut contract) = setup();
+        let pending = contract.pending_rewards_of(accounts(2)).0;
+        assert!(pending > 0);
+        let mut next_index = contract.distribute_rewards_chunk(0, 1);
+        assert_eq!(contract.get_bulk_cursor(DISTRIBUTE_REWARDS.to_string()), next_index);
+        while next_index != 0 {
+            next_index = contract.distribute_rewards_chunk(next_index, 1);
+        }
+        assert_eq!(contract.pending_rewards_of(accounts(1)).0, 0);
+        assert_eq!(contract.pending_rewards_of(accounts(2)).0, 0);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY / 2 + pending);
+    }
+
+    #[test]
+    #[should_panic(expected = "The next chunk of distribute_rewards starts at 1")]
+    fn test_replayed_chunk() {
+        let (_, mut contract) = setup();
+        contract.distribute_rewards_chunk(0, 1);
+        contract.distribute_rewards_chunk(0, 1);
+    }
+}
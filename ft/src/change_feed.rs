@@ -0,0 +1,89 @@
+/*!
+A change feed for indexers. Every recorded change bumps `state_version` and is logged with its
+type, so an indexer can poll `changes_since` with the last version it saw and re-read only what
+changed, instead of rescanning the whole state.
+
+The change types are `balance:<account_id>` after any balance change, `account_registered:<id>`
+and `account_closed:<id>`, and `config` after every owner action. Only the last
+`CHANGE_LOG_CAPACITY` changes are kept, so an indexer that falls further behind has to rescan.
+*/
+use crate::*;
+
+/// Changes kept in the log.
+pub const CHANGE_LOG_CAPACITY: u64 = 500;
+/// Most changes `changes_since` returns at once.
+const MAX_CHANGES_LIMIT: u64 = 100;
+
+#[near_bindgen]
+impl Contract {
+    /// Version of the last recorded change, zero before the first one.
+    pub fn get_state_version(&self) -> u64 {
+        self.state_version
+    }
+
+    /// Up to `limit` changes after `version`, oldest first, as `(version, change_type)`. Panics
+    /// if some of them were already evicted from the log.
+    pub fn changes_since(&self, version: u64, limit: u64) -> Vec<(u64, String)> {
+        let oldest = self.state_version.saturating_sub(CHANGE_LOG_CAPACITY) + 1;
+        assert!(version + 1 >= oldest, "The log doesn't go back that far, rescan the state");
+        let limit = limit.min(MAX_CHANGES_LIMIT);
+        (version + 1..=self.state_version)
+            .take(limit as usize)
+            .filter_map(|version| self.change_log.get(&version).map(|change| (version, change)))
+            .collect()
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_record_change(&mut self, change_type: String) {
+        self.state_version += 1;
+        self.change_log.insert(&self.state_version, &change_type);
+        if self.state_version > CHANGE_LOG_CAPACITY {
+            self.change_log.remove(&(self.state_version - CHANGE_LOG_CAPACITY));
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_changes_since() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        let version = contract.get_state_version();
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), 10.into(), None);
+        assert_eq!(
+            contract.changes_since(version, 10),
+            vec![
+                (version + 1, "balance:bob".to_string()),
+                (version + 2, "balance:charlie".to_string())
+            ]
+        );
+        assert_eq!(contract.changes_since(version, 1).len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "The log doesn't go back that far")]
+    fn test_evicted_changes() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        for _ in 0..CHANGE_LOG_CAPACITY {
+            contract.internal_record_change("config".to_string());
+        }
+        contract.changes_since(0, 10);
+    }
+}
@@ -0,0 +1,154 @@
+/*!
+Automatic pause on abnormal outflow. The breaker sums the amounts transferred within a window and
+pauses the contract as soon as the sum exceeds the threshold, without waiting for a human to react.
+
+The window is a fixed one that starts with the first transfer after the previous window expired.
+The transfer that trips the breaker still goes through, since panicking would also revert the
+pause; every transfer after it is rejected until the breaker is reset. Resetting only lifts a
+pause the breaker caused, a manual pause stays until the owner unpauses.
+*/
+use crate::events::emit_event;
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CircuitBreaker {
+    /// Outflow within a single window that trips the breaker.
+    pub threshold: U128,
+    /// Window length in seconds.
+    pub window: u64,
+    /// Timestamp (in seconds) at which the current window started.
+    pub window_start: u64,
+    /// Amount transferred since the current window started.
+    pub window_outflow: U128,
+    /// Whether the breaker paused the contract and wasn't reset since.
+    pub tripped: bool,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Enables the breaker, or disables it when `threshold` is zero.
+    pub fn set_circuit_breaker(&mut self, threshold: U128, window_seconds: u64) {
+        self.assert_owner_action();
+        if threshold.0 == 0 {
+            self.circuit_breaker = None;
+            return;
+        }
+        assert!(window_seconds > 0, "The window should be a positive number");
+        self.circuit_breaker = Some(CircuitBreaker {
+            threshold,
+            window: window_seconds,
+            window_start: current_timestamp_sec(),
+            window_outflow: 0.into(),
+            tripped: false,
+        });
+    }
+
+    /// Clears the outflow counted in the current window and lifts the pause if the breaker caused
+    /// it.
+    pub fn reset_circuit_breaker(&mut self) {
+        self.assert_owner_or_guardian();
+        let breaker = self.circuit_breaker.as_mut().expect("No circuit breaker is configured");
+        breaker.window_start = current_timestamp_sec();
+        breaker.window_outflow = 0.into();
+        if breaker.tripped {
+            breaker.tripped = false;
+            self.paused = false;
+        }
+    }
+
+    pub fn get_circuit_breaker(&self) -> Option<CircuitBreaker> {
+        self.circuit_breaker.clone()
+    }
+}
+
+impl Contract {
+    /// Adds `amount` to the outflow and pauses the contract if the threshold is exceeded.
+    pub(crate) fn internal_record_outflow(&mut self, amount: Balance) {
+        let breaker = match self.circuit_breaker.as_mut() {
+            Some(breaker) => breaker,
+            None => return,
+        };
+        let now = current_timestamp_sec();
+        if now >= breaker.window_start + breaker.window {
+            breaker.window_start = now;
+            breaker.window_outflow = 0.into();
+        }
+        breaker.window_outflow = (breaker.window_outflow.0 + amount).into();
+        if breaker.window_outflow.0 > breaker.threshold.0 && !self.paused {
+            breaker.tripped = true;
+            self.paused = true;
+            emit_event(
+                "circuit_breaker_tripped",
+                json!({
+                    "threshold": breaker.threshold,
+                    "window": breaker.window,
+                    "outflow": breaker.window_outflow,
+                }),
+            );
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_circuit_breaker(100.into(), 60);
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_breaker_trips_and_resets() {
+        let (mut context, mut contract) = setup();
+        contract.ft_transfer(accounts(2), 60.into(), None);
+        assert!(!contract.is_paused());
+        contract.ft_transfer(accounts(2), 60.into(), None);
+        assert!(contract.is_paused());
+        testing_env!(context.attached_deposit(0).build());
+        contract.reset_circuit_breaker();
+        assert!(!contract.is_paused());
+    }
+
+    #[test]
+    fn test_reset_keeps_manual_pause() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.attached_deposit(0).build());
+        contract.pause();
+        contract.reset_circuit_breaker();
+        assert!(contract.is_paused());
+    }
+
+    #[test]
+    fn test_window_expires() {
+        let (mut context, mut contract) = setup();
+        contract.ft_transfer(accounts(2), 60.into(), None);
+        testing_env!(context.block_timestamp(60 * NANOS).build());
+        contract.ft_transfer(accounts(2), 60.into(), None);
+        assert!(!contract.is_paused());
+    }
+
+    #[test]
+    #[should_panic(expected = "The contract is paused")]
+    fn test_transfer_after_trip() {
+        let (_, mut contract) = setup();
+        contract.ft_transfer(accounts(2), 101.into(), None);
+        contract.ft_transfer(accounts(2), 1.into(), None);
+    }
+}
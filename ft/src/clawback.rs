@@ -0,0 +1,111 @@
+/*!
+Emergency clawback for the launch period. Within the clawback window set at initialization, the
+owner can take tokens back from any account, e.g. after a bug in a launch distribution. Once the
+window has passed the power is gone for good, and the window can't be changed after
+initialization.
+*/
+use crate::events::emit_event;
+use crate::*;
+use near_sdk::serde_json::json;
+
+/// Longest allowed clawback window, so the power can't be kept indefinitely.
+pub const MAX_CLAWBACK_WINDOW_SEC: u64 = 30 * 24 * 60 * 60;
+
+#[near_bindgen]
+impl Contract {
+    /// Moves `amount` from `from` to the owner, or to the treasury while the owner is the
+    /// multisig contract account. Only possible within the clawback window.
+    pub fn emergency_clawback(&mut self, from: AccountId, amount: U128, reason: String) {
+        self.assert_owner_action();
+        assert!(
+            current_timestamp_sec() < self.deployed_at + self.clawback_window,
+            "The clawback window has passed"
+        );
+        assert!(
+            from != env::current_account_id(),
+            "Use recover_own_tokens for the contract account"
+        );
+        assert!(amount.0 > 0, "The amount should be a positive number");
+        let owner_id = self.owner_id.clone();
+        if owner_id == env::current_account_id() {
+            self.internal_register_contract_account();
+            self.treasury_balance += amount.0;
+        }
+        self.internal_move_balance(&from, &owner_id, amount.0, Some("clawback".to_string()));
+        emit_event(
+            "emergency_clawback",
+            json!({ "account_id": from, "amount": amount, "reason": reason }),
+        );
+    }
+
+    /// Timestamp (in seconds) until which the owner can claw back tokens, if ever.
+    pub fn get_clawback_deadline(&self) -> Option<u64> {
+        if self.clawback_window == 0 {
+            return None;
+        }
+        Some(self.deployed_at + self.clawback_window)
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_set_clawback_window(&mut self, clawback_window: u64) {
+        assert!(
+            clawback_window <= MAX_CLAWBACK_WINDOW_SEC,
+            "The clawback window can't exceed {} seconds",
+            MAX_CLAWBACK_WINDOW_SEC
+        );
+        self.clawback_window = clawback_window;
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_contract_standards::fungible_token::metadata::FT_METADATA_SPEC;
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let metadata = FungibleTokenMetadata {
+            spec: FT_METADATA_SPEC.to_string(),
+            name: "HealthGo".to_string(),
+            symbol: "HGT".to_string(),
+            icon: None,
+            reference: None,
+            reference_hash: None,
+            decimals: 24,
+        };
+        let mut contract =
+            Contract::new(accounts(1), TOTAL_SUPPLY.into(), metadata, None, Some(100));
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), 1_000.into(), None);
+        (context, contract)
+    }
+
+    #[test]
+    fn test_clawback_within_window() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.block_timestamp(99 * NANOS).build());
+        contract.emergency_clawback(accounts(2), 600.into(), "bad airdrop".to_string());
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 400);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, TOTAL_SUPPLY - 400);
+        assert_eq!(contract.get_clawback_deadline(), Some(100));
+    }
+
+    #[test]
+    #[should_panic(expected = "The clawback window has passed")]
+    fn test_clawback_after_window() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.block_timestamp(100 * NANOS).build());
+        contract.emergency_clawback(accounts(2), 600.into(), "bad airdrop".to_string());
+    }
+}
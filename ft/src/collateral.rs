@@ -0,0 +1,170 @@
+/*!
+In-place collateral for lending integrations. The owner registers lending contracts, and a
+registered lending contract can lock part of a borrower's balance as collateral without moving it.
+Locked collateral can't be sent or burned, like time-locked tokens, and becomes spendable again as
+soon as the lending contract that locked it releases it. Each lending contract can only release
+the collateral it locked itself.
+
+Borrowers can't unregister while any of their collateral is locked. Collateral doesn't decay under
+demurrage, and `ft_transfer_call` refunds out of the borrower's balance stop short of it.
+*/
+use crate::events::emit_event;
+use crate::*;
+use near_sdk::serde_json::json;
+
+#[near_bindgen]
+impl Contract {
+    pub fn add_lending_contract(&mut self, contract_id: AccountId) {
+        self.assert_owner_action();
+        self.lending_contracts.insert(&contract_id);
+    }
+
+    /// Stops `contract_id` from locking more collateral. It can still release what it locked.
+    pub fn remove_lending_contract(&mut self, contract_id: AccountId) {
+        self.assert_owner_action();
+        self.lending_contracts.remove(&contract_id);
+    }
+
+    pub fn is_lending_contract(&self, contract_id: AccountId) -> bool {
+        self.lending_contracts.contains(&contract_id)
+    }
+
+    /// Locks `amount` of `account_id`'s spendable balance as collateral of the calling lending
+    /// contract. Attached deposit must cover the added storage, the rest is refunded.
+    #[payable]
+    pub fn lock_collateral(&mut self, account_id: AccountId, amount: U128) {
+        let lender_id = env::predecessor_account_id();
+        assert!(
+            self.lending_contracts.contains(&lender_id),
+            "Only a lending contract can lock collateral"
+        );
+        assert!(amount.0 > 0, "The amount should be a positive number");
+        self.token.internal_unwrap_balance_of(&account_id);
+        self.internal_apply_demurrage(&account_id);
+        let unlocked = self.internal_unlocked_balance(&account_id);
+        assert!(amount.0 <= unlocked, "The amount exceeds the unlocked balance of {}", unlocked);
+        let initial_storage_usage = env::storage_usage();
+        let key = (account_id.clone(), lender_id.clone());
+        let locked = self.collateral_locks.get(&key).unwrap_or(0);
+        self.collateral_locks.insert(&key, &(locked + amount.0));
+        let total = self.internal_collateral_locked(&account_id);
+        self.collateral_locked.insert(&account_id, &(total + amount.0));
+        refund_deposit(initial_storage_usage);
+        emit_event(
+            "collateral_locked",
+            json!({ "account_id": account_id, "lender_id": lender_id, "amount": amount }),
+        );
+    }
+
+    /// Releases `amount` of the collateral the calling lending contract locked on `account_id`.
+    pub fn release_collateral(&mut self, account_id: AccountId, amount: U128) {
+        let lender_id = env::predecessor_account_id();
+        let key = (account_id.clone(), lender_id.clone());
+        let locked = self.collateral_locks.get(&key).unwrap_or(0);
+        assert!(amount.0 > 0 && amount.0 <= locked, "The locked collateral is too low");
+        if locked == amount.0 {
+            self.collateral_locks.remove(&key);
+        } else {
+            self.collateral_locks.insert(&key, &(locked - amount.0));
+        }
+        let total = self.internal_collateral_locked(&account_id) - amount.0;
+        if total == 0 {
+            self.collateral_locked.remove(&account_id);
+        } else {
+            self.collateral_locked.insert(&account_id, &total);
+        }
+        emit_event(
+            "collateral_released",
+            json!({ "account_id": account_id, "lender_id": lender_id, "amount": amount }),
+        );
+    }
+
+    /// Collateral `lender_id` locked on `account_id`.
+    pub fn collateral_of(&self, account_id: AccountId, lender_id: AccountId) -> U128 {
+        self.collateral_locks.get(&(account_id, lender_id)).unwrap_or(0).into()
+    }
+
+    /// Collateral locked on `account_id` by all lending contracts.
+    pub fn total_collateral_of(&self, account_id: AccountId) -> U128 {
+        self.internal_collateral_locked(&account_id).into()
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_collateral_locked(&self, account_id: &AccountId) -> Balance {
+        self.collateral_locked.get(account_id).unwrap_or(0)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.add_lending_contract(accounts(4));
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), 100.into(), None);
+        testing_env!(context
+            .attached_deposit(ONE_NEAR / 100)
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.lock_collateral(accounts(2), 60.into());
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(2)).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_released_collateral_is_spendable() {
+        let (mut context, mut contract) = setup();
+        contract.ft_transfer(accounts(1), 40.into(), None);
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.release_collateral(accounts(2), 60.into());
+        assert_eq!(contract.total_collateral_of(accounts(2)).0, 0);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.ft_transfer(accounts(1), 60.into(), None);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "The amount exceeds the unlocked balance of 40")]
+    fn test_locked_collateral_is_not_spendable() {
+        let (_, mut contract) = setup();
+        contract.ft_transfer(accounts(1), 41.into(), None);
+    }
+
+    #[test]
+    fn test_refund_stops_at_collateral() {
+        let (mut context, mut contract) = setup();
+        testing_env!(
+            context.predecessor_account_id(env::current_account_id()).build(),
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![near_sdk::PromiseResult::Failed],
+        );
+        let used = contract.ft_resolve_transfer(accounts(1), accounts(2), 50.into());
+        assert_eq!(used.0, 10);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 60);
+        assert_eq!(contract.total_collateral_of(accounts(2)).0, 60);
+    }
+
+    #[test]
+    #[should_panic(expected = "The amount exceeds the unlocked balance of 40")]
+    fn test_lock_more_than_balance() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.lock_collateral(accounts(2), 41.into());
+    }
+}
@@ -0,0 +1,137 @@
+/*!
+Commit-reveal transfers against front-running of large transfers (e.g. OTC settlements). The
+sender first commits to the `sha256` of the transfer, which hides its details, and reveals it in a
+later block to execute it. Unrevealed commitments expire after the commitment TTL set by the
+owner.
+
+The commitment is `sha256("<receiver_id>:<amount>:<nonce>:" ++ salt)`, with the amount in its
+smallest unit and the salt as raw bytes. A commitment is used up by its reveal, so the nonce lets a
+sender commit to the same transfer again.
+*/
+use crate::*;
+use near_sdk::json_types::Base64VecU8;
+
+pub const DEFAULT_COMMITMENT_TTL_SEC: u64 = 24 * 60 * 60;
+
+#[near_bindgen]
+impl Contract {
+    pub fn set_commitment_ttl(&mut self, ttl: u64) {
+        self.assert_owner_action();
+        assert!(ttl > 0, "The TTL should be a positive number");
+        self.commitment_ttl = ttl;
+    }
+
+    pub fn get_commitment_ttl(&self) -> u64 {
+        self.commitment_ttl
+    }
+
+    /// Commits the caller to a transfer. Attached deposit must cover the storage of the
+    /// commitment, the rest is refunded.
+    #[payable]
+    pub fn commit_transfer(&mut self, commitment: Base64VecU8) {
+        assert_eq!(commitment.0.len(), 32, "The commitment should be a sha256 hash");
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        self.token.internal_unwrap_balance_of(&account_id);
+        let initial_storage_usage = env::storage_usage();
+        let expires_at = current_timestamp_sec() + self.commitment_ttl;
+        self.transfer_commitments
+            .insert(&(account_id, commitment.0), &(env::block_height(), expires_at));
+        refund_deposit(initial_storage_usage);
+    }
+
+    /// Executes a transfer the caller committed to in an earlier block.
+    #[payable]
+    pub fn reveal_transfer(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        nonce: u64,
+        salt: Base64VecU8,
+    ) {
+        self.assert_priority_fee_attached(&[amount.0]);
+        self.assert_no_transfer_validator();
+        self.assert_no_attestation_gate();
+        let sender_id = env::predecessor_account_id();
+        let commitment = transfer_commitment(&receiver_id, amount.0, nonce, &salt.0);
+        let key = (sender_id.clone(), commitment);
+        let (block_height, expires_at) =
+            self.transfer_commitments.remove(&key).expect("No matching commitment");
+        assert!(env::block_height() > block_height, "Reveal the transfer in a later block");
+        assert!(current_timestamp_sec() < expires_at, "The commitment has expired");
+        self.internal_transfer(&sender_id, &receiver_id, amount.0, None);
+    }
+
+    /// Whether `account_id` has an unexpired commitment.
+    pub fn has_transfer_commitment(&self, account_id: AccountId, commitment: Base64VecU8) -> bool {
+        matches!(
+            self.transfer_commitments.get(&(account_id, commitment.0)),
+            Some((_, expires_at)) if expires_at > current_timestamp_sec()
+        )
+    }
+}
+
+fn transfer_commitment(
+    receiver_id: &AccountId,
+    amount: Balance,
+    nonce: u64,
+    salt: &[u8],
+) -> Vec<u8> {
+    let preimage = format!("{}:{}:{}:", receiver_id, amount, nonce);
+    env::sha256(&[preimage.as_bytes(), salt].concat())
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context
+            .attached_deposit(ONE_NEAR / 100)
+            .predecessor_account_id(accounts(1))
+            .block_index(10)
+            .build());
+        let commitment = transfer_commitment(&accounts(2), 1_000, 0, b"salt");
+        contract.commit_transfer(commitment.into());
+        testing_env!(context.attached_deposit(1).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_reveal_transfer() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.block_index(11).build());
+        contract.reveal_transfer(accounts(2), 1_000.into(), 0, b"salt".to_vec().into());
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 1_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "Reveal the transfer in a later block")]
+    fn test_reveal_in_same_block() {
+        let (_, mut contract) = setup();
+        contract.reveal_transfer(accounts(2), 1_000.into(), 0, b"salt".to_vec().into());
+    }
+
+    #[test]
+    #[should_panic(expected = "The commitment has expired")]
+    fn test_reveal_expired() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .block_index(11)
+            .block_timestamp(DEFAULT_COMMITMENT_TTL_SEC * NANOS)
+            .build());
+        contract.reveal_transfer(accounts(2), 1_000.into(), 0, b"salt".to_vec().into());
+    }
+}
@@ -0,0 +1,113 @@
+/*!
+Conversions from old token contracts, for migrating their holders. The owner registers an old
+token with a rate, and holders send it to this contract with `ft_transfer_call`. Each unit of the
+old token mints `rate_numerator / rate_denominator` units of this token to the sender, rounded
+down. The old tokens stay on the contract's account with the old token, out of circulation.
+
+Transfers of old tokens that aren't registered are refused, and so are transfers from senders
+that aren't registered with this token, in which case the old token refunds them.
+*/
+use crate::math::mul_div;
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Conversion {
+    pub rate_numerator: U128,
+    pub rate_denominator: U128,
+    /// Old tokens converted so far.
+    pub total_converted: U128,
+    /// Tokens minted for them.
+    pub total_minted: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Accepts `old_token` for conversion at the given rate, or updates the rate of a registered
+    /// old token.
+    pub fn register_conversion(
+        &mut self,
+        old_token: AccountId,
+        rate_numerator: U128,
+        rate_denominator: U128,
+    ) {
+        self.assert_owner_action();
+        assert!(rate_numerator.0 > 0 && rate_denominator.0 > 0, "The rate should be positive");
+        assert!(old_token != env::current_account_id(), "Can't convert the token into itself");
+        let conversion = match self.conversions.get(&old_token) {
+            Some(conversion) => Conversion { rate_numerator, rate_denominator, ..conversion },
+            None => Conversion {
+                rate_numerator,
+                rate_denominator,
+                total_converted: 0.into(),
+                total_minted: 0.into(),
+            },
+        };
+        self.conversions.insert(&old_token, &conversion);
+    }
+
+    /// Stops accepting `old_token`.
+    pub fn remove_conversion(&mut self, old_token: AccountId) {
+        self.assert_owner_action();
+        self.conversions.remove(&old_token).expect("The token is not registered for conversion");
+    }
+
+    pub fn get_conversion(&self, old_token: AccountId) -> Option<Conversion> {
+        self.conversions.get(&old_token)
+    }
+
+    pub fn get_conversions(
+        &self,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<(AccountId, Conversion)> {
+        let from_index = from_index.unwrap_or(0) as usize;
+        let limit = limit.unwrap_or(u64::MAX) as usize;
+        self.conversions.iter().skip(from_index).take(limit).collect()
+    }
+}
+
+impl Contract {
+    /// Mints the conversion of `amount` old tokens received from `sender_id`.
+    pub(crate) fn internal_on_conversion_transfer(
+        &mut self,
+        old_token: &AccountId,
+        sender_id: AccountId,
+        amount: Balance,
+    ) -> PromiseOrValue<U128> {
+        let mut conversion = self.conversions.get(old_token).unwrap();
+        let minted = mul_div(amount, conversion.rate_numerator.0, conversion.rate_denominator.0);
+        assert!(minted > 0, "The amount is too small to convert");
+        self.token.internal_unwrap_balance_of(&sender_id);
+        self.internal_mint(&sender_id, minted, "conversion");
+        conversion.total_converted = (conversion.total_converted.0 + amount).into();
+        conversion.total_minted = (conversion.total_minted.0 + minted).into();
+        self.conversions.insert(old_token, &conversion);
+        PromiseOrValue::Value(0.into())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_convert_old_tokens() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        // 3 old units = 2 new units.
+        contract.register_conversion(accounts(4), 2.into(), 3.into());
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), 100.into(), String::new());
+        let conversion = contract.get_conversion(accounts(4)).unwrap();
+        assert_eq!(conversion.total_converted.0, 100);
+        assert_eq!(conversion.total_minted.0, 66);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, TOTAL_SUPPLY + 66);
+    }
+}
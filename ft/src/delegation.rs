@@ -0,0 +1,230 @@
+/*!
+Delegation of voting power. A holder can delegate the voting power of its vote lock to another
+account without moving any tokens, and take it back at any time. `voting_power_of` counts the
+power delegated to an account and none of what it delegated away.
+
+Delegation is one hop only: an account's delegate gets the power of its own vote lock, not what
+others delegated to it, and power delegated to an account that delegates itself counts for
+nobody. Each account can have at most `MAX_DELEGATORS` delegators, so tallying its power stays
+cheap. Once the list is full, a new delegator replaces the one with the least voting power if it
+has more.
+
+Every change of an account's delegate is recorded with its timestamp next to its balance history,
+bounded to the same `balance_history_capacity`, so historical tallies can resolve who held the
+power at the time with `delegate_at`. Like the balance history, it's only recorded once the owner
+enables it.
+*/
+use crate::*;
+
+/// Most accounts that can delegate to the same account.
+pub const MAX_DELEGATORS: usize = 50;
+
+#[near_bindgen]
+impl Contract {
+    /// Delegates the caller's voting power to `to`, replacing any previous delegation. Attached
+    /// deposit must cover the added storage, the rest is refunded.
+    #[payable]
+    pub fn delegate(&mut self, to: AccountId) {
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        self.token.internal_unwrap_balance_of(&account_id);
+        assert_ne!(account_id, to, "Can't delegate to yourself");
+        assert!(self.token.accounts.contains_key(&to), "The account {} is not registered", to);
+        let initial_storage_usage = env::storage_usage();
+        self.internal_remove_delegation(&account_id);
+        let mut delegators = self.delegators.get(&to).unwrap_or_default();
+        if delegators.len() >= MAX_DELEGATORS {
+            let (index, power) = delegators
+                .iter()
+                .map(|delegator_id| self.internal_lock_voting_power(delegator_id))
+                .enumerate()
+                .min_by_key(|(_, power)| *power)
+                .unwrap();
+            assert!(
+                self.internal_lock_voting_power(&account_id) > power,
+                "@{} has too many delegators",
+                to
+            );
+            let evicted_id = delegators.swap_remove(index);
+            self.delegates.remove(&evicted_id);
+            self.internal_record_delegate(&evicted_id, None);
+        }
+        delegators.push(account_id.clone());
+        self.delegators.insert(&to, &delegators);
+        self.delegates.insert(&account_id, &to);
+        self.internal_record_delegate(&account_id, Some(to));
+        refund_deposit(initial_storage_usage);
+    }
+
+    /// Takes the caller's voting power back from its delegate.
+    pub fn undelegate(&mut self) {
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        assert!(self.delegates.contains_key(&account_id), "Not delegating");
+        self.internal_remove_delegation(&account_id);
+        self.internal_record_delegate(&account_id, None);
+    }
+
+    pub fn get_delegate(&self, account_id: AccountId) -> Option<AccountId> {
+        self.delegates.get(&account_id)
+    }
+
+    pub fn get_delegators(&self, account_id: AccountId) -> Vec<AccountId> {
+        self.delegators.get(&account_id).unwrap_or_default()
+    }
+
+    /// Who `account_id` delegated to at `timestamp` (in seconds), if anyone. The timestamp must be
+    /// covered by the recorded history.
+    pub fn delegate_at(&self, account_id: AccountId, timestamp: u64) -> Option<AccountId> {
+        let history = self.delegation_history.get(&account_id).unwrap_or_default();
+        if history.len() as u64 >= self.balance_history_capacity {
+            // Once the history is full, older entries may have been evicted.
+            assert!(
+                matches!(history.first(), Some((first_ts, _)) if *first_ts <= timestamp),
+                "The history doesn't go back that far"
+            );
+        }
+        history.iter().rev().find(|(ts, _)| *ts <= timestamp).and_then(|(_, to)| to.clone())
+    }
+}
+
+impl Contract {
+    /// Removes the delegation of `account_id`, if any, without recording it.
+    pub(crate) fn internal_remove_delegation(&mut self, account_id: &AccountId) {
+        let to = match self.delegates.remove(account_id) {
+            Some(to) => to,
+            None => return,
+        };
+        let mut delegators = self.delegators.get(&to).unwrap_or_default();
+        delegators.retain(|delegator_id| delegator_id != account_id);
+        if delegators.is_empty() {
+            self.delegators.remove(&to);
+        } else {
+            self.delegators.insert(&to, &delegators);
+        }
+    }
+
+    /// Ends all delegations from and to `account_id`, e.g. when it closes.
+    pub(crate) fn internal_clear_delegations(&mut self, account_id: &AccountId) {
+        self.internal_remove_delegation(account_id);
+        for delegator_id in self.delegators.remove(account_id).unwrap_or_default() {
+            self.delegates.remove(&delegator_id);
+            self.internal_record_delegate(&delegator_id, None);
+        }
+        self.delegation_history.remove(account_id);
+    }
+
+    fn internal_record_delegate(&mut self, account_id: &AccountId, to: Option<AccountId>) {
+        if self.balance_history_capacity == 0 {
+            return;
+        }
+        let now = current_timestamp_sec();
+        let mut history = self.delegation_history.get(account_id).unwrap_or_default();
+        match history.last_mut() {
+            Some(last) if last.0 == now => last.1 = to,
+            _ => history.push((now, to)),
+        }
+        let excess = history.len().saturating_sub(self.balance_history_capacity as usize);
+        history.drain(..excess);
+        self.delegation_history.insert(account_id, &history);
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+    use crate::vote_escrow::MAX_VOTE_LOCK_SEC;
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(ONE_NEAR / 100).build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_balance_history_capacity(24);
+        contract.lock_for_voting(1_000.into(), MAX_VOTE_LOCK_SEC);
+        for account_id in [accounts(2), accounts(3)] {
+            testing_env!(context
+                .attached_deposit(contract.storage_balance_bounds().min.into())
+                .predecessor_account_id(account_id)
+                .build());
+            contract.storage_deposit(None, None);
+        }
+        testing_env!(context
+            .attached_deposit(ONE_NEAR / 100)
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(10 * NANOS)
+            .build());
+        contract.delegate(accounts(2));
+        (context, contract)
+    }
+
+    #[test]
+    fn test_delegated_power() {
+        let (mut context, mut contract) = setup();
+        assert_eq!(contract.voting_power_of(accounts(1)).0, 0);
+        assert!(contract.voting_power_of(accounts(2)).0 > 0);
+        testing_env!(context.attached_deposit(0).block_timestamp(20 * NANOS).build());
+        contract.undelegate();
+        assert_eq!(contract.voting_power_of(accounts(2)).0, 0);
+        assert!(contract.voting_power_of(accounts(1)).0 > 0);
+        assert_eq!(contract.delegate_at(accounts(1), 15), Some(accounts(2)));
+        assert_eq!(contract.delegate_at(accounts(1), 20), None);
+        assert_eq!(contract.delegate_at(accounts(1), 5), None);
+    }
+
+    #[test]
+    fn test_one_hop_only() {
+        let (mut context, mut contract) = setup();
+        let power = contract.voting_power_of(accounts(2)).0;
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.delegate(accounts(3));
+        assert_eq!(contract.voting_power_of(accounts(2)).0, 0);
+        assert_eq!(contract.voting_power_of(accounts(3)).0, 0);
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.delegate(accounts(2));
+        assert_eq!(contract.voting_power_of(accounts(3)).0, 0);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.undelegate();
+        assert_eq!(contract.voting_power_of(accounts(2)).0, power);
+    }
+
+    #[test]
+    fn test_weakest_delegator_is_replaced() {
+        let (mut context, mut contract) = setup();
+        for i in 0..MAX_DELEGATORS {
+            let account_id: AccountId = format!("sybil{}.near", i).parse().unwrap();
+            contract.token.internal_register_account(&account_id);
+            testing_env!(context.predecessor_account_id(account_id).build());
+            contract.delegate(accounts(3));
+        }
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.delegate(accounts(3));
+        assert_eq!(contract.get_delegators(accounts(3)).len(), MAX_DELEGATORS);
+        assert!(contract.get_delegators(accounts(3)).contains(&accounts(1)));
+        assert!(contract.voting_power_of(accounts(3)).0 > 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "@danny has too many delegators")]
+    fn test_too_many_delegators() {
+        let (mut context, mut contract) = setup();
+        for i in 0..MAX_DELEGATORS {
+            let account_id: AccountId = format!("sybil{}.near", i).parse().unwrap();
+            contract.token.internal_register_account(&account_id);
+            testing_env!(context.predecessor_account_id(account_id).build());
+            contract.delegate(accounts(3));
+        }
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.delegate(accounts(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "The account eugene is not registered")]
+    fn test_delegate_to_unregistered() {
+        let (_, mut contract) = setup();
+        contract.delegate(accounts(4));
+    }
+}
@@ -0,0 +1,222 @@
+/*!
+Demurrage: balances lose `rate_bps` of what's left every `period_seconds` they sit in an account,
+so holding tokens costs a little and spending them doesn't. The decayed tokens are either burned
+or redistributed to all holders as rewards.
+
+Nothing iterates over accounts. Each account remembers when its decay was last applied, and the
+decay of all whole periods since then is applied on the next change to its balance, which is why
+all balance changes go through `internal_transfer`, `internal_move_balance`, `internal_mint` or
+`internal_burn`. `ft_balance_of` already reports the balance after the pending decay, while
+`ft_total_supply` only drops once burned decay is applied.
+
+Only the unlocked part of a balance decays, the way only it can be spent: time and vote locks,
+settlement holds, receive locks, sub-balances and collateral are never eaten into, so a balance
+can't fall below what's locked in it. The contract account, which holds pools and staked tokens,
+doesn't decay. Changing the configuration starts over for everyone, decay accrued under the
+previous one is forgiven. While the supply is frozen, decay that would be burned isn't applied,
+and catches up once it's unfrozen.
+*/
+use crate::math::{mul_div, BPS_DENOMINATOR};
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+
+/// Scale of the fixed-point decay factor.
+const DECAY_PRECISION: Balance = 1_000_000_000_000_000_000_000_000;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Demurrage {
+    /// Share of the balance lost every period, in basis points.
+    pub rate_bps: u16,
+    pub period_seconds: u64,
+    /// Whether the decayed tokens go to all holders as rewards instead of being burned.
+    pub redistribute: bool,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Enables demurrage, or disables it when `None`.
+    pub fn set_demurrage(&mut self, demurrage: Option<Demurrage>) {
+        self.assert_owner_action();
+        if let Some(demurrage) = &demurrage {
+            assert!(demurrage.rate_bps <= BPS_DENOMINATOR, "The rate can't exceed 100%");
+            assert!(demurrage.period_seconds > 0, "The period should be a positive number");
+        }
+        self.demurrage = demurrage;
+        self.demurrage_since = current_timestamp_sec();
+    }
+
+    pub fn get_demurrage(&self) -> Option<Demurrage> {
+        self.demurrage.clone()
+    }
+
+    /// Decay `account_id` will lose on its next balance change.
+    pub fn pending_demurrage_of(&self, account_id: AccountId) -> U128 {
+        let unlocked = self.internal_unlocked_balance(&account_id);
+        self.internal_pending_demurrage(&account_id, unlocked).map_or(0, |(decay, _)| decay).into()
+    }
+}
+
+impl Contract {
+    /// Balance of `account_id` after its pending decay, as `ft_balance_of` reports it.
+    pub(crate) fn internal_decayed_balance(&self, account_id: &AccountId) -> Balance {
+        let balance = self.token.accounts.get(account_id).unwrap_or(0);
+        balance - self.pending_demurrage_of(account_id.clone()).0
+    }
+
+    /// Applies the decay `account_id` accrued since it was last applied. Must be called before any
+    /// change to its balance.
+    pub(crate) fn internal_apply_demurrage(&mut self, account_id: &AccountId) {
+        if !self.token.accounts.contains_key(account_id) {
+            return;
+        }
+        let unlocked = self.internal_unlocked_balance(account_id);
+        let (decay, applied_until) = match self.internal_pending_demurrage(account_id, unlocked) {
+            Some(pending) => pending,
+            None => return,
+        };
+        // A balance with nothing unlocked has nothing to lose, so its next period starts now.
+        let applied_until = if unlocked == 0 { current_timestamp_sec() } else { applied_until };
+        self.last_demurrage_ts.insert(account_id, &applied_until);
+        if decay == 0 {
+            return;
+        }
+        let redistribute = matches!(&self.demurrage, Some(demurrage) if demurrage.redistribute);
+        self.internal_settle_rewards(account_id);
+        if redistribute {
+            let current_account_id = env::current_account_id();
+            self.internal_register_contract_account();
+            self.token.internal_transfer(
+                account_id,
+                &current_account_id,
+                decay,
+                Some("demurrage".to_string()),
+            );
+            self.internal_record_balance(account_id);
+            self.internal_add_rewards(decay);
+        } else {
+            self.token.internal_withdraw(account_id, decay);
+            self.internal_record_balance(account_id);
+            FtBurn { owner_id: account_id, amount: &U128(decay), memo: Some("demurrage") }.emit();
+            self.on_tokens_burned(account_id.clone(), decay);
+        }
+    }
+
+    /// The decay of the unlocked `balance` over the whole periods since it was last applied, and
+    /// the end of the last of those periods. `None` if there's nothing to apply.
+    fn internal_pending_demurrage(
+        &self,
+        account_id: &AccountId,
+        balance: Balance,
+    ) -> Option<(Balance, u64)> {
+        let demurrage = self.demurrage.as_ref()?;
+        if account_id == &env::current_account_id()
+            || (!demurrage.redistribute && self.supply_frozen)
+        {
+            return None;
+        }
+        let since = self.last_demurrage_ts.get(account_id).unwrap_or(0).max(self.demurrage_since);
+        let periods = current_timestamp_sec().saturating_sub(since) / demurrage.period_seconds;
+        if periods == 0 {
+            return None;
+        }
+        let remaining = decay(balance, demurrage.rate_bps, periods);
+        Some((balance - remaining, since + periods * demurrage.period_seconds))
+    }
+
+    pub(crate) fn measure_demurrage_storage_usage(&mut self) {
+        let initial_storage_usage = env::storage_usage();
+        let tmp_account_id = AccountId::new_unchecked("a".repeat(64));
+        self.last_demurrage_ts.insert(&tmp_account_id, &0);
+        self.token.account_storage_usage += env::storage_usage() - initial_storage_usage;
+        self.last_demurrage_ts.remove(&tmp_account_id);
+    }
+}
+
+/// What's left of `balance` after losing `rate_bps` of it `periods` times. Rounds down.
+fn decay(balance: Balance, rate_bps: u16, periods: u64) -> Balance {
+    let mut factor = DECAY_PRECISION;
+    let mut base = mul_div(
+        DECAY_PRECISION,
+        (BPS_DENOMINATOR - rate_bps) as Balance,
+        BPS_DENOMINATOR as Balance,
+    );
+    let mut periods = periods;
+    while periods > 0 && factor > 0 {
+        if periods % 2 == 1 {
+            factor = mul_div(factor, base, DECAY_PRECISION);
+        }
+        base = mul_div(base, base, DECAY_PRECISION);
+        periods /= 2;
+    }
+    mul_div(balance, factor, DECAY_PRECISION)
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    fn setup(redistribute: bool) -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), 10_000.into(), None);
+        contract.set_demurrage(Some(Demurrage { rate_bps: 100, period_seconds: 10, redistribute }));
+        (context, contract)
+    }
+
+    #[test]
+    fn test_decay() {
+        assert_eq!(decay(10_000, 100, 0), 10_000);
+        assert_eq!(decay(10_000, 100, 1), 9_900);
+        assert_eq!(decay(10_000, 100, 2), 9_801);
+        assert_eq!(decay(10_000, 10_000, 1), 0);
+    }
+
+    #[test]
+    fn test_decay_is_burned_on_transfer() {
+        let (mut context, mut contract) = setup(false);
+        testing_env!(context.block_timestamp(25 * NANOS).build());
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 9_801);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.ft_transfer(accounts(1), 801.into(), None);
+        assert_eq!(contract.token.accounts.get(&accounts(2)), Some(9_000));
+        let owner_balance = contract.token.accounts.get(&accounts(1)).unwrap();
+        assert_eq!(contract.ft_total_supply().0, owner_balance + 9_000);
+        // The partial period carries over.
+        testing_env!(context.block_timestamp(30 * NANOS).build());
+        assert_eq!(contract.pending_demurrage_of(accounts(2)).0, 90);
+    }
+
+    #[test]
+    fn test_locked_tokens_dont_decay() {
+        let (mut context, mut contract) = setup(false);
+        contract.internal_set_unlock_schedule(&accounts(2), vec![(1_000, 6_000.into())]);
+        testing_env!(context.block_timestamp(10 * NANOS).build());
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 9_960);
+        contract.internal_apply_demurrage(&accounts(2));
+        assert_eq!(contract.token.accounts.get(&accounts(2)), Some(9_960));
+        assert_eq!(contract.locked_balance_of(accounts(2)).0, 6_000);
+        assert_eq!(contract.internal_unlocked_balance(&accounts(2)), 3_960);
+    }
+
+    #[test]
+    fn test_decay_is_redistributed() {
+        let (mut context, mut contract) = setup(true);
+        testing_env!(context.block_timestamp(10 * NANOS).build());
+        contract.internal_apply_demurrage(&accounts(2));
+        assert_eq!(contract.token.accounts.get(&accounts(2)), Some(9_900));
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+        assert!(contract.pending_rewards_of(accounts(1)).0 > 0);
+    }
+}
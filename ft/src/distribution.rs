@@ -0,0 +1,89 @@
+/*!
+Proportional distribution of an amount by weights, for dividends, fee splits and airdrops. Each
+share is rounded down, and the units lost to rounding go one each to the largest remainders (ties
+to the earlier entry), so the shares always add up to exactly the total and the result only
+depends on the input.
+*/
+use crate::math::U256;
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// Returns how `total` would be split between the accounts by their weights.
+    pub fn preview_distribution(
+        &self,
+        total: U128,
+        weights: Vec<(AccountId, U128)>,
+    ) -> Vec<(AccountId, U128)> {
+        let weights: Vec<(AccountId, u128)> =
+            weights.into_iter().map(|(account_id, weight)| (account_id, weight.0)).collect();
+        distribute_proportionally(total.0, &weights)
+            .into_iter()
+            .map(|(account_id, share)| (account_id, share.into()))
+            .collect()
+    }
+}
+
+/// Splits `total` in proportion to the weights with the largest remainder method. The shares are
+/// returned in the order of `weights` and add up to exactly `total`.
+pub(crate) fn distribute_proportionally(
+    total: Balance,
+    weights: &[(AccountId, u128)],
+) -> Vec<(AccountId, Balance)> {
+    let total_weight =
+        weights.iter().fold(U256::zero(), |sum, (_, weight)| sum + U256::from(*weight));
+    assert!(!total_weight.is_zero(), "The total weight should be positive");
+    let mut remainders = Vec::with_capacity(weights.len());
+    let mut shares: Vec<(AccountId, Balance)> = weights
+        .iter()
+        .enumerate()
+        .map(|(index, (account_id, weight))| {
+            let (share, remainder) =
+                (U256::from(total) * U256::from(*weight)).div_mod(total_weight);
+            remainders.push((remainder, index));
+            (account_id.clone(), share.as_u128())
+        })
+        .collect();
+    let distributed: Balance = shares.iter().map(|(_, share)| share).sum();
+    // Less than one unit is lost per share, so there are fewer leftovers than shares.
+    let leftover = (total - distributed) as usize;
+    remainders.sort_by(|(a, a_index), (b, b_index)| b.cmp(a).then(a_index.cmp(b_index)));
+    for (_, index) in remainders.into_iter().take(leftover) {
+        shares[index].1 += 1;
+    }
+    shares
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+
+    use super::*;
+
+    fn shares(total: Balance, weights: &[u128]) -> Vec<Balance> {
+        let weights: Vec<(AccountId, u128)> =
+            weights.iter().enumerate().map(|(i, weight)| (accounts(i), *weight)).collect();
+        distribute_proportionally(total, &weights).into_iter().map(|(_, share)| share).collect()
+    }
+
+    #[test]
+    fn test_leftovers_go_to_largest_remainders() {
+        assert_eq!(shares(100, &[1, 1, 1]), vec![34, 33, 33]);
+        assert_eq!(shares(10, &[2, 3, 5]), vec![2, 3, 5]);
+        // 7 * 1 / 6 = 1.17, 7 * 2 / 6 = 2.33, 7 * 3 / 6 = 3.5.
+        assert_eq!(shares(7, &[1, 2, 3]), vec![1, 2, 4]);
+        assert_eq!(shares(5, &[0, 1]), vec![0, 5]);
+    }
+
+    #[test]
+    fn test_sum_is_exact_for_large_values() {
+        let weights = [u128::MAX, u128::MAX / 3, 7];
+        assert_eq!(shares(u128::MAX, &weights).iter().sum::<Balance>(), u128::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "The total weight should be positive")]
+    fn test_zero_weights() {
+        shares(100, &[0, 0]);
+    }
+}
@@ -0,0 +1,159 @@
+/*!
+Donations for a cause-linked token. While the owner configures a recipient, `base_bps` of every
+transfer goes from the sender to the recipient on top of the auto-LP fee, and the receiver gets
+the rest. Senders can opt into donating more than the base, but never less.
+
+Transfers to and from the contract account and the recipient itself don't donate. Every donation
+adds to `total_donated`, so anyone can check what the cause received.
+*/
+use crate::math::bps_of;
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+
+/// Highest donation share, base or personal.
+pub const MAX_DONATION_BPS: u16 = 5_000;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Donation {
+    pub recipient_id: AccountId,
+    /// Share of every transfer donated, in basis points.
+    pub base_bps: u16,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Starts routing donations to a registered recipient, or stops when `None`.
+    pub fn set_donation(&mut self, donation: Option<Donation>) {
+        self.assert_owner_action();
+        if let Some(donation) = &donation {
+            assert!(
+                donation.base_bps <= MAX_DONATION_BPS,
+                "The share can't exceed {}",
+                MAX_DONATION_BPS
+            );
+            self.token.internal_unwrap_balance_of(&donation.recipient_id);
+        }
+        self.donation = donation;
+    }
+
+    pub fn get_donation(&self) -> Option<Donation> {
+        self.donation.clone()
+    }
+
+    /// Sets the share the caller donates on its transfers, if higher than the base, or goes back
+    /// to the base when `None`. Attached deposit must cover the added storage, the rest is
+    /// refunded.
+    #[payable]
+    pub fn set_personal_donation(&mut self, donation_bps: Option<u16>) {
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        self.token.internal_unwrap_balance_of(&account_id);
+        let initial_storage_usage = env::storage_usage();
+        match donation_bps {
+            Some(donation_bps) => {
+                assert!(
+                    donation_bps <= MAX_DONATION_BPS,
+                    "The share can't exceed {}",
+                    MAX_DONATION_BPS
+                );
+                self.personal_donations.insert(&account_id, &donation_bps)
+            }
+            None => self.personal_donations.remove(&account_id),
+        };
+        refund_deposit(initial_storage_usage);
+    }
+
+    /// Share `account_id` donates on its transfers, in basis points.
+    pub fn donation_bps_of(&self, account_id: AccountId) -> u16 {
+        let base_bps = self.donation.as_ref().map_or(0, |donation| donation.base_bps);
+        self.personal_donations.get(&account_id).unwrap_or(0).max(base_bps)
+    }
+
+    pub fn total_donated(&self) -> U128 {
+        self.total_donated.into()
+    }
+}
+
+impl Contract {
+    /// Moves the sender's donation on a transfer of `amount` to the recipient and returns it.
+    pub(crate) fn internal_take_donation(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Balance,
+    ) -> Balance {
+        let recipient_id = match self.donation.as_ref() {
+            Some(donation) => donation.recipient_id.clone(),
+            None => return 0,
+        };
+        let current_account_id = env::current_account_id();
+        if [sender_id, receiver_id]
+            .iter()
+            .any(|account_id| *account_id == &current_account_id || *account_id == &recipient_id)
+        {
+            return 0;
+        }
+        let donation = bps_of(amount, self.donation_bps_of(sender_id.clone()));
+        if donation == 0 {
+            return 0;
+        }
+        self.internal_move_balance(
+            sender_id,
+            &recipient_id,
+            donation,
+            Some("donation".to_string()),
+        );
+        self.total_donated += donation;
+        donation
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        for account_id in [accounts(2), accounts(4)] {
+            testing_env!(context
+                .attached_deposit(contract.storage_balance_bounds().min.into())
+                .predecessor_account_id(account_id)
+                .build());
+            contract.storage_deposit(None, None);
+        }
+        testing_env!(context.attached_deposit(0).predecessor_account_id(accounts(1)).build());
+        contract.set_donation(Some(Donation { recipient_id: accounts(4), base_bps: 100 }));
+        testing_env!(context.attached_deposit(1).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_transfer_donates() {
+        let (mut context, mut contract) = setup();
+        contract.ft_transfer(accounts(2), 1_000.into(), None);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 990);
+        assert_eq!(contract.ft_balance_of(accounts(4)).0, 10);
+
+        testing_env!(context.attached_deposit(ONE_NEAR / 100).build());
+        contract.set_personal_donation(Some(500));
+        testing_env!(context.attached_deposit(1).build());
+        contract.ft_transfer(accounts(2), 1_000.into(), None);
+        assert_eq!(contract.ft_balance_of(accounts(4)).0, 60);
+        assert_eq!(contract.total_donated().0, 60);
+    }
+
+    #[test]
+    fn test_personal_share_below_base() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.attached_deposit(ONE_NEAR / 100).build());
+        contract.set_personal_donation(Some(10));
+        assert_eq!(contract.donation_bps_of(accounts(1)), 100);
+    }
+}
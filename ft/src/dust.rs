@@ -0,0 +1,61 @@
+/*!
+Pool of rounding dust. A reward distribution credits holders through an accumulator with limited
+precision, so a few units of each distribution can never be claimed. Those units go to the dust
+pool instead of staying unclaimable in the rewards pool, and the owner can flush the pool to any
+account. The pool is held by the contract account like the rewards.
+*/
+use crate::events::emit_event;
+use crate::*;
+use near_sdk::serde_json::json;
+
+#[near_bindgen]
+impl Contract {
+    pub fn get_dust_pool(&self) -> U128 {
+        self.dust_pool.into()
+    }
+
+    /// Sends the whole dust pool to `to`.
+    pub fn flush_dust(&mut self, to: AccountId) -> U128 {
+        self.assert_owner_action();
+        let amount = self.dust_pool;
+        assert!(amount > 0, "The dust pool is empty");
+        self.dust_pool = 0;
+        self.internal_move_balance(
+            &env::current_account_id(),
+            &to,
+            amount,
+            Some("dust".to_string()),
+        );
+        emit_event("dust_flushed", json!({ "account_id": to, "amount": U128(amount) }));
+        amount.into()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::get_context;
+
+    #[test]
+    fn test_distribution_remainder_is_flushed() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), 8.into());
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        // 1 token over the 7 eligible ones rounds down to nothing per token.
+        contract.distribute_rewards(1.into());
+        assert_eq!(contract.get_dust_pool().0, 1);
+        assert_eq!(contract.pending_rewards_of(accounts(1)).0, 0);
+        assert_eq!(contract.flush_dust(accounts(2)).0, 1);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 1);
+        assert_eq!(contract.get_dust_pool().0, 0);
+    }
+}
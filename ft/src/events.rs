@@ -0,0 +1,17 @@
+use near_sdk::log;
+use near_sdk::serde_json::{json, Value};
+
+/// Standard name for the contract's own NEP-297 events, on top of the NEP-141 ones.
+const EVENT_STANDARD: &str = "hgt";
+const EVENT_VERSION: &str = "1.0.0";
+
+/// Logs a NEP-297 event with a single data entry.
+pub(crate) fn emit_event(event: &str, data: Value) {
+    let event = json!({
+        "standard": EVENT_STANDARD,
+        "version": EVENT_VERSION,
+        "event": event,
+        "data": [data],
+    });
+    log!("EVENT_JSON:{}", event);
+}
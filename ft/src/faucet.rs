@@ -0,0 +1,233 @@
+/*!
+Faucet dripping from a fixed budget, for testnets and reward programs. Anyone can fund the
+faucet pool with their own tokens, and every registered account can take the drip amount once per
+cooldown until the pool runs out. The last drip pays whatever is left, and drips from an empty
+faucet pay nothing instead of failing. The pool is held by the contract account like the airdrop
+pool, so the faucet never mints.
+
+While a personhood gate is configured, the caller must be confirmed as a unique human before it
+drips, the same way as for airdrop claims.
+*/
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::PromiseError;
+
+const GAS_FOR_ON_FAUCET_PERSONHOOD_CHECKED: Gas = Gas(20_000_000_000_000);
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Faucet {
+    /// Tokens paid per drip.
+    pub drip_amount: U128,
+    /// Time (in seconds) an account waits between drips.
+    pub cooldown_sec: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Configures the faucet, or stops drips when `None`. The pool is kept either way.
+    pub fn set_faucet(&mut self, faucet: Option<Faucet>) {
+        self.assert_owner_action();
+        if let Some(faucet) = faucet.as_ref() {
+            assert!(faucet.drip_amount.0 > 0, "The drip amount should be a positive number");
+        }
+        self.faucet = faucet;
+    }
+
+    pub fn get_faucet(&self) -> Option<Faucet> {
+        self.faucet.clone()
+    }
+
+    /// Moves `amount` of the caller's tokens to the faucet pool. Anyone can fund it, which also
+    /// works once the owner is the multisig contract account.
+    #[payable]
+    pub fn fund_faucet(&mut self, amount: U128) {
+        assert_one_yocto();
+        assert!(amount.0 > 0, "The amount should be a positive number");
+        self.internal_register_contract_account();
+        let sender_id = env::predecessor_account_id();
+        self.internal_record_activity(&sender_id);
+        self.internal_move_balance(
+            &sender_id,
+            &env::current_account_id(),
+            amount.0,
+            Some("faucet funding".to_string()),
+        );
+        self.faucet_pool += amount.0;
+    }
+
+    /// Tokens left in the faucet pool.
+    pub fn get_faucet_pool(&self) -> U128 {
+        self.faucet_pool.into()
+    }
+
+    /// Pays the drip amount to the caller, right away if there's no personhood gate or the
+    /// caller is already confirmed, otherwise once the registry confirmed it. Attached deposit
+    /// must cover the storage of the caller's first drip, the rest is refunded. Resolves to the
+    /// amount paid.
+    #[payable]
+    pub fn faucet_drip(&mut self) -> PromiseOrValue<U128> {
+        self.assert_not_paused();
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        match self.internal_personhood_check(&account_id) {
+            Some(check) => {
+                // Fail early rather than in the callback, which would keep the deposit.
+                self.internal_assert_drip_allowed(&account_id);
+                check
+                    .then(
+                        Self::ext(env::current_account_id())
+                            .with_attached_deposit(env::attached_deposit())
+                            .with_static_gas(GAS_FOR_ON_FAUCET_PERSONHOOD_CHECKED)
+                            .on_faucet_personhood_checked(account_id),
+                    )
+                    .into()
+            }
+            None => {
+                let initial_storage_usage = env::storage_usage();
+                let amount = self.internal_drip(&account_id);
+                refund_deposit(initial_storage_usage);
+                PromiseOrValue::Value(amount.into())
+            }
+        }
+    }
+
+    /// Completes a drip once the registry answered, refunding the deposit if `account_id` isn't
+    /// confirmed as a unique human.
+    #[private]
+    #[payable]
+    pub fn on_faucet_personhood_checked(
+        &mut self,
+        account_id: AccountId,
+        #[callback_result] is_human: Result<bool, PromiseError>,
+    ) -> U128 {
+        if !self.internal_record_personhood(&account_id, is_human == Ok(true)) {
+            Promise::new(account_id).transfer(env::attached_deposit());
+            return 0.into();
+        }
+        let initial_storage_usage = env::storage_usage();
+        let amount = self.internal_drip(&account_id);
+        refund_deposit_to(&account_id, initial_storage_usage, 0);
+        amount.into()
+    }
+}
+
+impl Contract {
+    /// Checks that the faucet is configured and `account_id` is registered and past its cooldown.
+    fn internal_assert_drip_allowed(&self, account_id: &AccountId) -> Faucet {
+        let faucet = self.faucet.clone().expect("The faucet is not configured");
+        self.token.internal_unwrap_balance_of(account_id);
+        if let Some(last_drip) = self.faucet_drips.get(account_id) {
+            assert!(
+                current_timestamp_sec() >= last_drip + faucet.cooldown_sec,
+                "The next drip is possible from {}",
+                last_drip + faucet.cooldown_sec
+            );
+        }
+        faucet
+    }
+
+    /// Pays the drip amount to `account_id`. Returns the amount paid.
+    fn internal_drip(&mut self, account_id: &AccountId) -> Balance {
+        let faucet = self.internal_assert_drip_allowed(account_id);
+        let amount = faucet.drip_amount.0.min(self.faucet_pool);
+        if amount == 0 {
+            log!("The faucet is empty");
+            return 0;
+        }
+        self.faucet_drips.insert(account_id, &current_timestamp_sec());
+        self.faucet_pool -= amount;
+        self.internal_move_balance(
+            &env::current_account_id(),
+            account_id,
+            amount,
+            Some("faucet".to_string()),
+        );
+        amount
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(1).build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_faucet(Some(Faucet { drip_amount: 10.into(), cooldown_sec: 60 }));
+        contract.fund_faucet(15.into());
+        testing_env!(context
+            .attached_deposit(ONE_NEAR / 100)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        (context, contract)
+    }
+
+    fn drip(contract: &mut Contract) -> Balance {
+        match contract.faucet_drip() {
+            PromiseOrValue::Value(amount) => amount.0,
+            PromiseOrValue::Promise(_) => panic!("The drip was deferred"),
+        }
+    }
+
+    #[test]
+    fn test_drips_until_pool_is_empty() {
+        let (mut context, mut contract) = setup();
+        assert_eq!(drip(&mut contract), 10);
+        testing_env!(context.block_timestamp(60 * NANOS).build());
+        assert_eq!(drip(&mut contract), 5);
+        assert_eq!(contract.get_faucet_pool().0, 0);
+        testing_env!(context.block_timestamp(120 * NANOS).build());
+        assert_eq!(drip(&mut contract), 0);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 15);
+    }
+
+    #[test]
+    fn test_drip_after_personhood_check() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.attached_deposit(0).predecessor_account_id(accounts(1)).build());
+        contract.set_personhood_gate(Some(crate::personhood::PersonhoodGate {
+            registry_id: accounts(4),
+            ttl_seconds: 3600,
+        }));
+        testing_env!(context
+            .attached_deposit(ONE_NEAR / 100)
+            .predecessor_account_id(accounts(2))
+            .build());
+        assert!(matches!(contract.faucet_drip(), PromiseOrValue::Promise(_)));
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 0);
+        assert_eq!(contract.on_faucet_personhood_checked(accounts(2), Ok(false)).0, 0);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 0);
+        assert_eq!(contract.on_faucet_personhood_checked(accounts(2), Ok(true)).0, 10);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 10);
+        assert!(contract.is_verified_human(accounts(2)));
+    }
+
+    #[test]
+    fn test_fund_under_multisig() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.attached_deposit(0).predecessor_account_id(accounts(1)).build());
+        contract.set_multisig(crate::multisig::Multisig {
+            signers: vec![accounts(3)],
+            threshold: 1,
+            proposal_lifetime: 100,
+        });
+        testing_env!(context.attached_deposit(1).build());
+        contract.fund_faucet(5.into());
+        assert_eq!(contract.get_faucet_pool().0, 20);
+    }
+
+    #[test]
+    #[should_panic(expected = "The next drip is possible from 60")]
+    fn test_drip_within_cooldown() {
+        let (_, mut contract) = setup();
+        contract.faucet_drip();
+        contract.faucet_drip();
+    }
+}
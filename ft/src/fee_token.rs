@@ -0,0 +1,238 @@
+/*!
+Transfer fees paid in a separate fee token. While configured, the auto-LP fee of a transfer is
+charged to the sender's deposit of the fee token, converted at the configured rate, and the
+transferred amount arrives in full. Accounts deposit the fee token with
+`ft_transfer_call(receiver_id: <this contract>, msg: "deposit_fee")` and can withdraw what they
+don't use. If a sender's deposit doesn't cover a fee, the fee is either taken in this token as
+usual or the transfer fails, depending on the configuration.
+
+The fees paid in the fee token are collected by the contract and the owner withdraws them. The fee
+token can't be changed while the contract holds any of it.
+*/
+use crate::math::mul_div;
+use crate::*;
+use near_contract_standards::fungible_token::core::ext_ft_core;
+use near_sdk::is_promise_success;
+use near_sdk::serde::{Deserialize, Serialize};
+
+const GAS_FOR_FEE_TOKEN_TRANSFER: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_ON_FEE_TOKEN_WITHDRAWN: Gas = Gas(10_000_000_000_000);
+
+/// `ft_transfer_call` message that deposits the fee token for paying fees.
+pub const DEPOSIT_FEE_MSG: &str = "deposit_fee";
+
+/// A fee of one unit of this token costs `rate_numerator / rate_denominator` units of the fee
+/// token, rounded down.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeeToken {
+    pub token_id: AccountId,
+    pub rate_numerator: U128,
+    pub rate_denominator: U128,
+    /// Whether fees the sender's deposit doesn't cover are taken in this token instead of
+    /// failing the transfer.
+    pub fallback_to_token_fee: bool,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Configures the fee token, or goes back to fees in this token when `None`.
+    pub fn set_fee_token(&mut self, fee_token: Option<FeeToken>) {
+        self.assert_owner_action();
+        if let Some(fee_token) = fee_token.as_ref() {
+            assert!(
+                fee_token.rate_numerator.0 > 0 && fee_token.rate_denominator.0 > 0,
+                "The rate should be positive"
+            );
+        }
+        let token_id = |fee_token: &Option<FeeToken>| {
+            fee_token.as_ref().map(|fee_token| fee_token.token_id.clone())
+        };
+        assert!(
+            token_id(&self.fee_token) == token_id(&fee_token) || self.fee_token_held == 0,
+            "Can't change the fee token while holding any of it"
+        );
+        self.fee_token = fee_token;
+    }
+
+    pub fn get_fee_token(&self) -> Option<FeeToken> {
+        self.fee_token.clone()
+    }
+
+    pub fn fee_token_deposit_of(&self, account_id: AccountId) -> U128 {
+        self.fee_token_deposits.get(&account_id).unwrap_or(0).into()
+    }
+
+    /// Fee tokens collected as fees and not yet withdrawn by the owner.
+    pub fn get_fee_token_collected(&self) -> U128 {
+        self.fee_token_collected.into()
+    }
+
+    /// Sends `amount` of the caller's fee token deposit back to it. The deposit is restored if
+    /// the transfer fails.
+    #[payable]
+    pub fn withdraw_fee_token_deposit(&mut self, amount: U128) -> Promise {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        let deposit = self.fee_token_deposits.get(&account_id).unwrap_or(0);
+        assert!(amount.0 > 0 && amount.0 <= deposit, "The fee token deposit is too low");
+        self.internal_set_fee_token_deposit(&account_id, deposit - amount.0);
+        self.internal_send_fee_token(account_id.clone(), amount.0, Some(account_id))
+    }
+
+    /// Sends `amount` of the collected fee tokens to `receiver_id`.
+    pub fn withdraw_fee_token_collected(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+    ) -> Promise {
+        self.assert_owner_action();
+        assert!(
+            amount.0 > 0 && amount.0 <= self.fee_token_collected,
+            "The collected fee tokens are too low"
+        );
+        self.fee_token_collected -= amount.0;
+        self.internal_send_fee_token(receiver_id, amount.0, None)
+    }
+
+    /// Restores the deposit of `account_id`, or the collected fees when `None`, if the transfer
+    /// failed.
+    #[private]
+    pub fn on_fee_token_withdrawn(&mut self, account_id: Option<AccountId>, amount: U128) -> bool {
+        if is_promise_success() {
+            self.fee_token_held -= amount.0;
+            return true;
+        }
+        match account_id {
+            Some(account_id) => {
+                let deposit = self.fee_token_deposits.get(&account_id).unwrap_or(0);
+                self.internal_set_fee_token_deposit(&account_id, deposit + amount.0);
+            }
+            None => self.fee_token_collected += amount.0,
+        }
+        false
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_on_fee_token_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: Balance,
+        msg: &str,
+    ) -> PromiseOrValue<U128> {
+        assert_eq!(msg, DEPOSIT_FEE_MSG, "Unsupported message");
+        self.token.internal_unwrap_balance_of(&sender_id);
+        let deposit = self.fee_token_deposits.get(&sender_id).unwrap_or(0);
+        self.internal_set_fee_token_deposit(&sender_id, deposit + amount);
+        self.fee_token_held += amount;
+        PromiseOrValue::Value(0.into())
+    }
+
+    /// Charges `fee` to the fee token deposit of `sender_id`. Returns whether it was charged, or
+    /// whether the fee has to be taken in this token instead.
+    pub(crate) fn internal_charge_fee_token(
+        &mut self,
+        sender_id: &AccountId,
+        fee: Balance,
+    ) -> bool {
+        let fee_token = match self.fee_token.as_ref() {
+            Some(fee_token) => fee_token,
+            None => return false,
+        };
+        let fee_token_amount =
+            mul_div(fee, fee_token.rate_numerator.0, fee_token.rate_denominator.0);
+        let deposit = self.fee_token_deposits.get(sender_id).unwrap_or(0);
+        if fee_token_amount > deposit {
+            assert!(fee_token.fallback_to_token_fee, "Not enough fee tokens deposited");
+            return false;
+        }
+        self.internal_set_fee_token_deposit(sender_id, deposit - fee_token_amount);
+        self.fee_token_collected += fee_token_amount;
+        true
+    }
+
+    fn internal_set_fee_token_deposit(&mut self, account_id: &AccountId, deposit: Balance) {
+        if deposit == 0 {
+            self.fee_token_deposits.remove(account_id);
+        } else {
+            self.fee_token_deposits.insert(account_id, &deposit);
+        }
+    }
+
+    fn internal_send_fee_token(
+        &mut self,
+        receiver_id: AccountId,
+        amount: Balance,
+        account_id: Option<AccountId>,
+    ) -> Promise {
+        let fee_token = self.fee_token.as_ref().expect("No fee token is configured");
+        ext_ft_core::ext(fee_token.token_id.clone())
+            .with_attached_deposit(1)
+            .with_static_gas(GAS_FOR_FEE_TOKEN_TRANSFER)
+            .ft_transfer(receiver_id, amount.into(), None)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_ON_FEE_TOKEN_WITHDRAWN)
+                    .on_fee_token_withdrawn(account_id, amount.into()),
+            )
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup(fallback_to_token_fee: bool) -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_auto_lp(accounts(4), (TOTAL_SUPPLY / 2).into(), 100);
+        // A fee of 1 token costs 2 fee token units.
+        contract.set_fee_token(Some(FeeToken {
+            token_id: accounts(5),
+            rate_numerator: 2.into(),
+            rate_denominator: 1.into(),
+            fallback_to_token_fee,
+        }));
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context.predecessor_account_id(accounts(5)).build());
+        contract.ft_on_transfer(accounts(1), 200.into(), DEPOSIT_FEE_MSG.to_string());
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), 10_000.into(), None);
+        (context, contract)
+    }
+
+    #[test]
+    fn test_fee_paid_in_fee_token() {
+        let (_, contract) = setup(false);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 10_000);
+        assert_eq!(contract.fee_token_deposit_of(accounts(1)).0, 0);
+        assert_eq!(contract.get_fee_token_collected().0, 200);
+        assert_eq!(contract.get_auto_lp().unwrap().accumulated.0, 0);
+    }
+
+    #[test]
+    fn test_fallback_to_token_fee() {
+        let (_, mut contract) = setup(true);
+        contract.ft_transfer(accounts(2), 10_000.into(), None);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 10_000 + 9_900);
+        assert_eq!(contract.get_auto_lp().unwrap().accumulated.0, 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough fee tokens deposited")]
+    fn test_no_fallback() {
+        let (_, mut contract) = setup(false);
+        contract.ft_transfer(accounts(2), 10_000.into(), None);
+    }
+}
@@ -0,0 +1,147 @@
+/*!
+Automatic forwarding of incoming transfers, e.g. from deposit sub-accounts to an omnibus treasury.
+An account can name a destination, and everything it receives through a transfer is moved on to
+the destination right away, in the same call. If the forward can't happen, e.g. because the
+destination unregistered, the whole transfer reverts.
+
+Tokens received through `ft_transfer_call` aren't forwarded: the receiver's contract can refund
+part of them, and the refund comes out of its own balance.
+
+Forwarding only goes one hop, so tokens forwarded to an account that forwards itself stay there.
+Setting a destination that forwards straight back is rejected.
+*/
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// Forwards everything the caller receives to `destination`, or stops forwarding when `None`.
+    /// Requires at least 1 yoctoNEAR attached, which must also cover the added storage, the rest
+    /// is refunded.
+    #[payable]
+    pub fn set_forward_to(&mut self, destination: Option<AccountId>) {
+        assert_at_least_one_yocto();
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        self.token.internal_unwrap_balance_of(&account_id);
+        let destination = match destination {
+            Some(destination) => destination,
+            None => {
+                assert_one_yocto();
+                self.forward_to.remove(&account_id);
+                return;
+            }
+        };
+        assert_ne!(account_id, destination, "Can't forward to yourself");
+        self.token.internal_unwrap_balance_of(&destination);
+        assert_ne!(
+            self.forward_to.get(&destination).as_ref(),
+            Some(&account_id),
+            "Forwarding loops are not allowed"
+        );
+        let initial_storage_usage = env::storage_usage();
+        self.forward_to.insert(&account_id, &destination);
+        refund_deposit(initial_storage_usage);
+    }
+
+    pub fn get_forward_to(&self, account_id: AccountId) -> Option<AccountId> {
+        self.forward_to.get(&account_id)
+    }
+}
+
+impl Contract {
+    /// Fails if `receiver_id` forwards to an account that doesn't accept its transfers.
+    pub(crate) fn check_forward_allowed(&self, receiver_id: &AccountId) -> Result<(), String> {
+        match self.forward_to.get(receiver_id) {
+            Some(destination) => self.check_incoming_allowed(receiver_id, &destination),
+            None => Ok(()),
+        }
+    }
+
+    /// Moves `amount` just received by `receiver_id` on to its destination, if it has one.
+    /// Returns the account that ends up with the tokens.
+    pub(crate) fn internal_forward(
+        &mut self,
+        receiver_id: &AccountId,
+        amount: Balance,
+    ) -> AccountId {
+        match self.forward_to.get(receiver_id) {
+            Some(destination) if amount > 0 => {
+                assert_ok(self.check_incoming_allowed(receiver_id, &destination));
+                self.internal_move_balance(
+                    receiver_id,
+                    &destination,
+                    amount,
+                    Some("forward".to_string()),
+                );
+                destination
+            }
+            _ => receiver_id.clone(),
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        for account_id in [accounts(2), accounts(3)] {
+            testing_env!(context
+                .attached_deposit(contract.storage_balance_bounds().min.into())
+                .predecessor_account_id(account_id)
+                .build());
+            contract.storage_deposit(None, None);
+        }
+        testing_env!(context
+            .attached_deposit(ONE_NEAR / 100)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.set_forward_to(Some(accounts(3)));
+        (context, contract)
+    }
+
+    #[test]
+    fn test_incoming_transfer_is_forwarded() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), 100.into(), None);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 0);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 100);
+    }
+
+    #[test]
+    fn test_transfer_call_is_not_forwarded() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(1))
+            .prepaid_gas(Gas(300_000_000_000_000))
+            .build());
+        contract.ft_transfer_call(accounts(2), 100.into(), None, String::new());
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 100);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Forwarding loops are not allowed")]
+    fn test_forwarding_loop() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.set_forward_to(Some(accounts(2)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of at least 1 yoctoNEAR")]
+    fn test_changing_destination_requires_deposit() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.attached_deposit(0).build());
+        contract.set_forward_to(Some(accounts(1)));
+    }
+}
@@ -0,0 +1,86 @@
+/*!
+Verifiable giveaway draws. `pick_winner` picks a holder with probability proportional to its
+balance, using a seed supplied by the caller (e.g. the output of a VRF), so anyone can re-run the
+draw and check the result. The contract's own account, holding the pools and fees, never wins.
+
+Iterating over every holder doesn't fit in a view call for a large token, so the draw runs over
+a single page of holders. To draw over everyone, split the holders into pages off-chain, pick a
+page with probability proportional to its total balance, then call `pick_winner` on that page
+with the same seed.
+*/
+use crate::*;
+use near_sdk::json_types::Base64VecU8;
+
+#[near_bindgen]
+impl Contract {
+    /// Picks a holder from the page `[from_index, from_index + limit)` with probability
+    /// proportional to its balance.
+    pub fn pick_winner(
+        &self,
+        seed: Base64VecU8,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> AccountId {
+        assert!(!seed.0.is_empty(), "The seed can't be empty");
+        let current_account_id = env::current_account_id();
+        let holders: Vec<(AccountId, Balance)> = self
+            .get_holders(from_index, limit)
+            .into_iter()
+            .filter(|account_id| *account_id != current_account_id)
+            .map(|account_id| {
+                let balance = self.token.accounts.get(&account_id).unwrap_or(0);
+                (account_id, balance)
+            })
+            .collect();
+        let total_weight: Balance = holders.iter().map(|(_, balance)| balance).sum();
+        assert!(total_weight > 0, "No holder in the page has a positive balance");
+
+        let hash = env::sha256(&seed.0);
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(&hash[..16]);
+        let mut target = u128::from_le_bytes(bytes) % total_weight;
+        for (account_id, balance) in holders {
+            if target < balance {
+                return account_id;
+            }
+            target -= balance;
+        }
+        unreachable!()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_contract_account_never_wins() {
+        testing_env!(get_context(accounts(1)).build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.internal_register_contract_account();
+        contract.internal_move_balance(&accounts(1), &accounts(0), TOTAL_SUPPLY - 1, None);
+        for seed in 0..10u8 {
+            assert_eq!(contract.pick_winner(vec![seed].into(), None, None), accounts(1));
+        }
+    }
+
+    #[test]
+    fn test_pick_winner_skips_empty_balances() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        assert_eq!(contract.get_holders(None, None), vec![accounts(1), accounts(2)]);
+        for seed in 0..10u8 {
+            assert_eq!(contract.pick_winner(vec![seed].into(), None, None), accounts(1));
+        }
+    }
+}
@@ -0,0 +1,112 @@
+/*!
+Global spenders: protocols the owner trusts, e.g. a DEX router, that can move tokens out of any
+account without a per-account approval, until a contract-wide limit is used up.
+
+This puts every holder's balance at the mercy of the owner and of each global spender: a
+compromised or malicious spender can move anyone's tokens up to the remaining limit. Holders have
+to trust both, so keep the list short and the limit tight. Every use emits a `global_spend` event
+for monitoring. Adding a spender or raising the limit is subject to the admin cooldown, while the
+owner or the guardian can revoke a spender instantly.
+*/
+use crate::events::emit_event;
+use crate::*;
+use near_sdk::serde_json::json;
+
+#[near_bindgen]
+impl Contract {
+    /// Grants or revokes the global spend permission of `spender_id`.
+    pub fn set_global_spender(&mut self, spender_id: AccountId, enabled: bool) {
+        if enabled {
+            self.assert_owner_action();
+            self.global_spenders.insert(&spender_id);
+        } else {
+            self.assert_owner_or_guardian();
+            self.global_spenders.remove(&spender_id);
+        }
+        emit_event("global_spender_set", json!({ "spender_id": spender_id, "enabled": enabled }));
+    }
+
+    pub fn is_global_spender(&self, account_id: AccountId) -> bool {
+        self.global_spenders.contains(&account_id)
+    }
+
+    /// Sets the total amount global spenders can still move, shared between all of them.
+    pub fn set_global_spend_limit(&mut self, limit: U128) {
+        self.assert_owner_action();
+        self.global_spend_limit = limit.0;
+    }
+
+    pub fn get_global_spend_limit(&self) -> U128 {
+        self.global_spend_limit.into()
+    }
+
+    /// Transfers `amount` from `owner_id` to `receiver_id`. Only callable by global spenders.
+    #[payable]
+    pub fn ft_transfer_from_global(
+        &mut self,
+        owner_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+    ) {
+        self.assert_priority_fee_attached(&[amount.0]);
+        self.assert_no_transfer_validator();
+        self.assert_no_attestation_gate();
+        let spender_id = env::predecessor_account_id();
+        assert!(self.global_spenders.contains(&spender_id), "Not a global spender");
+        assert!(amount.0 <= self.global_spend_limit, "The global spend limit is too low");
+        self.global_spend_limit -= amount.0;
+        self.internal_transfer(&owner_id, &receiver_id, amount.0, memo);
+        emit_event(
+            "global_spend",
+            json!({
+                "spender_id": spender_id,
+                "owner_id": owner_id,
+                "receiver_id": receiver_id,
+                "amount": amount,
+            }),
+        );
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_global_spender(accounts(2), true);
+        contract.set_global_spend_limit(100.into());
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(2)).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_global_spend() {
+        let (_, mut contract) = setup();
+        contract.ft_transfer_from_global(accounts(1), accounts(3), 60.into(), None);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 60);
+        assert_eq!(contract.get_global_spend_limit().0, 40);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not a global spender")]
+    fn test_revoked_spender() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.set_global_spender(accounts(2), false);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.ft_transfer_from_global(accounts(1), accounts(3), 60.into(), None);
+    }
+}
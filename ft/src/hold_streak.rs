@@ -0,0 +1,141 @@
+/*!
+Reward boost for long-term holders. Each account's hold streak starts when it registers and
+restarts whenever its balance goes down, e.g. when it sends or burns tokens. Moving tokens into
+the contract, e.g. to stake them, doesn't restart it.
+
+With a boost configured, staking rewards and claimed dividends grow by `bps_per_day` for every
+full day of the streak, up to `max_boost_bps`. The extra is minted on top, so it's subject to the
+mint limits and skipped once the supply is frozen.
+*/
+use crate::math::bps_of;
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+
+const DAY_SEC: u64 = 24 * 60 * 60;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct HoldBoost {
+    /// Boost gained per full day of the streak, in basis points.
+    pub bps_per_day: u16,
+    /// Highest boost, in basis points.
+    pub max_boost_bps: u16,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Configures the boost, or disables it when `boost` is `None`.
+    pub fn set_hold_boost(&mut self, boost: Option<HoldBoost>) {
+        self.assert_owner_action();
+        self.hold_boost = boost;
+    }
+
+    pub fn get_hold_boost(&self) -> Option<HoldBoost> {
+        self.hold_boost.clone()
+    }
+
+    /// Length of `account_id`'s hold streak in seconds.
+    pub fn hold_streak_of(&self, account_id: AccountId) -> u64 {
+        self.hold_since
+            .get(&account_id)
+            .map_or(0, |hold_since| current_timestamp_sec().saturating_sub(hold_since))
+    }
+
+    /// Current reward boost of `account_id`, in basis points.
+    pub fn hold_boost_of(&self, account_id: AccountId) -> u16 {
+        let boost = match self.hold_boost.as_ref() {
+            Some(boost) => boost,
+            None => return 0,
+        };
+        let days = self.hold_streak_of(account_id) / DAY_SEC;
+        (days.saturating_mul(boost.bps_per_day as u64)).min(boost.max_boost_bps as u64) as u16
+    }
+}
+
+impl Contract {
+    /// Restarts the hold streak of `account_id`. Must be called whenever its balance goes down.
+    pub(crate) fn internal_reset_hold_streak(&mut self, account_id: &AccountId) {
+        if account_id != &env::current_account_id() {
+            self.hold_since.insert(account_id, &current_timestamp_sec());
+        }
+    }
+
+    /// Mints the boost on a `reward` of `account_id` just paid to `to`. Returns the amount minted.
+    pub(crate) fn internal_mint_hold_boost(
+        &mut self,
+        account_id: &AccountId,
+        to: &AccountId,
+        reward: Balance,
+    ) -> Balance {
+        if self.supply_frozen {
+            return 0;
+        }
+        let boost = bps_of(reward, self.hold_boost_of(account_id.clone()))
+            .min(self.internal_mint_headroom());
+        if boost > 0 {
+            self.internal_mint(to, boost, "hold streak boost");
+        }
+        boost
+    }
+
+    /// Adds the storage of a streak entry for the longest possible account ID to the storage
+    /// required to register an account.
+    pub(crate) fn measure_hold_streak_storage_usage(&mut self) {
+        let initial_storage_usage = env::storage_usage();
+        let tmp_account_id = AccountId::new_unchecked("a".repeat(64));
+        self.internal_reset_hold_streak(&tmp_account_id);
+        self.token.account_storage_usage += env::storage_usage() - initial_storage_usage;
+        self.hold_since.remove(&tmp_account_id);
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_hold_boost(Some(HoldBoost { bps_per_day: 100, max_boost_bps: 1_000 }));
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), TOTAL_SUPPLY.into(), None);
+        (context, contract)
+    }
+
+    #[test]
+    fn test_streak_and_boost() {
+        let (mut context, contract) = setup();
+        testing_env!(context.block_timestamp(3 * DAY_SEC * NANOS).build());
+        assert_eq!(contract.hold_streak_of(accounts(2)), 3 * DAY_SEC);
+        assert_eq!(contract.hold_boost_of(accounts(2)), 300);
+        testing_env!(context.block_timestamp(30 * DAY_SEC * NANOS).build());
+        assert_eq!(contract.hold_boost_of(accounts(2)), 1_000);
+    }
+
+    #[test]
+    fn test_dividends_are_boosted() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(5 * DAY_SEC * NANOS)
+            .build());
+        contract.distribute_rewards(1_000.into());
+        // Moving tokens into the contract doesn't restart the streak.
+        assert_eq!(contract.hold_boost_of(accounts(2)), 500);
+        assert_eq!(contract.claim_rewards(None).0, 999 + 49);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY - 1_000 + 999 + 49);
+
+        contract.ft_transfer(accounts(1), 1.into(), None);
+        assert_eq!(contract.hold_streak_of(accounts(2)), 0);
+    }
+}
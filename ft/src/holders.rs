@@ -0,0 +1,48 @@
+/*!
+Iterable set of registered accounts. The standard keeps balances in a `LookupMap`, which can't be
+iterated, so registrations and unregistrations are mirrored here. The set entry is included in
+the storage cost of registration.
+*/
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    pub fn get_holders_count(&self) -> u64 {
+        self.holders.len()
+    }
+
+    pub fn get_holders(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<AccountId> {
+        let from_index = from_index.unwrap_or(0);
+        let limit = limit.unwrap_or(self.holders.len());
+        self.holders.iter().skip(from_index as usize).take(limit as usize).collect()
+    }
+}
+
+impl Contract {
+    /// Registers `account_id` with the token and adds it to the holders.
+    pub(crate) fn internal_register_account(&mut self, account_id: &AccountId) {
+        self.token.internal_register_account(account_id);
+        self.holders.insert(account_id);
+        self.internal_init_reward_checkpoint(account_id);
+        self.internal_reset_hold_streak(account_id);
+    }
+
+    /// Registers the contract's own account if needed. It holds tokens on behalf of others, e.g.
+    /// presale tokens, fees, rewards and stakes.
+    pub(crate) fn internal_register_contract_account(&mut self) {
+        let current_account_id = env::current_account_id();
+        if self.token.accounts.get(&current_account_id).is_none() {
+            self.internal_register_account(&current_account_id);
+        }
+    }
+
+    /// Adds the storage of a holder entry for the longest possible account ID to the storage
+    /// required to register an account.
+    pub(crate) fn measure_holder_storage_usage(&mut self) {
+        let initial_storage_usage = env::storage_usage();
+        let tmp_account_id = AccountId::new_unchecked("a".repeat(64));
+        self.holders.insert(&tmp_account_id);
+        self.token.account_storage_usage += env::storage_usage() - initial_storage_usage;
+        self.holders.remove(&tmp_account_id);
+    }
+}
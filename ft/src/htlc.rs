@@ -0,0 +1,239 @@
+/*!
+Hash-time-locked contracts for trustless swaps, including cross-chain ones. `htlc_lock` moves the
+sender's tokens to the contract account, to be released to the receiver by anyone revealing the
+preimage of the `sha256` hashlock before the timelock, or refunded to the sender after it.
+
+The revealed preimage is part of the `htlc_claimed` event, so the counterparty can use it to claim
+its side of the swap. Locked tokens stay in the total supply and are held by the contract account
+on behalf of the swaps, like the bridge's.
+
+Locking goes through the same receiver and sender checks as a transfer to the receiver (blocked
+incoming, trusted receivers, transfer policy and transaction limit), and claimed tokens are
+receive-locked and count towards the circuit breaker's outflow like transferred ones. Both claims
+and refunds stop while the contract is paused.
+*/
+use crate::events::emit_event;
+use crate::*;
+use near_sdk::json_types::Base64VecU8;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum HtlcStatus {
+    Locked,
+    Claimed,
+    Refunded,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Htlc {
+    pub sender_id: AccountId,
+    pub receiver_id: AccountId,
+    pub amount: U128,
+    /// `sha256` of the preimage releasing the tokens.
+    pub hashlock: Base64VecU8,
+    /// Timestamp (in seconds) from which the tokens can no longer be claimed, only refunded.
+    pub timelock: u64,
+    pub status: HtlcStatus,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Locks `amount` of the caller's tokens for `receiver_id` until `timelock`. Attached deposit
+    /// must cover the storage of the HTLC, the rest is refunded. Returns the HTLC id.
+    #[payable]
+    pub fn htlc_lock(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        hashlock: Base64VecU8,
+        timelock: u64,
+    ) -> u64 {
+        self.assert_not_paused();
+        assert!(amount.0 > 0, "The amount should be a positive number");
+        assert_eq!(hashlock.0.len(), 32, "The hashlock should be a sha256 hash");
+        assert!(timelock > current_timestamp_sec(), "The timelock should be in the future");
+        let sender_id = env::predecessor_account_id();
+        self.internal_record_activity(&sender_id);
+        assert_ne!(sender_id, receiver_id, "Can't lock tokens for yourself");
+        self.token.internal_unwrap_balance_of(&receiver_id);
+        assert_ok(self.check_incoming_allowed(&sender_id, &receiver_id));
+        assert_ok(self.check_trusted_receiver(&sender_id, &receiver_id));
+        assert_ok(self.check_transfer_policy_allows(&sender_id, &receiver_id));
+        assert_ok(self.check_within_tx_limit(&sender_id, amount.0));
+        let initial_storage_usage = env::storage_usage();
+        self.internal_register_contract_account();
+        self.internal_move_balance(
+            &sender_id,
+            &env::current_account_id(),
+            amount.0,
+            Some("htlc lock".to_string()),
+        );
+        self.htlc_locked += amount.0;
+        let htlc_id = self.next_htlc_id;
+        self.next_htlc_id += 1;
+        let htlc =
+            Htlc { sender_id, receiver_id, amount, hashlock, timelock, status: HtlcStatus::Locked };
+        self.htlcs.insert(&htlc_id, &htlc);
+        emit_event(
+            "htlc_locked",
+            json!({
+                "htlc_id": htlc_id,
+                "sender_id": htlc.sender_id,
+                "receiver_id": htlc.receiver_id,
+                "amount": htlc.amount,
+                "hashlock": htlc.hashlock,
+                "timelock": timelock,
+            }),
+        );
+        refund_deposit(initial_storage_usage);
+        htlc_id
+    }
+
+    /// Releases the tokens of a locked HTLC to its receiver, if `sha256(preimage)` matches the
+    /// hashlock and the timelock hasn't passed yet.
+    pub fn htlc_claim(&mut self, htlc_id: u64, preimage: Base64VecU8) {
+        self.assert_not_paused();
+        let mut htlc = self.internal_unwrap_locked_htlc(htlc_id);
+        assert!(current_timestamp_sec() < htlc.timelock, "The HTLC has expired");
+        assert!(env::sha256(&preimage.0) == htlc.hashlock.0, "The preimage doesn't match");
+        self.internal_release_htlc(&htlc.receiver_id, htlc.amount.0, "htlc claim");
+        self.internal_lock_received(&htlc.receiver_id, htlc.amount.0);
+        self.internal_record_outflow(htlc.amount.0);
+        htlc.status = HtlcStatus::Claimed;
+        self.htlcs.insert(&htlc_id, &htlc);
+        emit_event("htlc_claimed", json!({ "htlc_id": htlc_id, "preimage": preimage }));
+    }
+
+    /// Returns the tokens of an expired, unclaimed HTLC to its sender.
+    pub fn htlc_refund(&mut self, htlc_id: u64) {
+        self.assert_not_paused();
+        let mut htlc = self.internal_unwrap_locked_htlc(htlc_id);
+        assert!(current_timestamp_sec() >= htlc.timelock, "The HTLC hasn't expired yet");
+        self.internal_release_htlc(&htlc.sender_id, htlc.amount.0, "htlc refund");
+        htlc.status = HtlcStatus::Refunded;
+        self.htlcs.insert(&htlc_id, &htlc);
+        emit_event("htlc_refunded", json!({ "htlc_id": htlc_id }));
+    }
+
+    pub fn get_htlc(&self, htlc_id: u64) -> Option<Htlc> {
+        self.htlcs.get(&htlc_id)
+    }
+
+    pub fn get_htlcs(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<(u64, Htlc)> {
+        let from_index = from_index.unwrap_or(0) as usize;
+        let limit = limit.unwrap_or(u64::MAX) as usize;
+        self.htlcs.iter().skip(from_index).take(limit).collect()
+    }
+
+    /// Total amount locked in HTLCs that are neither claimed nor refunded.
+    pub fn get_htlc_locked(&self) -> U128 {
+        self.htlc_locked.into()
+    }
+}
+
+impl Contract {
+    fn internal_unwrap_locked_htlc(&self, htlc_id: u64) -> Htlc {
+        let htlc = self.htlcs.get(&htlc_id).expect("HTLC not found");
+        assert_eq!(htlc.status, HtlcStatus::Locked, "The HTLC is already settled");
+        htlc
+    }
+
+    fn internal_release_htlc(&mut self, account_id: &AccountId, amount: Balance, memo: &str) {
+        self.htlc_locked -= amount;
+        self.internal_move_balance(
+            &env::current_account_id(),
+            account_id,
+            amount,
+            Some(memo.to_string()),
+        );
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    const PREIMAGE: &[u8] = b"secret";
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context
+            .attached_deposit(ONE_NEAR / 10)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.htlc_lock(accounts(2), 1_000.into(), env::sha256(PREIMAGE).into(), 100);
+        testing_env!(context.attached_deposit(0).predecessor_account_id(accounts(3)).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_claim() {
+        let (_, mut contract) = setup();
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, TOTAL_SUPPLY - 1_000);
+        assert_eq!(contract.get_htlc_locked().0, 1_000);
+        contract.htlc_claim(0, PREIMAGE.to_vec().into());
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 1_000);
+        assert_eq!(contract.get_htlc(0).unwrap().status, HtlcStatus::Claimed);
+        assert_eq!(contract.get_htlc_locked().0, 0);
+    }
+
+    #[test]
+    fn test_claim_is_receive_locked() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.set_receive_lock_seconds(100);
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.htlc_claim(0, PREIMAGE.to_vec().into());
+        assert_eq!(contract.receive_locked_balance_of(accounts(2)).0, 1_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't accept incoming transfers")]
+    fn test_lock_for_blocked_receiver() {
+        let (mut context, mut contract) = setup();
+        let storage = contract.storage_balance_bounds().min.0;
+        testing_env!(context.attached_deposit(storage).predecessor_account_id(accounts(3)).build());
+        contract.storage_deposit(None, None);
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(3), 1_000.into(), None);
+        testing_env!(context
+            .attached_deposit(ONE_NEAR / 10)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.block_incoming(true);
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.htlc_lock(accounts(2), 1_000.into(), env::sha256(PREIMAGE).into(), 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "The preimage doesn't match")]
+    fn test_wrong_preimage() {
+        let (_, mut contract) = setup();
+        contract.htlc_claim(0, b"guess".to_vec().into());
+    }
+
+    #[test]
+    #[should_panic(expected = "The HTLC is already settled")]
+    fn test_refund_after_expiry() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.block_timestamp(100 * NANOS).build());
+        contract.htlc_refund(0);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, TOTAL_SUPPLY);
+        assert_eq!(contract.get_htlc(0).unwrap().status, HtlcStatus::Refunded);
+        contract.htlc_claim(0, PREIMAGE.to_vec().into());
+    }
+}
@@ -0,0 +1,118 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// Same as `ft_transfer`, but with `amount` in whole tokens as a decimal string, e.g. "1.5",
+    /// converted to base units using the token's decimals.
+    #[payable]
+    pub fn ft_transfer_human(
+        &mut self,
+        receiver_id: AccountId,
+        amount: String,
+        memo: Option<String>,
+    ) {
+        self.assert_no_transfer_validator();
+        self.assert_no_attestation_gate();
+        let decimals = self.metadata.get().expect("Metadata is not set").decimals;
+        let amount = parse_decimal_amount(&amount, decimals);
+        self.assert_priority_fee_attached(&[amount]);
+        let sender_id = env::predecessor_account_id();
+        self.internal_transfer(&sender_id, &receiver_id, amount, memo);
+    }
+
+    /// Balance of `account_id` in whole tokens as a decimal string, rounded down to the display
+    /// decimals.
+    pub fn ft_balance_of_display(&self, account_id: AccountId) -> String {
+        let decimals = self.metadata.get().expect("Metadata is not set").decimals;
+        let balance = self.ft_balance_of(account_id).0;
+        format_decimal_amount(balance, decimals, self.display_decimals.unwrap_or(decimals))
+    }
+}
+
+/// Parses a non-negative decimal string such as "1.5" into base units with `decimals` decimal
+/// places. Panics on anything else, including more decimal places than `decimals`.
+pub(crate) fn parse_decimal_amount(amount: &str, decimals: u8) -> Balance {
+    let (whole, fraction) = match amount.split_once('.') {
+        Some((whole, fraction)) => (whole, fraction),
+        None => (amount, ""),
+    };
+    let is_digits = |part: &str| part.bytes().all(|byte| byte.is_ascii_digit());
+    assert!(
+        !whole.is_empty()
+            && is_digits(whole)
+            && is_digits(fraction)
+            && (!fraction.is_empty() || !amount.contains('.')),
+        "Invalid amount {:?}",
+        amount
+    );
+    assert!(fraction.len() <= decimals as usize, "The amount has more than {} decimals", decimals);
+    let padded_fraction = format!("{:0<width$}", fraction, width = decimals as usize);
+    format!("{}{}", whole, padded_fraction).parse().expect("The amount is too large")
+}
+
+/// Formats `amount` base units with `decimals` decimal places as a decimal string with
+/// `display_decimals` decimal places, rounding down.
+pub(crate) fn format_decimal_amount(amount: Balance, decimals: u8, display_decimals: u8) -> String {
+    let digits = format!("{:0>width$}", amount, width = decimals as usize + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals as usize);
+    if display_decimals == 0 {
+        return whole.to_string();
+    }
+    format!("{}.{}", whole, &fraction[..display_decimals as usize])
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_parse_decimal_amount() {
+        assert_eq!(parse_decimal_amount("1.5", 6), 1_500_000);
+        assert_eq!(parse_decimal_amount("0.000001", 6), 1);
+        assert_eq!(parse_decimal_amount("42", 2), 4_200);
+        assert_eq!(parse_decimal_amount("7", 0), 7);
+    }
+
+    #[test]
+    fn test_format_decimal_amount() {
+        assert_eq!(format_decimal_amount(1_567_890, 6, 2), "1.56");
+        assert_eq!(format_decimal_amount(1, 6, 6), "0.000001");
+        assert_eq!(format_decimal_amount(4_200, 2, 0), "42");
+        assert_eq!(format_decimal_amount(0, 24, 3), "0.000");
+    }
+
+    #[test]
+    #[should_panic(expected = "The amount has more than 2 decimals")]
+    fn test_parse_too_many_decimals() {
+        parse_decimal_amount("1.005", 2);
+    }
+
+    #[test]
+    fn test_parse_malformed_amounts() {
+        for amount in ["", "-1", "+1", "1.", ".5", "1.2.3", "1e5", " 1", "1,5"] {
+            assert!(std::panic::catch_unwind(|| parse_decimal_amount(amount, 6)).is_err());
+        }
+    }
+
+    #[test]
+    fn test_transfer_human() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        let decimals = contract.ft_metadata().decimals;
+        contract.ft_transfer_human(accounts(2), "0.00000000015".to_string(), None);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 15 * 10u128.pow(decimals as u32 - 11));
+        contract.update_metadata_display_decimals(Some(10));
+        assert_eq!(contract.ft_balance_of_display(accounts(2)), "0.0000000001");
+    }
+}
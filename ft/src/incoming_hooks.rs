@@ -0,0 +1,102 @@
+/*!
+Push notifications for incoming transfers. A receiver can register a contract and method that get
+called whenever it receives tokens, without having to implement `ft_on_transfer` or be the target
+of `ft_transfer_call`.
+
+The call is fire-and-forget: it runs in its own receipt after the transfer is done, so a failing
+hook never reverts the transfer. Senders need to attach `GAS_FOR_INCOMING_HOOK` on top of the
+usual gas when the receiver has a hook configured.
+*/
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+
+pub const GAS_FOR_INCOMING_HOOK: Gas = Gas(5_000_000_000_000);
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct IncomingHook {
+    pub contract_id: AccountId,
+    pub method_name: String,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Registers `contract_id.method_name` to be called with
+    /// `{ "sender_id", "receiver_id", "amount", "memo" }` whenever the caller receives tokens.
+    /// Attached deposit must cover the added storage, the rest is refunded.
+    #[payable]
+    pub fn set_incoming_hook(&mut self, contract_id: AccountId, method_name: String) {
+        assert!(!method_name.is_empty(), "The method name can't be empty");
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        self.token.internal_unwrap_balance_of(&account_id);
+        let initial_storage_usage = env::storage_usage();
+        self.incoming_hooks.insert(&account_id, &IncomingHook { contract_id, method_name });
+        refund_deposit(initial_storage_usage);
+    }
+
+    #[payable]
+    pub fn clear_incoming_hook(&mut self) {
+        assert_one_yocto();
+        self.internal_record_activity(&env::predecessor_account_id());
+        self.incoming_hooks.remove(&env::predecessor_account_id());
+    }
+
+    pub fn get_incoming_hook(&self, account_id: AccountId) -> Option<IncomingHook> {
+        self.incoming_hooks.get(&account_id)
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_notify_incoming_hook(
+        &self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Balance,
+        memo: Option<String>,
+    ) {
+        if let Some(hook) = self.incoming_hooks.get(receiver_id) {
+            let args = json!({
+                "sender_id": sender_id,
+                "receiver_id": receiver_id,
+                "amount": U128(amount),
+                "memo": memo,
+            });
+            Promise::new(hook.contract_id).function_call(
+                hook.method_name,
+                args.to_string().into_bytes(),
+                0,
+                GAS_FOR_INCOMING_HOOK,
+            );
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, get_created_receipts};
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_transfer_notifies_receiver_hook() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        testing_env!(context
+            .attached_deposit(ONE_NEAR / 100)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        contract.set_incoming_hook(accounts(3), "on_deposit".to_string());
+
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), 10.into(), None);
+        let receipts = get_created_receipts();
+        assert!(receipts.iter().any(|receipt| receipt.receiver_id == accounts(3)));
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 10);
+    }
+}
@@ -0,0 +1,123 @@
+/*!
+Cap on the amount minted over any rolling 365 days, as a share of the supply excluding the tokens
+minted within that window.
+
+Mints are bucketed by day. The running total of the window is kept in state and the buckets that
+fall out of the window are dropped on the next mint, so a mint touches at most a year's worth of
+buckets and usually none. Mints are only tracked while the cap is set.
+*/
+use crate::math::{bps_of, BPS_DENOMINATOR};
+use crate::*;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+const DAYS_PER_YEAR: u64 = 365;
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the cap in basis points, or removes it. Setting a new cap starts a fresh window.
+    pub fn set_max_annual_inflation_bps(&mut self, max_annual_inflation_bps: Option<u16>) {
+        self.assert_owner_action();
+        if let Some(bps) = max_annual_inflation_bps {
+            assert!(bps <= BPS_DENOMINATOR, "The cap can't exceed {} bps", BPS_DENOMINATOR);
+        }
+        self.internal_expire_inflation_window(u64::MAX);
+        self.max_annual_inflation_bps = max_annual_inflation_bps;
+    }
+
+    pub fn get_max_annual_inflation_bps(&self) -> Option<u16> {
+        self.max_annual_inflation_bps
+    }
+
+    /// How much can still be minted within the current 365-day window, or `None` if there's no
+    /// cap.
+    pub fn get_annual_mint_headroom(&self) -> Option<U128> {
+        let bps = self.max_annual_inflation_bps?;
+        let minted = self.minted_in_last_year();
+        let allowed = bps_of(self.token.total_supply.saturating_sub(minted), bps);
+        Some(allowed.saturating_sub(minted).into())
+    }
+}
+
+impl Contract {
+    fn minted_in_last_year(&self) -> Balance {
+        let expired_before = current_day().saturating_sub(DAYS_PER_YEAR - 1);
+        let last_expired = expired_before.min(self.inflation_last_day + 1);
+        (self.inflation_oldest_day..last_expired).fold(self.inflation_window_total, |total, day| {
+            total - self.inflation_minted.get(&day).unwrap_or(0)
+        })
+    }
+
+    /// Drops the buckets of the days before `expired_before`.
+    fn internal_expire_inflation_window(&mut self, expired_before: u64) {
+        let last_expired = expired_before.min(self.inflation_last_day + 1);
+        while self.inflation_oldest_day < last_expired {
+            if let Some(amount) = self.inflation_minted.remove(&self.inflation_oldest_day) {
+                self.inflation_window_total -= amount;
+            }
+            self.inflation_oldest_day += 1;
+        }
+        if self.inflation_window_total == 0 {
+            self.inflation_oldest_day = current_day();
+        }
+    }
+
+    /// Counts `amount` towards the current window. Panics if it exceeds the cap.
+    pub(crate) fn internal_record_annual_mint(&mut self, amount: Balance) {
+        if self.max_annual_inflation_bps.is_none() {
+            return;
+        }
+        let headroom = self.get_annual_mint_headroom().unwrap().0;
+        assert!(amount <= headroom, "The mint exceeds the annual inflation cap");
+        let day = current_day();
+        self.internal_expire_inflation_window(day.saturating_sub(DAYS_PER_YEAR - 1));
+        let minted = self.inflation_minted.get(&day).unwrap_or(0);
+        self.inflation_minted.insert(&day, &(minted + amount));
+        self.inflation_window_total += amount;
+        self.inflation_last_day = day;
+    }
+}
+
+fn current_day() -> u64 {
+    current_timestamp_sec() / SECONDS_PER_DAY
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    const DAY: u64 = SECONDS_PER_DAY * NANOS;
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.block_timestamp(1_000 * DAY).build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_max_annual_inflation_bps(Some(1_000));
+        contract.internal_mint(&accounts(1), TOTAL_SUPPLY / 20, "mint");
+        (context, contract)
+    }
+
+    #[test]
+    fn test_headroom_over_the_year() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.block_timestamp(1_200 * DAY).build());
+        contract.internal_mint(&accounts(1), TOTAL_SUPPLY / 20, "mint");
+        assert_eq!(contract.get_annual_mint_headroom(), Some(0.into()));
+        testing_env!(context.block_timestamp(1_365 * DAY).build());
+        // The first mint is now part of the supply the cap is based on.
+        assert_eq!(contract.get_annual_mint_headroom(), Some((TOTAL_SUPPLY * 11 / 200).into()));
+        testing_env!(context.block_timestamp(1_565 * DAY).build());
+        assert!(contract.get_annual_mint_headroom().unwrap().0 > TOTAL_SUPPLY / 10);
+        contract.internal_mint(&accounts(1), TOTAL_SUPPLY / 10, "mint");
+    }
+
+    #[test]
+    #[should_panic(expected = "The mint exceeds the annual inflation cap")]
+    fn test_mint_above_cap() {
+        let (_, mut contract) = setup();
+        contract.internal_mint(&accounts(1), TOTAL_SUPPLY / 20 + 1, "mint");
+    }
+}
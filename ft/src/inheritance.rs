@@ -0,0 +1,193 @@
+/*!
+Dead-man's-switch for estate planning. An account designates a beneficiary and an inactivity
+period; once the account hasn't acted for that long, the beneficiary can claim the whole balance.
+Any transfer sent by the account resets the timer, and so does any other call changing its state:
+staking, approvals, claims, sub-ledger moves, settings and so on.
+*/
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+
+/// Shortest inactivity period an account can configure, so a beneficiary can't be handed the
+/// balance of an account that simply went quiet for a few days.
+pub const MIN_INACTIVITY_PERIOD_SEC: u64 = 30 * 24 * 60 * 60;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Inheritance {
+    pub beneficiary_id: AccountId,
+    /// How long (in seconds) the account must stay inactive before the beneficiary can claim.
+    pub inactivity_period: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Designates `beneficiary_id` to inherit the caller's balance after `inactivity_period`
+    /// seconds without any activity of the caller. Replaces any previous designation. Requires
+    /// at least 1 yoctoNEAR attached, which must also cover the added storage, the rest is
+    /// refunded.
+    #[payable]
+    pub fn set_inheritance(&mut self, beneficiary_id: AccountId, inactivity_period: u64) {
+        assert_at_least_one_yocto();
+        let account_id = env::predecessor_account_id();
+        assert_ne!(account_id, beneficiary_id, "Can't designate yourself as the beneficiary");
+        assert!(
+            inactivity_period >= MIN_INACTIVITY_PERIOD_SEC,
+            "The inactivity period must be at least {} seconds",
+            MIN_INACTIVITY_PERIOD_SEC
+        );
+        self.token.internal_unwrap_balance_of(&account_id);
+        let initial_storage_usage = env::storage_usage();
+        self.inheritances.insert(&account_id, &Inheritance { beneficiary_id, inactivity_period });
+        self.internal_record_activity(&account_id);
+        refund_deposit(initial_storage_usage);
+    }
+
+    /// Removes the caller's beneficiary designation.
+    #[payable]
+    pub fn clear_inheritance(&mut self) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        assert!(self.inheritances.remove(&account_id).is_some(), "No inheritance is set");
+        self.internal_record_activity(&account_id);
+    }
+
+    /// Transfers the whole balance of `deceased_account_id` to the caller, if the caller is its
+    /// designated beneficiary and the account has been inactive for the configured period.
+    /// Returns the amount transferred.
+    #[payable]
+    pub fn claim_inheritance(&mut self, deceased_account_id: AccountId) -> U128 {
+        assert_one_yocto();
+        let beneficiary_id = env::predecessor_account_id();
+        self.internal_record_activity(&beneficiary_id);
+        let inheritance =
+            self.inheritances.get(&deceased_account_id).expect("No inheritance is set");
+        assert_eq!(
+            inheritance.beneficiary_id, beneficiary_id,
+            "Only the designated beneficiary can claim"
+        );
+        let last_activity = self.last_activity.get(&deceased_account_id).unwrap_or(0);
+        assert!(
+            current_timestamp_sec() >= last_activity + inheritance.inactivity_period,
+            "The account has been active within the inactivity period"
+        );
+        let balance = self.token.internal_unwrap_balance_of(&deceased_account_id);
+        assert!(balance > 0, "Nothing to claim");
+        self.internal_move_balance(
+            &deceased_account_id,
+            &beneficiary_id,
+            balance,
+            Some("inheritance".to_string()),
+        );
+        balance.into()
+    }
+
+    pub fn inheritance_of(&self, account_id: AccountId) -> Option<Inheritance> {
+        self.inheritances.get(&account_id)
+    }
+
+    /// Timestamp (in seconds) of the last activity of the account, if any.
+    pub fn last_activity_of(&self, account_id: AccountId) -> Option<u64> {
+        self.last_activity.get(&account_id)
+    }
+}
+
+impl Contract {
+    /// Resets the inactivity timer of `account_id`. Only registered accounts are tracked, their
+    /// registration covers the storage.
+    pub(crate) fn internal_record_activity(&mut self, account_id: &AccountId) {
+        if self.token.accounts.contains_key(account_id) {
+            self.last_activity.insert(account_id, &current_timestamp_sec());
+        }
+    }
+
+    /// Adds the storage of an activity entry for the longest possible account ID to the storage
+    /// required to register an account.
+    pub(crate) fn measure_activity_storage_usage(&mut self) {
+        let initial_storage_usage = env::storage_usage();
+        let tmp_account_id = AccountId::new_unchecked("a".repeat(64));
+        self.last_activity.insert(&tmp_account_id, &0);
+        self.token.account_storage_usage += env::storage_usage() - initial_storage_usage;
+        self.last_activity.remove(&tmp_account_id);
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context
+            .attached_deposit(ONE_NEAR / 100)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.set_inheritance(accounts(2), MIN_INACTIVITY_PERIOD_SEC);
+        (context, contract)
+    }
+
+    #[test]
+    fn test_claim_inheritance_after_inactivity() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(MIN_INACTIVITY_PERIOD_SEC * NANOS)
+            .build());
+        assert_eq!(contract.claim_inheritance(accounts(1)).0, TOTAL_SUPPLY);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 0);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of at least 1 yoctoNEAR")]
+    fn test_replacing_beneficiary_requires_deposit() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.attached_deposit(0).build());
+        contract.set_inheritance(accounts(3), MIN_INACTIVITY_PERIOD_SEC);
+    }
+
+    #[test]
+    #[should_panic(expected = "The account has been active within the inactivity period")]
+    fn test_transfer_resets_inactivity_timer() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .attached_deposit(1)
+            .block_timestamp(MIN_INACTIVITY_PERIOD_SEC / 2 * NANOS)
+            .build());
+        contract.ft_transfer(accounts(2), 1.into(), None);
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(MIN_INACTIVITY_PERIOD_SEC * NANOS)
+            .build());
+        contract.claim_inheritance(accounts(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "The account has been active within the inactivity period")]
+    fn test_approval_resets_inactivity_timer() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .attached_deposit(ONE_NEAR / 100)
+            .block_timestamp(MIN_INACTIVITY_PERIOD_SEC / 2 * NANOS)
+            .build());
+        contract.ft_approve(accounts(3), 1.into());
+        assert_eq!(contract.last_activity_of(accounts(1)), Some(MIN_INACTIVITY_PERIOD_SEC / 2));
+        testing_env!(context
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(MIN_INACTIVITY_PERIOD_SEC * NANOS)
+            .build());
+        contract.claim_inheritance(accounts(1));
+    }
+}
@@ -0,0 +1,139 @@
+/*!
+Insurance fund fed by the transfer fees. While configured, `fee_share_bps` of every auto-LP fee
+goes to the fund instead of the liquidity accumulator. The fund is held by the contract account,
+apart from the treasury and the circulating supply, and the owner or the risk manager can draw
+from it to cover shortfalls, one capped draw at a time and always with a reason.
+*/
+use crate::events::emit_event;
+use crate::math::{bps_of, BPS_DENOMINATOR};
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+
+/// Longest accepted reason for a draw.
+pub const MAX_DRAW_REASON_LEN: usize = 256;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Insurance {
+    /// Account allowed to draw from the fund alongside the owner.
+    pub risk_manager_id: Option<AccountId>,
+    /// Share of the auto-LP fees going to the fund, in basis points.
+    pub fee_share_bps: u16,
+    /// Largest single draw.
+    pub max_draw: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Configures the fund, or stops feeding it when `None`. The balance of the fund is kept
+    /// either way.
+    pub fn set_insurance(&mut self, insurance: Option<Insurance>) {
+        self.assert_owner_action();
+        if let Some(insurance) = insurance.as_ref() {
+            assert!(
+                insurance.fee_share_bps + self.treasury_fee_share_bps <= BPS_DENOMINATOR,
+                "The treasury and insurance shares can't exceed {} bps",
+                BPS_DENOMINATOR
+            );
+        }
+        self.insurance = insurance;
+    }
+
+    pub fn get_insurance(&self) -> Option<Insurance> {
+        self.insurance.clone()
+    }
+
+    pub fn insurance_fund_balance(&self) -> U128 {
+        self.insurance_fund.into()
+    }
+
+    /// Pays `amount` out of the fund to `to`. Only callable by the owner or the risk manager.
+    pub fn draw_from_insurance(&mut self, to: AccountId, amount: U128, reason: String) {
+        let insurance = self.insurance.clone().expect("The insurance fund is not configured");
+        if Some(env::predecessor_account_id()) != insurance.risk_manager_id {
+            self.assert_owner_action();
+        }
+        assert!(
+            !reason.is_empty() && reason.len() <= MAX_DRAW_REASON_LEN,
+            "The reason should be between 1 and {} bytes long",
+            MAX_DRAW_REASON_LEN
+        );
+        assert!(amount.0 > 0, "The amount should be a positive number");
+        assert!(amount.0 <= insurance.max_draw.0, "The draw exceeds the limit per draw");
+        assert!(amount.0 <= self.insurance_fund, "The insurance fund is too low");
+        self.insurance_fund -= amount.0;
+        self.internal_move_balance(
+            &env::current_account_id(),
+            &to,
+            amount.0,
+            Some("insurance draw".to_string()),
+        );
+        emit_event(
+            "insurance_draw",
+            json!({ "account_id": to, "amount": amount, "reason": reason }),
+        );
+    }
+}
+
+impl Contract {
+    /// Moves the fund's share of `fee`, already held by the contract account, to the fund and
+    /// returns it.
+    pub(crate) fn internal_take_insurance_share(&mut self, fee: Balance) -> Balance {
+        let share = match self.insurance.as_ref() {
+            Some(insurance) => bps_of(fee, insurance.fee_share_bps),
+            None => return 0,
+        };
+        self.insurance_fund += share;
+        share
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_auto_lp(accounts(4), (TOTAL_SUPPLY / 2).into(), 100);
+        contract.set_insurance(Some(Insurance {
+            risk_manager_id: Some(accounts(3)),
+            fee_share_bps: 5_000,
+            max_draw: 20.into(),
+        }));
+        for account_id in [accounts(2), accounts(5)] {
+            testing_env!(context
+                .attached_deposit(contract.storage_balance_bounds().min.into())
+                .predecessor_account_id(account_id)
+                .build());
+            contract.storage_deposit(None, None);
+        }
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), 10_000.into(), None);
+        testing_env!(context.attached_deposit(0).predecessor_account_id(accounts(3)).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_fees_feed_the_fund() {
+        let (_, mut contract) = setup();
+        assert_eq!(contract.insurance_fund_balance().0, 50);
+        assert_eq!(contract.get_auto_lp().unwrap().accumulated.0, 50);
+        contract.draw_from_insurance(accounts(5), 20.into(), "bad debt".to_string());
+        assert_eq!(contract.insurance_fund_balance().0, 30);
+        assert_eq!(contract.ft_balance_of(accounts(5)).0, 20);
+    }
+
+    #[test]
+    #[should_panic(expected = "The draw exceeds the limit per draw")]
+    fn test_draw_above_limit() {
+        let (_, mut contract) = setup();
+        contract.draw_from_insurance(accounts(5), 21.into(), "bad debt".to_string());
+    }
+}
@@ -15,20 +15,509 @@ NOTES:
   - To prevent the deployed contract from being modified or deleted, it should not have any access
     keys on its account.
 */
+use near_contract_standards::fungible_token::core::FungibleTokenCore;
+use near_contract_standards::fungible_token::events::{FtBurn, FtMint};
 use near_contract_standards::fungible_token::metadata::{
     FungibleTokenMetadata, FungibleTokenMetadataProvider, FT_METADATA_SPEC,
 };
+use near_contract_standards::fungible_token::receiver::{ext_ft_receiver, FungibleTokenReceiver};
+use near_contract_standards::fungible_token::resolver::{ext_ft_resolver, FungibleTokenResolver};
 use near_contract_standards::fungible_token::FungibleToken;
+use near_contract_standards::storage_management::{
+    StorageBalance, StorageBalanceBounds, StorageManagement,
+};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::LazyOption;
+use near_sdk::collections::{LazyOption, LookupMap, LookupSet, UnorderedMap, UnorderedSet};
 use near_sdk::json_types::U128;
-use near_sdk::{env, log, near_bindgen, AccountId, Balance, PanicOnDefault, PromiseOrValue};
+use near_sdk::{
+    assert_one_yocto, env, log, near_bindgen, AccountId, Balance, BorshStorageKey, Gas,
+    PanicOnDefault, Promise, PromiseOrValue, StorageUsage,
+};
+
+use crate::allowance_log::{AllowanceLogRange, AllowanceSpend};
+use crate::attestation_gate::AttestationGate;
+use crate::auto_lp::AutoLp;
+use crate::balance_alerts::BalanceAlert;
+use crate::balance_proofs::BALANCES_PREFIX;
+use crate::batch_transfers::DeferredTransferEvent;
+use crate::circuit_breaker::CircuitBreaker;
+use crate::conversions::Conversion;
+use crate::demurrage::Demurrage;
+use crate::donations::Donation;
+use crate::faucet::Faucet;
+use crate::fee_token::FeeToken;
+use crate::hold_streak::HoldBoost;
+use crate::htlc::Htlc;
+use crate::incoming_hooks::IncomingHook;
+use crate::inheritance::Inheritance;
+use crate::insurance::Insurance;
+use crate::memo::check_memo;
+use crate::metadata_updates::MetadataMirror;
+use crate::mint_queue::MintRequest;
+use crate::multisig::{Multisig, Proposal};
+use crate::personhood::PersonhoodGate;
+use crate::presale::Sale;
+use crate::priority_fee::PriorityFeeSchedule;
+use crate::referrals::ReferralProgram;
+use crate::reserve_attestations::ReserveAttestation;
+use crate::reserve_ratio::ReserveRequirement;
+use crate::rewards::RewardCheckpoint;
+use crate::settlement_holds::SettlementHold;
+use crate::stabilizer::Stabilizer;
+use crate::stable::StablePeg;
+use crate::staking::{LockTier, StakePosition};
+use crate::storage_in_token::StorageTokenFee;
+use crate::streaming_allowances::StreamingAllowance;
+use crate::supply_history::{SupplyCheckpoint, DEFAULT_SUPPLY_HISTORY_CAPACITY};
+use crate::transfer_policy::TransferPolicy;
+use crate::transfer_receipts::{TransferReceipt, DEFAULT_RECEIPT_CAPACITY};
+use crate::treasury::TreasurySpend;
+use crate::vote_escrow::VoteLock;
+
+mod account_tags;
+mod admin_cooldown;
+mod airdrop;
+mod allowance_log;
+mod allowances;
+mod attestation_gate;
+mod auto_lp;
+mod balance_alerts;
+mod balance_privacy;
+mod balance_proofs;
+mod batch_burns;
+mod batch_transfers;
+mod block_gap;
+mod blocked_incoming;
+mod bridge;
+mod bulk_operations;
+mod change_feed;
+mod circuit_breaker;
+mod clawback;
+mod collateral;
+mod committed_transfers;
+mod conversions;
+mod delegation;
+mod demurrage;
+mod distribution;
+mod donations;
+mod dust;
+mod events;
+mod faucet;
+mod fee_token;
+mod forwarding;
+mod giveaway;
+mod global_spenders;
+mod hold_streak;
+mod holders;
+mod htlc;
+mod human_amounts;
+mod incoming_hooks;
+mod inflation_cap;
+mod inheritance;
+mod insurance;
+mod math;
+mod memo;
+mod metadata_updates;
+mod migration;
+mod mint_ceiling;
+mod mint_queue;
+mod multi_transfer_call;
+mod multisig;
+mod own_token_recovery;
+mod owner;
+mod pause;
+mod personhood;
+mod presale;
+mod priority_fee;
+mod receive_lock;
+mod referrals;
+mod registration_cost;
+mod registration_observer;
+mod relayed_transfers;
+mod reserve_attestations;
+mod reserve_ratio;
+mod rewards;
+mod settlement_holds;
+mod signatures;
+mod stabilizer;
+mod stable;
+mod staking;
+mod storage_in_token;
+mod streaming_allowances;
+mod sub_ledger;
+mod supply_freeze;
+mod supply_history;
+mod supply_report;
+mod tiers;
+mod time_locks;
+mod token_gating;
+mod transfer_call_gas;
+mod transfer_counts;
+mod transfer_policy;
+mod transfer_receipts;
+mod transfer_validator;
+mod treasury;
+mod trusted_receivers;
+mod twab;
+mod tx_limit;
+mod vote_escrow;
+
+const GAS_FOR_RESOLVE_TRANSFER: Gas = Gas(5_000_000_000_000);
+const GAS_FOR_FT_TRANSFER_CALL: Gas = Gas(25_000_000_000_000 + GAS_FOR_RESOLVE_TRANSFER.0);
+
+#[derive(BorshStorageKey, BorshSerialize)]
+pub(crate) enum StorageKey {
+    LastActivity,
+    Inheritances,
+    Minters,
+    MintRequests,
+    SaleContributions,
+    StrictReceivers,
+    TrustedReceivers,
+    TrustedReceiversPerAccount { account_hash: Vec<u8> },
+    IncomingHooks,
+    LastReceiveBlock,
+    LastSendBlock,
+    MarketMakers,
+    TokenFundedAccounts,
+    Referrers,
+    ReferralEarnings,
+    Holders,
+    RewardCheckpoints,
+    InflationMinted,
+    Allowances,
+    AllowanceLog,
+    AllowanceLogRanges,
+    Stakes,
+    PrivateBalances,
+    BalanceViewers,
+    TimeLocks,
+    TransfersSent,
+    TransfersReceived,
+    ProcessedBridgeTxs,
+    ForwardTo,
+    TxLimitExempt,
+    BalanceHistory,
+    GlobalSpenders,
+    PendingSettlement,
+    HoldSince,
+    AttestedUntil,
+    SupplyHistory,
+    ReceiveLockExempt,
+    ReceivedLocks,
+    BlockedIncoming,
+    AirdropClaims,
+    Proposals,
+    Receipts,
+    MaxAllowances,
+    RelayKeys,
+    VoteLocks,
+    TransferPolicies,
+    TransferDecisions,
+    LastDemurrageTs,
+    Burners,
+    BatchBurnable,
+    Delegates,
+    Delegators,
+    DelegationHistory,
+    VerifiedHumans,
+    ChangeLog,
+    PersonalDonations,
+    Htlcs,
+    BulkCursors,
+    AccountTags,
+    AllowanceWindows,
+    Tiers,
+    TransferCommitments,
+    StreamingAllowances,
+    TreasurySpends,
+    MinIncoming,
+    FaucetDrips,
+    BlockTransferCounts,
+    Conversions,
+    BalanceAlerts,
+    SubBalances,
+    SubAllocated,
+    FeeTokenDeposits,
+    LendingContracts,
+    CollateralLocks,
+    CollateralLocked,
+    RegistrationPaid,
+    MigrationAccounts,
+}
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct Contract {
     token: FungibleToken,
     metadata: LazyOption<FungibleTokenMetadata>,
+    owner_id: AccountId,
+    /// Timestamp (in seconds) of the last transfer initiated by each account.
+    last_activity: LookupMap<AccountId, u64>,
+    /// Beneficiaries designated by accounts to claim their balance after a period of inactivity.
+    inheritances: LookupMap<AccountId, Inheritance>,
+    /// Accounts allowed to propose mints for the owner to approve.
+    minters: UnorderedSet<AccountId>,
+    mint_requests: UnorderedMap<u64, MintRequest>,
+    next_mint_request_id: u64,
+    /// How long (in seconds) a mint request stays approvable.
+    mint_request_ttl: u64,
+    /// Paired stable token this token can be converted to and from at a fixed rate.
+    stable_peg: Option<StablePeg>,
+    /// Amount of the stable token held by the contract to back redemptions.
+    stable_reserve: Balance,
+    sale: Option<Sale>,
+    /// NEAR contributed to the sale by each account that hasn't claimed or been refunded yet.
+    sale_contributions: LookupMap<AccountId, Balance>,
+    /// Presale tokens held by the contract account until contributors claim them.
+    sale_tokens_held: Balance,
+    /// Accounts that only allow sending to receivers they've explicitly trusted.
+    strict_receivers: LookupSet<AccountId>,
+    trusted_receivers: LookupMap<AccountId, UnorderedSet<AccountId>>,
+    /// Once set, the total supply can never change again.
+    supply_frozen: bool,
+    /// Contracts to notify about incoming transfers, configured by each receiver.
+    incoming_hooks: LookupMap<AccountId, IncomingHook>,
+    /// Minimum number of blocks between receiving and sending (or the other way round) for the
+    /// same account. Zero disables the check.
+    min_block_gap: u64,
+    last_receive_block: LookupMap<AccountId, u64>,
+    last_send_block: LookupMap<AccountId, u64>,
+    /// Accounts exempt from the block gap.
+    market_makers: LookupSet<AccountId>,
+    /// Price of registration in tokens. Registration in tokens is disabled if not set.
+    storage_token_fee: Option<StorageTokenFee>,
+    /// NEAR set aside to cover the storage of accounts registered in tokens.
+    storage_reserve: Balance,
+    /// Accounts whose storage is covered by the reserve.
+    token_funded_accounts: LookupSet<AccountId>,
+    /// While set, transfers, mints and burns are rejected.
+    paused: bool,
+    /// Account allowed to pause the contract besides the owner.
+    guardian_id: Option<AccountId>,
+    circuit_breaker: Option<CircuitBreaker>,
+    /// Referrer of each account that named one.
+    referrers: LookupMap<AccountId, AccountId>,
+    /// Total referral rewards received by each referrer.
+    referral_earnings: LookupMap<AccountId, Balance>,
+    referral_program: ReferralProgram,
+    /// Registered accounts, so they can be iterated.
+    holders: UnorderedSet<AccountId>,
+    auto_lp: Option<AutoLp>,
+    /// Contract that has to approve every transfer, if any.
+    transfer_validator: Option<AccountId>,
+    /// Most that can be minted within a single block, if limited.
+    max_mint_per_block: Option<Balance>,
+    minted_block_height: u64,
+    minted_this_block: Balance,
+    /// Rewards distributed per token held, scaled by the reward precision.
+    reward_per_token: Balance,
+    /// Distributed rewards held by the contract account and not yet paid out.
+    rewards_pool: Balance,
+    reward_checkpoints: LookupMap<AccountId, RewardCheckpoint>,
+    /// Whether pending rewards are paid out on every transfer.
+    auto_claim_rewards: bool,
+    /// Most that can be minted over 365 days, in basis points of the supply, if capped.
+    max_annual_inflation_bps: Option<u16>,
+    /// Amount minted on each day of the current window.
+    inflation_minted: LookupMap<u64, Balance>,
+    inflation_window_total: Balance,
+    inflation_oldest_day: u64,
+    inflation_last_day: u64,
+    /// Amount each spender can still transfer out of each owner's account.
+    allowances: LookupMap<(AccountId, AccountId), Balance>,
+    /// Allowance spends of each owner, keyed by owner and sequence number.
+    allowance_log: LookupMap<(AccountId, u64), AllowanceSpend>,
+    allowance_log_ranges: LookupMap<AccountId, AllowanceLogRange>,
+    /// Number of allowance spends kept per owner.
+    allowance_log_capacity: u64,
+    lock_tiers: Vec<LockTier>,
+    /// Staking APR before the tier multiplier, in basis points.
+    base_apr_bps: u16,
+    /// Active stake positions of each account.
+    stakes: LookupMap<AccountId, Vec<StakePosition>>,
+    next_stake_id: u64,
+    /// Sum of all active stakes, held by the contract account.
+    total_staked: Balance,
+    /// Contract the metadata is pushed to after every update, if any.
+    metadata_mirror_target: Option<MetadataMirror>,
+    /// Accounts whose balance reads as zero from `ft_balance_of`.
+    private_balances: LookupSet<AccountId>,
+    /// Ed25519 keys of the viewers each account authorized to read its private balance.
+    balance_viewers: LookupMap<(AccountId, AccountId), Vec<u8>>,
+    /// Minimum number of seconds between privileged owner actions.
+    admin_cooldown: u64,
+    last_admin_action_ts: u64,
+    /// Ed25519 key of the custodian attesting off-chain reserves.
+    reserve_custodian_key: Option<Vec<u8>>,
+    latest_attestation: Option<ReserveAttestation>,
+    /// Unlock points of the locked parts of each account's balance, sorted by timestamp.
+    time_locks: LookupMap<AccountId, Vec<(u64, Balance)>>,
+    /// Least gas reserved for the receiver of `ft_transfer_call`.
+    transfer_call_gas: Gas,
+    /// Number of transfers each account has sent.
+    transfers_sent: LookupMap<AccountId, u64>,
+    /// Number of transfers each account has received.
+    transfers_received: LookupMap<AccountId, u64>,
+    /// Timestamp (in seconds) of the initialization.
+    deployed_at: u64,
+    /// How long (in seconds) after initialization the owner can claw back tokens.
+    clawback_window: u64,
+    /// Storage a registration uses per character of the account ID.
+    storage_per_account_id_byte: StorageUsage,
+    /// Account allowed to release bridged tokens.
+    bridge_id: Option<AccountId>,
+    /// Tokens locked for other chains, held by the contract account.
+    bridge_locked: Balance,
+    /// Source transactions the bridge already released tokens for.
+    processed_bridge_txs: LookupSet<String>,
+    /// Account each account forwards its incoming transfers to.
+    forward_to: LookupMap<AccountId, AccountId>,
+    /// Largest transfer in basis points of the total supply. Zero disables the limit.
+    max_tx_bps: u16,
+    /// Accounts that can send transfers over the limit, besides the owner.
+    tx_limit_exempt: LookupSet<AccountId>,
+    /// Recent balance changes of each account as `(timestamp in seconds, new balance)`.
+    balance_history: LookupMap<AccountId, Vec<(u64, Balance)>>,
+    /// Number of balance changes kept per account.
+    balance_history_capacity: u64,
+    /// Protocols that can transfer out of any account.
+    global_spenders: LookupSet<AccountId>,
+    /// Total amount global spenders can still transfer.
+    global_spend_limit: Balance,
+    stabilizer: Option<Stabilizer>,
+    /// Tokens minted by supply expansions and held by the contract account, not circulating.
+    stabilization_pool: Balance,
+    /// Threshold and period of the holds on large transfers, if enabled.
+    settlement_hold: Option<SettlementHold>,
+    /// Storage of a new hold entry for the longest possible account ID.
+    settlement_hold_storage_usage: StorageUsage,
+    /// Holds on each account's balance as `(amount, release timestamp in seconds)`.
+    pending_settlement: LookupMap<AccountId, Vec<(Balance, u64)>>,
+    /// Start of each account's hold streak, in seconds.
+    hold_since: LookupMap<AccountId, u64>,
+    hold_boost: Option<HoldBoost>,
+    attestation_gate: Option<AttestationGate>,
+    /// Gate generation and expiry (in seconds) of each account's cached attestation.
+    attested_until: LookupMap<AccountId, (u64, u64)>,
+    /// Bumped whenever the gate changes, invalidating the cached attestations.
+    attestation_epoch: u64,
+    /// Checkpoints of the total supply by sequence number.
+    supply_history: LookupMap<u64, SupplyCheckpoint>,
+    /// Sequence numbers of the oldest checkpoint and of the next one.
+    supply_history_first: u64,
+    supply_history_next: u64,
+    supply_history_capacity: u64,
+    receive_lock_seconds: u64,
+    receive_lock_exempt: LookupSet<AccountId>,
+    /// Locked amount, and the timestamps (in seconds) the lock started and ends at.
+    received_locks: LookupMap<AccountId, (Balance, u64, u64)>,
+    consolidated_batch_events: bool,
+    /// Transfer events held back while a consolidated batch runs. Never stored.
+    #[borsh_skip]
+    deferred_transfer_events: Option<Vec<DeferredTransferEvent>>,
+    /// End (in seconds) of a scheduled pause.
+    paused_until: Option<u64>,
+    blocked_incoming: LookupSet<AccountId>,
+    airdrop_root: Option<Vec<u8>>,
+    airdrop_pool: Balance,
+    /// Hashes of the claimed leaves together with the root they were claimed under.
+    airdrop_claims: LookupSet<Vec<u8>>,
+    insurance: Option<Insurance>,
+    insurance_fund: Balance,
+    priority_fee_schedule: Option<PriorityFeeSchedule>,
+    multisig: Option<Multisig>,
+    proposals: UnorderedMap<u64, Proposal>,
+    next_proposal_id: u64,
+    /// Proposals below this id were created under earlier signers and can't be approved.
+    first_valid_proposal_id: u64,
+    receipt_threshold: Option<U128>,
+    receipts: UnorderedMap<u64, TransferReceipt>,
+    /// Ids of the oldest kept receipt and of the next one.
+    first_receipt_id: u64,
+    next_receipt_id: u64,
+    receipt_capacity: u64,
+    max_allowances: LookupMap<AccountId, Balance>,
+    dust_pool: Balance,
+    /// Relay key and next relay nonce of each account.
+    relay_keys: LookupMap<AccountId, (Option<Vec<u8>>, u64)>,
+    vote_locks: LookupMap<AccountId, VoteLock>,
+    transfer_policies: LookupMap<AccountId, (TransferPolicy, u64)>,
+    transfer_decisions: LookupMap<(AccountId, AccountId), (u64, bool, u64)>,
+    next_transfer_policy_nonce: u64,
+    transfer_decision_storage_usage: StorageUsage,
+    /// Goes up with every metadata update, starting at 1.
+    metadata_version: u64,
+    demurrage: Option<Demurrage>,
+    /// When the demurrage configuration last changed, in seconds.
+    demurrage_since: u64,
+    last_demurrage_ts: LookupMap<AccountId, u64>,
+    burners: UnorderedSet<AccountId>,
+    batch_burnable: LookupSet<AccountId>,
+    delegates: LookupMap<AccountId, AccountId>,
+    delegators: LookupMap<AccountId, Vec<AccountId>>,
+    delegation_history: LookupMap<AccountId, Vec<(u64, Option<AccountId>)>>,
+    emergency_unstake_penalty_bps: Option<u16>,
+    personhood_gate: Option<PersonhoodGate>,
+    verified_humans: LookupMap<AccountId, (u64, u64)>,
+    personhood_epoch: u64,
+    display_decimals: Option<u8>,
+    state_version: u64,
+    change_log: LookupMap<u64, String>,
+    premium_threshold: Option<Balance>,
+    donation: Option<Donation>,
+    personal_donations: LookupMap<AccountId, u16>,
+    total_donated: Balance,
+    htlcs: UnorderedMap<u64, Htlc>,
+    next_htlc_id: u64,
+    htlc_locked: Balance,
+    bulk_cursors: LookupMap<String, u64>,
+    account_tags: LookupMap<AccountId, Vec<String>>,
+    allowance_windows: LookupMap<(AccountId, AccountId), (u64, u64)>,
+    reserve_requirement: Option<ReserveRequirement>,
+    supply_report_interval: Option<u64>,
+    last_report_ts: u64,
+    total_burned: Balance,
+    tier_thresholds: Vec<Balance>,
+    tiers: LookupMap<AccountId, u8>,
+    registration_observer: Option<AccountId>,
+    transfer_commitments: LookupMap<(AccountId, Vec<u8>), (u64, u64)>,
+    commitment_ttl: u64,
+    streaming_allowances: LookupMap<(AccountId, AccountId), StreamingAllowance>,
+    treasury_fee_share_bps: u16,
+    treasury_balance: Balance,
+    treasury_spends: UnorderedMap<u64, TreasurySpend>,
+    next_treasury_spend_id: u64,
+    min_incoming: LookupMap<AccountId, Balance>,
+    faucet: Option<Faucet>,
+    faucet_pool: Balance,
+    /// Timestamp (in seconds) of each account's last drip.
+    faucet_drips: LookupMap<AccountId, u64>,
+    max_transfers_per_block: u32,
+    /// Block of each account's last transfer, with the number of transfers it sent in it.
+    block_transfer_counts: LookupMap<AccountId, (u64, u32)>,
+    conversions: UnorderedMap<AccountId, Conversion>,
+    balance_alerts: LookupMap<AccountId, Vec<BalanceAlert>>,
+    sub_balances: LookupMap<(AccountId, String), Balance>,
+    /// Sum of the sub-balances of each account.
+    sub_allocated: LookupMap<AccountId, Balance>,
+    fee_token: Option<FeeToken>,
+    fee_token_deposits: LookupMap<AccountId, Balance>,
+    fee_token_collected: Balance,
+    /// Fee tokens the contract holds, deposits and collected fees alike.
+    fee_token_held: Balance,
+    lending_contracts: LookupSet<AccountId>,
+    /// Collateral locked per `(borrower, lending contract)`.
+    collateral_locks: LookupMap<(AccountId, AccountId), Balance>,
+    /// Collateral locked per borrower, by all lending contracts.
+    collateral_locked: LookupMap<AccountId, Balance>,
+    /// Registration cost paid by the accounts registered before a migration, zero if the
+    /// contract wasn't migrated.
+    legacy_registration_cost: Balance,
+    /// Balances of the accounts registered before a migration that rewards don't track yet.
+    legacy_supply: Balance,
+    /// Registration cost paid by each account registered before a migration, once tracked.
+    registration_paid: LookupMap<AccountId, Balance>,
 }
 
 const DATA_IMAGE_SVG_NEAR_ICON: &str = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAGQAAABkCAYAAABw4pVUAAAAAXNSR0IB2cksfwAAAAlwSFlzAAAuIwAALiMBeKU/dgAAHTBJREFUeJztXQl4VNXZbgI62ReyQmI2krAjAlpEs0z2kJ1Awu6KBWUpqIiCu0WtS6vV2talrTYzmZnMTFbAHUUFrbtiFTd+FcQFQcsqJv3f995zkzs3k8wkGQxCzvPch5DMnHvO937rOd93zq9+NdAG2kAbaAPtWLc4a5lPpLlwUlBt1mOBRv1rQ0y5C2OtJVEjG6q9+ntsJ1VLsk0fHGWZlhxcm3OtnyHjPz41aUd1NWn/86lJ3wtgGsPMedkxdcXBYxvnDABzrFtK/cyQIaa8uf6GzOcAwAECIcDYD3A+xr8/4t8dQcasuyA9Eye0zPfu7zGfkC3BVh4Qbi7IBqGtIPpBFRCH/Y36LRHmgmtirCVzQmpz7vc1ZHxGqfEzZO4Iqc1dHm2Zlji+ed4AMJ5ooxtnDQZBx8NOPARC7wYIbQKMnyAl/wk15d4abs6/MsCofxp/3xlcm/3PSEvh4qDa7H/h/98RPHzuFUjV+Yn26aGT1p8/oMZ604bbK72HWYpjwPFXURWpgGgDob8B4R+OshRein//AaL/oEgMnlYA8DEM/C2wM0sgPc9TtfEJNGY1RZkLM+Jt5T79Pb9fVEuur/QD11eC658FIQ+p1NMR/G4TJOA3IPidAOojSooKjP+pPnsAwGzFZ5egL4CaKX0WYH4OZ+Cu2LrS+DGNswfUWHct0V6hA9efBTvxmK8hfY+KwD+CuO9A7awB16/Ezy/6OALRRikRRr5NDQz62Uu7g+9dAmn7C/7/Ne0L+qCbvHiopShyfNO8ATWmbqc3z/dOsFfEQe//kQZZxfVtkILdtBOwI5fSToDo/1UTHcQ9AoI3hpnySwDYHHxmqxOpaUW/u6jehtYVnRdkzLbQthBEf0qcubAs0Vah62869HtDAOc9rK4kFpy7DBz7b52IJ4Sd+BIA1cB7WgFCPgLO/sYRCMlYbwqtzbkEwWDEr9df6EVgQfAEfH4tgHxH1Z/yUDLeAsDrwk35a4RKPOBbk/417Mu/IsyFU5PrZ5x89iXBVuZ1mrU0CFw9HUTZoOJ6qp79JBT1Pjh+HQj7gYawcGcz3geIVF8p4OzB2v6H11fq4CJPgSRQRX1FCdGoN77jSXzmKoBzB/rbTvvEd6HfG9hvSv2MTv2ekA3xhA+4firUzH2+NRlfK1wP1cOA7h0Q6HZw79UK96oJSYOM7z3A76fWzzzF1buS7NMDYSdmoi87+vpeIy3sbxfc6UfR3+V0lwmeFNcYMl/AOBbFWEsjJ68//8Q0/KkNVd7RlqKEEFPujYwXHPV7+m5w5iNQN7Ohpki8IxqO3hdo1Nfh71NHNVYP6vG762f6hZnzL4Kn9RptjtYjo3qENN4Kh+I3eM8zlFhG/FBjLZDUAkhzIDyyE8vwx1nLkmFAX6DH1E4IcC041AxPZ6EUYddIdqJdvQg39ykQpSrOVhowrqn3a1OIawbFyPbqJgDzidYbE57c6wBuNSTmSvz8Et5/lLYLUvkInIpkT9Kj3xu4ewom+JVQT1QLL8O7WRFmyrsGP3+g9ozIxVBfb0LdXA4gw89onu8x7hzZWD04uq5oIri/RtgXB2AY9+Bvj0daChYDvD/jM/tot/DzBQDlxJESNSDg0Hegvi72l+3EQY36oPq6C+pjHMBw27COaZrj0qYoDfGOF57gIea8KkjgZqonJ/blc9iR+Yx/yCwIJi/GmE5MQPyNmVshGVfi58NqOwH7YQAXnjumcZbbdgJOgi/1PLj6b3AG5sZaS8NOb3JPokY0VHnBzQ0LNeVcwXUxTfxyAOp0sb9B//YJD0gAAAHxVumEgQUxPgRAldDxQROaF7g16cKnV3jh8+Oh3//iK/fbSk+KbjRsUtoZzQvclpgke8UpcHVHANTHVC72SQaIuQMQuJwbYuqKfd3pB4Rm8JeM79zIeMTZWhbd2UDZPT4bALut9gDmfHz/4MkICPcv1ICsx2S7BQSqhUBEQrIuYJwgFh21npLaOB8RAeQ1DPSgnlyqQfQ776QFBB7Wle4CkmyvpJ3IR4xQ6ysvtXcJhBNgDsJmbYaBnhtvKw/rbownLSAg7BZMzqWETGie5xVjLR4OLr9Hu6jY04euNgj8OICdfHqT893DkxmQl9wBJKV+xqAwc95qXedFwt4+bXC5H4bRdyqNJzEgWS/BvXUJSHJ95aAhprwb+iIZ2gd2ha61n7MxntSAgEsHAOnP1gmQugFA+rVpAHlxWF3xACD92QYAOc6aFpCYupIBQPqzaQB5of8AyayHdEZMbDmvE3FPWkCCAEistXtATrOWepNwCAof9iQg3BbG+02QkjMQuTtkmiA+qsZnlK3eA4G1WctOiuV3cF63gCTYyoPDzXlMlNuo2Vf31NPKbEgQ+UaMa7iS94ufo+UdxYwdOnmP/21fg7QXf+IDAgngWtaPakDAsadGmAsnBxuzHwYhvlMR0IMSksZ8rMPi5x+ZxxVm5tJ/ceiUDRd5TV5/IXOJz4V0mLhHI959UgCySgtIpKVwsp/jdi4TqrdBxWzwEChteNfGIaZcphcxIY/79yKJIsuG9587oqHq1PFNc71Os5YFhcmJd0x6OBByYgOSDUDKOgECTs0VksFMw89AhL/h9wvw+Q2etCF4n3GopWhBkDGbq8d7BdhSEjfe+QdIyOgke+UpNPyx1tJIOBZLI8wF+THW4hMWkM1dAQIJ+YxGlyUFIhd3l0o6mFq6x8f9xUYuJn4YKO+bH1L9noBvDzXl3hlpLmSKanuuMP/FO94LMeWsSbBVRE5qOd8LzyBIzYmVaqoFJM4JIOHm/GwAcTc48jqoqnd1HSlBJOCn+B6THzIA1CoQbZuzHCvV53dAAu7He8dCDQ0B8Rf6G6WNLWUfX0kzeo7pqkzQQ58f6tqBST9KdcVEhxhrSUTa44tOHOlg0wDyvDNAwsz5OdDl7ziklkKF4XdGgJUWZysLmNA83ytezn6cCILfrbIF/1PZg0cBam5sXWmQ8v5E+/RTADb3VliXuF3nmEe8G1JpQZ/LmcGIPr7tUGPpXwOYughLYU68tcxpQPmLagtevEniLPcAabcholQtcysM8AUAINxZ34m26YER5vx8EKwZtmAfk9vA0Zeg77CzN1zslKMhLTqoqXOYXK1J4Gahz7v4/k3o8yrWofh0JPURtE8wznWwPcnJ9TN6nD3Z7y3RXuEdXVcUNa5p7jnQwd5OAOnk9sqAZHxN1QHCXI7JD5vYfJ7L3FrENKHo/ywEkpFd7QZqW5ytPCDCUlAoShxUxaNpR8AIr0ZaCpZCym7TFAXRvnwA72sZ38Uqr75T6mdoMNjBEP9yTPbxKPM0M1zcU9wBBJw7NdSUdzv+PYPu57EeJyt0YR+iAf5SGP9tKjXGNNe9rCfBWJZANRow9u8UaYIaPYi5rYcaLcH3g1y/qZ8auE4Hl/F0EPwfviLbHMA0OgHkuXhr+SofDSDlm1YNnrpxodO0HYA8BFyZigBueIq90qM1HOT0BFt5HMbxVyZ+q20SJOITSO41NPzM/fVR5yYbJNAeijIXjj2uCn5GNc7yArGSuBQhCjTbc6W6BMRWfqUWkO7eAZ2/FgTZCc58H6BMPhbziLOW+kEisuAm1/uqyhdEvu+TdMVDa3PvwRw/V9keqrHtdBZAg4QzWtxL9DsmjTmyNKDQqeeBWG/rHJPWfmJMAYkx9gUQfHcI/laB/pt1cnbifqiK64dapmWPbKjyqKSk2GeEp9TPDIDEBDLjnlVdmqLTfUzAhm27gKUKmpqTn/yN+tdhd+bBGwxNtlf+vMDAjWSlbJGmAkoR8x3Qy38Et60GNz/YF0AA9tkgzJf4rio7nrUb+k2pDTOiPTmnSHPBMhDaBKnOAyjBYKYEALACjPWeSo2xgms71NgtmP8VzDFTu9CsWwRNGtFHYZJ9ulvZmH1q8MV98bKzQNT7VcsOSjC2CwaQUfbSkNrsB/H/r7pQWZtgQ9wCBG7vmXLJWfs+RZs402R9av2MKE/ODdx9Pb0sut6IS/4J93dKPLgdTDUaY74P8/lCAYZuOWtXaPQB2r0iqFTHNt/hO/fAW5t8TOrih8P3jqkrgZ3IvkEc+HKknUCG9O/BWQ0g/hXQsX+ia6j8Hb+zdQFIJ6Pu7L1Qi4HRddPSwKUWqgVKIwM46OuJUFmnjm2a7YW+/eFiB6ofvLPHXpoA5Efh/nL5fRsrg6Ms0yZzTSvcxMBVb/cxdKwC0wnA+P/FMbFI1ceg8sZq0g6jj3cB2Fp4Ywmp9TM9E78k2CpCwamz/eWVT5VOlaqOXoFauR5R9nXgmC2qv0trQtC35w+3T9fGIZt6YdSXoL+tAYbMp9DnOOX3UCvRIaacezBps/LAuYDayZ+HSL1HhZuOgKTvQz8PU+rBDO8G12bN5aYZnqGsP5TL49rrSniuytugw01QY1eTJmpvTE5n1T+B/meClsG9xeFXSXAvIZI5IHQLkFdvFNFgI0DKX4O/r/Q3cHGufY2oTZyWcAuINeLXGy5UNn/UgDzrrspS2ujG2X6Q0EgQhIa3ndAYx6/Rx9eqsUkPgOHeeY9UhRoQPLvBBCsxb8Wj+geedg4fVlcE+5K7iouXKolgULkVamo5+qLH+X86dYmegdW/WfWQpkzMoWduMiafytJj8cKfVMTeHVTLtaWCK2S/XdKrYkDpewkeDF4ZYhKHgKmvgHTV8P4pXQBS2xdAMNbdmMvl+PkLZ4CwIfbwD5fW3/Rm1TKM5NTQrrDkgrZOcxaLxMw8wwWgjnZ7cPhCsxoI6m764jyiQpwz8plaV0JK/o0BzD7NVuZUJDUbVL0GhO4kOIwrwwF8oB706OMbLSDosw6fC1c+BzXnsoBHDQgI/CX6gJ1L39kVIEqLt1UEQCqKIB2bVCV6XBt7D+9eLc5a4UqASo2lHQGN33BnzlID0TZ3uLGZb0EiruTpB3jJGz6qUjSu9dBogeDx3ZUsO2SdSICU9QoQxiXcJ8F3XsKzRV4dTtPWCDK75Fv8fSs/g+cFgLMA+rtbo6oB5Hu8p0FlwLsEhG0iAkLMcRiYdYk/bKfKG/sBY1yP91+K/u5TFZq2BhuzPnVnzlJTAKFtoOqC+NY72Ima9G/x+weHWYpGTW5xXVyvKfr8Et99TRl0TwCBGkrxlU936NFOIWzaA5DubqVEY0McNrpcAaK0ic3necFNToS3eQelrB0YQ/oegHU3pOVOISm9BuQQYos1+PdN8f8j0ItNMOiFp1nL/E9vdu8kHTUgiq5VqZcN0KfHFJAgSFVfAAEDGnsST6TUz/AB8TNgxK06EZ8wiwWq83ciJOg9IFxHQidvyqKc8TF86kkj6qvcAoInwgGMRHDonV2l9IjI/iL0OyTFRb/HAhCqG6jCAB5e0NX2sBQsIhhGH+Oh+tyKcXj6A77D5LsDYp7bID03C03Te0CgrtbqOgD5EIMf5+r7yfYZ3rHWkki4hotgd57X7Gl3nnBN+nfcmYPkFWPC/giinALjSZU1rnEOs0t0EZbCc2DXHtCeOKR9xDmOb0GipYXEOJvrOnoAMgff3S8AeQ+A3ChscG8BSTsUYJAAecNdQBAI+iNIymKZcU8T3fzkcxNvi7QUjp/Ucn6nCQ+tKx4Gj66ZkspdPBCRXlCnSlzo7P9yH17+TMZHkMBVsXUl7f0xPoq2FCVBKlaCyO93B0RnYKT99g0M9ABoaA8AeR/vvKGvgBwWEuISkJGNPP+qeGSQkQdWttuL3jzMLHw/tDbn2nhbmUOhJm0WIvBhIO4oPiAo0z/3aPsAqC3gxgn8DGKqEYgZApU+RjRU+YFhFvJ4jx5ksDgD5nvY0wbWxSfZpztVh2pAfKXdxvzr8POhXgOiY4xhzIQNSX/dFSCJ9ooASMUjXRjGXk04um7aPBjTLu1KbwJDME2an3wikSeS71rh9DwF4ONdA5K+nfbYU4C85gqQ02ylwWLfos9giOcoVNeyWGtpl4CAsF0tnRidAWL49HEvgFzAZQxPjRNEfgNSkuIKEDwf4bNr+g6IIXMt0D0uAYkw56fCBmzmTqXy0L6EmHJugnfXyRvqT0DA1B/D7lytk7cSeg+I33EMCNsZLQtC4bpGK8+ElvlRMNpO1VV/AgIafjK0btpVPp4ABJz36vEKSE9a/wKSsQNzWSXWu/oCSMa1AGUAkD4CwuV4uMlXiFCgb4CIY1z7AZCCFXApPZaQVvPpxv4E5DPQbqWPHLn3SWX1FyCtmOyGSHPBOQDFI/vSIxuqfcLNBecx2a0fAPkcn1uh6yMgRxAZE5BX+gEQSUpE5vvNcGNHjW+e16t9aQAxSM4dk87Z4tKLx6qyemBDdsLLWt5nQAIkQPT9BYj0iCyP50NNufO4jdvVWpe2jWioZrZ8MAhRje8/oS5H+LkBwbt3YQzLxP97DwheeG2A+4A0eRYIaZ1KfcT4AeYMw7boE20VXZYH4G9ew6HmWPmE8dtF7pjSr8ekoyeA6OR9+qViLL0HJMiYxYySl10BAk5Ulk48woUk/hBT7r1yNotDcY5U8gb1c2d0XdEY7WlxoxqqB0dZCsfj73eLQ5vbC3+4wwkitbhafe7B8xPo82SMxfXSCfeDuKOIeOSHvgFSm32tO4Don7iM6iESauUKbY5vbyfrb8x8JcoybVGYOX+VJuFCPpTMmPU0AInl+0c3VHvz/aK0+RMVEG0iDfRR2JHZ+E6zB9bbWpUjbZmvNqF5gVNPUGNDvokyFwKQjO/7BAg47bpA+RoIt5bfUxtmnsqSAsGhe3swSe7ff8CkO3VZNEsBMDEjgOEJ2L8Hsb/wk6+fuA2/G8eD88c2zfHjtUZi70VbtvYsxnNZiCnnDk1qDnMCvtKUz7kjuYcCa7MeCzfnZ8VZy7utqtJ4Wd9CtV0GmuzrNSAiOwKAZLkNiNLArb5QHXqAyYu8uvP7pespQOB7QeDRcdLBAfmlAXIKzUHVZ3bBZb0bweKN8LjOZP/J9TN0zChkSZraTojNpP8wgQ925LcswvFRnSnPC2O4X4PxTaH3FVqbq1yt1CUwUt6VIfM59FmNdwedteFCl46FBpA9Qy3FlzLNtE+AYLDXBXUA8ilE9Ex3z9cd2zibeyTR6OMicdXdYUcg0veA8MYwUx53CgOUrHHahaF1xTyrfSmvLqKYBxgzuSlUFWcti4AU6kDoyUHG7Ns5Jp1j7tjnIMRDTL8B0a3MMdZ15I7xqoonoFar0H8kU1EF8xDYc6VbG+RyAzUwzKV6AwxzVbSlKDHJ3n32itLibWXBoCMXEw8JQPbyYhr0v6cPgKQfweAJyBbx/wOY7CMgxpTh9kq3s++SO3KDbxf3De4HobeAwBczvTLJNt0pwDxuHN9LAXEX4OehNNgEmNcV4fvcVj7awcGsbMp8gfdO8VYetXrykeOZj8AYq9FPTKKtc/TPPXCegIq5lYhszR/ElRa8mWeCs7tKnDVuVkHSR4bINwbt7pDKjH2Q8MW+clFp7wFhNgYI+axqckxEfg+/u14ugqx0O1ijJwaR14Mwi0C4VARsbp1AXfzs5d5wYwMhSdPhgterjr3gZFki8HFobQ5LBNbi75tV2YK8DuMrMNT9eO/UBHuFy4hfOqzZUhTLxAvMvSymrjiM9equvse6SqjzqGB5W/jfGlu2n1czYXwLfeSqgV4b9Z9YogbvgHr4RbUelgymQb+JQRcIHeJ25z1sLNyX1FNt1p/U6keM4Qdx0ABLmh9kGbOjxOibAeLs1PqqQIwxOtk+I9D1G3veRjXM4rZwEY/o0OQRtIoi1jskW2bIfFGo154Bgsk9qdK7NGZbea+GnEScuV2tY+UkYn0zxLEQqsnfU5Mc1zTXG+qKd96u0+p1sdf/DMsAMNk/iGpZZbzMyt+MsS6ARAxJsJWH4zPL6EKLYpppKR66awpA06PMENdfOOztkzkw9ofo4QU5Bqf0JplmeqPbL+KgmRDnIwySTk4W+44XdImbNE3ayikmXoOL74W+HQX74vZh+E4m6RVvLQsNM+XP5mk9mpiB/v9OqLzfk+MCjFkbVaqB3PiFfOxS8UiqR3hR+XAMWIS6T+2t4TO3w0BznL26ayrRXj5omJxvdr2f7FSomeWIv1H/KpkFUnOLYJZWQcO9vGYpyjztLHqIbr8QdsFL3D9bTZdR10EUTvrzUFMeY4CFzgjmJx8ccx24O254fc9quenKgqPyeGmXNppm0oN0cExd0Xx4V2ZR46dcJsZ6jr/B4I/lyjDGxiqvRxmvRJoL1kJC7iIQKgZi/QpvBF0day2NHu3mJZPjmqWTglgvs9xfvgzgqIZZvgAjXY938iiPt3Qd2fQHIUVPYVz6RDfsWJdtRH31IB5mHyJzgoK0sqbE29RWkVtFZOyw5sRD83mzs6sz19nGN8/FewonyW5n+k4tx8F7eiZcVpm3ikxy5UySQ8JOVMJbC8p7erlXnK1sKia/SRCrFVy5iycLRcg1hDaVtMjjNOqfAoHnAMhua85HNFSzrpL1982aEgOq7X0s04hgXXttdg2v4pMZRSpqejWkNncJD0Jwxzlwq01onj8Yhn08OPPP6quCeCUQuNbAMgX5QmBHPSr8/g0gRjYMaqeolsstQ3lZmHwGyQ6N/98qTk64KVKuE9/acXe6XL3FaiZEyw6Aw3NjuUIeVK5NtRHVKktE3g1UJeDeLRoviHflmvE9PaTFQZXA6xocYSk4WyT+7dPMT7oOln0OkV3tdsaUKwOyeWpd0rkbL/F8Ve5EoEsXkBl7skqRF/xkNzjzLfz+ZkxopUbFKcT4JNiYfVssYhH4+pKbTI4W5cfPaFZjJfUEjrNCahbxRAXhPSnnWn2F39FrmdBVnu2Ihpnc/xgWKl9a+Xq7RBmkS18eB3Mt5dlYGjV2VD76L3sd5pk6tmmOdJFYqFQplbFNMycuu3wRYsq5C1LxW1GNLEmNr1yCUBduysvkgTmevEPLaaP3wyJIcOdKUTJ8VMVlT4ObF7Puz9fxpB5pwv4ScLmL4RjkBcp3ozuKvjhrhKoQkrFGtUjZJjIFrfiuHurFLYM43DadgSVv/7xNfYocz1ShzUHkfDHG0ejjeA0Gl122AbB1UJUOa2NiHJynHeO4VJzpJUXeVJ9SzaU5bxYvzpzo5mqGxxq9BHIp1Nhdvh2ehrI0/veOpYtOamyfb02nnFzlRIR7EMCtEZtJ+xQCkAN5Bwg4N2pMU89veUZ0zkqqcwHoP5V72UlAvPMt6TJLGH750uN2R6JNOCvqe3d/pKqj64/v/F4+aiP9sLhV7m3MeTUckpQxjbP695bQkY2zfCLM+cUIEteLKFReOjdkvskzQsDpNzgReTVAEHFuOkkrsveogj9et70dE/0dRD9G/+SSPnMcDwTAeObL9kgO4FjQyionvH+RzPGOxZqyrcyQyp95+WSQfEDmAZ24FJN7QLChU6A5jq/rWqXJmmFfDPrnFE6TjV7WExgwj+wjsTuqimTvaSu4cyWIdK0ovldUyk44CX9CPDFq4cu3elz0ETCynHq5nziXVxB9D4j9d6ix3zDeouckais3RnD5Xh7/Nyo7wUPPcs7ZeEmvY65j3kY2VHmLg2jWihNGFQLD9ZSO21hGaWAwBYLcDY67DBNrUpbnhXpqYZ0I7ITHon5nDQx0KoJDnl5EqVCcBq7svgc7dwsY5XKM42oQHY5Ke7xF7+4N/P1CSO0v5y52+OyDufAIt+8PahXEMgPWt8PQVvHaVZWKY83Fy+Gm/Oqf2yAm2St5rNQkaR1KdpPbZMLrXxMXT+4XQH0GIFbAjg0b1VD9yzi8TNsADBMNsuVlGNmjohpQ7XUrp0zfjIkmIBLut4kygIXKnScWUTuWjAzp3yJgfRDzONNZEdEvsg1HFCyM6StiF1Bcn539VwRO48Y1zTsuJorYg0sj0Tyrl9eJMyqHw1KQWj/z2J/s83O3JFvFYBh3Vj2tg7qywb0tievioIH+bin1M04Fo4yJsZbEpDbM/GWqp4E20AbaQBtoA22g9W/7fzwNdUbekop/AAAAAElFTkSuQmCC";
@@ -51,41 +540,765 @@ impl Contract {
                 reference_hash: None,
                 decimals: 24,
             },
+            None,
+            None,
         )
     }
 
+    /// Initializes the contract without any supply, with only `owner_id` registered. Tokens are
+    /// created later through the mint queue.
+    #[init]
+    pub fn new_empty(owner_id: AccountId, metadata: FungibleTokenMetadata) -> Self {
+        Self::new(owner_id, 0.into(), metadata, None, None)
+    }
+
     /// Initializes the contract with the given total supply owned by the given `owner_id` with
-    /// the given fungible token metadata.
+    /// the given fungible token metadata. An optional `unlock_schedule` of
+    /// `(unlock timestamp in seconds, amount)` pairs locks parts of the initial supply until then.
+    /// An optional `clawback_window` in seconds allows `emergency_clawback` for that long.
     #[init]
-    pub fn new(owner_id: AccountId, total_supply: U128, metadata: FungibleTokenMetadata) -> Self {
+    pub fn new(
+        owner_id: AccountId,
+        total_supply: U128,
+        metadata: FungibleTokenMetadata,
+        unlock_schedule: Option<Vec<(u64, U128)>>,
+        clawback_window: Option<u64>,
+    ) -> Self {
         assert!(!env::state_exists(), "Already initialized");
         metadata.assert_valid();
-        let mut this = Self {
-            token: FungibleToken::new(b"a".to_vec()),
-            metadata: LazyOption::new(b"m".to_vec(), Some(&metadata)),
-        };
-        this.token.internal_register_account(&owner_id);
-        this.token.internal_deposit(&owner_id, total_supply.into());
-        near_contract_standards::fungible_token::events::FtMint {
-            owner_id: &owner_id,
-            amount: &total_supply,
-            memo: Some("Initial tokens supply is minted"),
-        }
-        .emit();
+        let mut this = Self::with_defaults(
+            owner_id.clone(),
+            FungibleToken::new(BALANCES_PREFIX.to_vec()),
+            LazyOption::new(b"m".to_vec(), Some(&metadata)),
+        );
+        this.internal_register_account(&owner_id);
+        if total_supply.0 > 0 {
+            this.token.internal_deposit(&owner_id, total_supply.into());
+            this.internal_record_balance(&owner_id);
+            FtMint {
+                owner_id: &owner_id,
+                amount: &total_supply,
+                memo: Some("Initial tokens supply is minted"),
+            }
+            .emit();
+        }
+        this.internal_record_total_supply();
+        if let Some(unlock_schedule) = unlock_schedule {
+            this.internal_set_unlock_schedule(&owner_id, unlock_schedule);
+        }
+        if let Some(clawback_window) = clawback_window {
+            this.internal_set_clawback_window(clawback_window);
+        }
         this
     }
 
     fn on_account_closed(&mut self, account_id: AccountId, balance: Balance) {
+        self.last_activity.remove(&account_id);
+        self.inheritances.remove(&account_id);
+        self.strict_receivers.remove(&account_id);
+        self.internal_clear_trusted_receivers(&account_id);
+        self.private_balances.remove(&account_id);
+        self.incoming_hooks.remove(&account_id);
+        self.last_receive_block.remove(&account_id);
+        self.last_send_block.remove(&account_id);
+        self.referrers.remove(&account_id);
+        self.holders.remove(&account_id);
+        self.reward_checkpoints.remove(&account_id);
+        self.time_locks.remove(&account_id);
+        self.transfers_sent.remove(&account_id);
+        self.transfers_received.remove(&account_id);
+        self.forward_to.remove(&account_id);
+        self.balance_history.remove(&account_id);
+        self.pending_settlement.remove(&account_id);
+        self.hold_since.remove(&account_id);
+        self.attested_until.remove(&account_id);
+        self.received_locks.remove(&account_id);
+        self.blocked_incoming.remove(&account_id);
+        self.max_allowances.remove(&account_id);
+        self.relay_keys.remove(&account_id);
+        self.vote_locks.remove(&account_id);
+        self.transfer_policies.remove(&account_id);
+        self.last_demurrage_ts.remove(&account_id);
+        self.batch_burnable.remove(&account_id);
+        self.personal_donations.remove(&account_id);
+        self.account_tags.remove(&account_id);
+        self.tiers.remove(&account_id);
+        self.min_incoming.remove(&account_id);
+        self.faucet_drips.remove(&account_id);
+        self.block_transfer_counts.remove(&account_id);
+        self.balance_alerts.remove(&account_id);
+        self.registration_paid.remove(&account_id);
+        self.internal_clear_delegations(&account_id);
+        self.total_burned += balance;
+        self.internal_record_total_supply();
         log!("Closed @{} with {}", account_id, balance);
     }
 
     fn on_tokens_burned(&mut self, account_id: AccountId, amount: Balance) {
+        self.total_burned += amount;
+        self.internal_record_total_supply();
         log!("Account @{} burned {}", account_id, amount);
     }
 }
 
-near_contract_standards::impl_fungible_token_core!(Contract, token, on_tokens_burned);
-near_contract_standards::impl_fungible_token_storage!(Contract, token, on_account_closed);
+impl Contract {
+    /// The contract with `token` and `metadata` and every feature at its default, with the
+    /// storage of a registration measured.
+    pub(crate) fn with_defaults(
+        owner_id: AccountId,
+        token: FungibleToken,
+        metadata: LazyOption<FungibleTokenMetadata>,
+    ) -> Self {
+        let mut this = Self {
+            token,
+            metadata,
+            owner_id,
+            last_activity: LookupMap::new(StorageKey::LastActivity),
+            inheritances: LookupMap::new(StorageKey::Inheritances),
+            minters: UnorderedSet::new(StorageKey::Minters),
+            mint_requests: UnorderedMap::new(StorageKey::MintRequests),
+            next_mint_request_id: 0,
+            mint_request_ttl: mint_queue::DEFAULT_MINT_REQUEST_TTL_SEC,
+            stable_peg: None,
+            stable_reserve: 0,
+            sale: None,
+            sale_contributions: LookupMap::new(StorageKey::SaleContributions),
+            sale_tokens_held: 0,
+            strict_receivers: LookupSet::new(StorageKey::StrictReceivers),
+            trusted_receivers: LookupMap::new(StorageKey::TrustedReceivers),
+            supply_frozen: false,
+            incoming_hooks: LookupMap::new(StorageKey::IncomingHooks),
+            min_block_gap: 0,
+            last_receive_block: LookupMap::new(StorageKey::LastReceiveBlock),
+            last_send_block: LookupMap::new(StorageKey::LastSendBlock),
+            market_makers: LookupSet::new(StorageKey::MarketMakers),
+            storage_token_fee: None,
+            storage_reserve: 0,
+            token_funded_accounts: LookupSet::new(StorageKey::TokenFundedAccounts),
+            paused: false,
+            guardian_id: None,
+            circuit_breaker: None,
+            referrers: LookupMap::new(StorageKey::Referrers),
+            referral_earnings: LookupMap::new(StorageKey::ReferralEarnings),
+            referral_program: ReferralProgram { bps: 0, cap: 0.into(), total_rewarded: 0.into() },
+            holders: UnorderedSet::new(StorageKey::Holders),
+            auto_lp: None,
+            transfer_validator: None,
+            max_mint_per_block: None,
+            minted_block_height: 0,
+            minted_this_block: 0,
+            reward_per_token: 0,
+            rewards_pool: 0,
+            reward_checkpoints: LookupMap::new(StorageKey::RewardCheckpoints),
+            auto_claim_rewards: false,
+            max_annual_inflation_bps: None,
+            inflation_minted: LookupMap::new(StorageKey::InflationMinted),
+            inflation_window_total: 0,
+            inflation_oldest_day: 0,
+            inflation_last_day: 0,
+            allowances: LookupMap::new(StorageKey::Allowances),
+            allowance_log: LookupMap::new(StorageKey::AllowanceLog),
+            allowance_log_ranges: LookupMap::new(StorageKey::AllowanceLogRanges),
+            allowance_log_capacity: allowance_log::DEFAULT_ALLOWANCE_LOG_CAPACITY,
+            lock_tiers: vec![],
+            base_apr_bps: 0,
+            stakes: LookupMap::new(StorageKey::Stakes),
+            next_stake_id: 0,
+            total_staked: 0,
+            metadata_mirror_target: None,
+            private_balances: LookupSet::new(StorageKey::PrivateBalances),
+            balance_viewers: LookupMap::new(StorageKey::BalanceViewers),
+            admin_cooldown: 0,
+            last_admin_action_ts: 0,
+            reserve_custodian_key: None,
+            latest_attestation: None,
+            time_locks: LookupMap::new(StorageKey::TimeLocks),
+            transfer_call_gas: transfer_call_gas::DEFAULT_TRANSFER_CALL_GAS,
+            transfers_sent: LookupMap::new(StorageKey::TransfersSent),
+            transfers_received: LookupMap::new(StorageKey::TransfersReceived),
+            deployed_at: current_timestamp_sec(),
+            clawback_window: 0,
+            storage_per_account_id_byte: 0,
+            bridge_id: None,
+            bridge_locked: 0,
+            processed_bridge_txs: LookupSet::new(StorageKey::ProcessedBridgeTxs),
+            forward_to: LookupMap::new(StorageKey::ForwardTo),
+            max_tx_bps: 0,
+            tx_limit_exempt: LookupSet::new(StorageKey::TxLimitExempt),
+            balance_history: LookupMap::new(StorageKey::BalanceHistory),
+            balance_history_capacity: twab::DEFAULT_BALANCE_HISTORY_CAPACITY,
+            global_spenders: LookupSet::new(StorageKey::GlobalSpenders),
+            global_spend_limit: 0,
+            stabilizer: None,
+            stabilization_pool: 0,
+            settlement_hold: None,
+            settlement_hold_storage_usage: 0,
+            pending_settlement: LookupMap::new(StorageKey::PendingSettlement),
+            hold_since: LookupMap::new(StorageKey::HoldSince),
+            hold_boost: None,
+            attestation_gate: None,
+            attested_until: LookupMap::new(StorageKey::AttestedUntil),
+            attestation_epoch: 0,
+            supply_history: LookupMap::new(StorageKey::SupplyHistory),
+            supply_history_first: 0,
+            supply_history_next: 0,
+            supply_history_capacity: DEFAULT_SUPPLY_HISTORY_CAPACITY,
+            receive_lock_seconds: 0,
+            receive_lock_exempt: LookupSet::new(StorageKey::ReceiveLockExempt),
+            received_locks: LookupMap::new(StorageKey::ReceivedLocks),
+            consolidated_batch_events: false,
+            deferred_transfer_events: None,
+            paused_until: None,
+            blocked_incoming: LookupSet::new(StorageKey::BlockedIncoming),
+            airdrop_root: None,
+            airdrop_pool: 0,
+            airdrop_claims: LookupSet::new(StorageKey::AirdropClaims),
+            insurance: None,
+            insurance_fund: 0,
+            priority_fee_schedule: None,
+            multisig: None,
+            proposals: UnorderedMap::new(StorageKey::Proposals),
+            next_proposal_id: 0,
+            first_valid_proposal_id: 0,
+            receipt_threshold: None,
+            receipts: UnorderedMap::new(StorageKey::Receipts),
+            first_receipt_id: 0,
+            next_receipt_id: 0,
+            receipt_capacity: DEFAULT_RECEIPT_CAPACITY,
+            max_allowances: LookupMap::new(StorageKey::MaxAllowances),
+            dust_pool: 0,
+            relay_keys: LookupMap::new(StorageKey::RelayKeys),
+            vote_locks: LookupMap::new(StorageKey::VoteLocks),
+            transfer_policies: LookupMap::new(StorageKey::TransferPolicies),
+            transfer_decisions: LookupMap::new(StorageKey::TransferDecisions),
+            next_transfer_policy_nonce: 0,
+            transfer_decision_storage_usage: 0,
+            metadata_version: 1,
+            demurrage: None,
+            demurrage_since: 0,
+            last_demurrage_ts: LookupMap::new(StorageKey::LastDemurrageTs),
+            burners: UnorderedSet::new(StorageKey::Burners),
+            batch_burnable: LookupSet::new(StorageKey::BatchBurnable),
+            delegates: LookupMap::new(StorageKey::Delegates),
+            delegators: LookupMap::new(StorageKey::Delegators),
+            delegation_history: LookupMap::new(StorageKey::DelegationHistory),
+            emergency_unstake_penalty_bps: None,
+            personhood_gate: None,
+            verified_humans: LookupMap::new(StorageKey::VerifiedHumans),
+            personhood_epoch: 0,
+            display_decimals: None,
+            state_version: 0,
+            change_log: LookupMap::new(StorageKey::ChangeLog),
+            premium_threshold: None,
+            donation: None,
+            personal_donations: LookupMap::new(StorageKey::PersonalDonations),
+            total_donated: 0,
+            htlcs: UnorderedMap::new(StorageKey::Htlcs),
+            next_htlc_id: 0,
+            htlc_locked: 0,
+            bulk_cursors: LookupMap::new(StorageKey::BulkCursors),
+            account_tags: LookupMap::new(StorageKey::AccountTags),
+            allowance_windows: LookupMap::new(StorageKey::AllowanceWindows),
+            reserve_requirement: None,
+            supply_report_interval: None,
+            last_report_ts: 0,
+            total_burned: 0,
+            tier_thresholds: vec![],
+            tiers: LookupMap::new(StorageKey::Tiers),
+            registration_observer: None,
+            transfer_commitments: LookupMap::new(StorageKey::TransferCommitments),
+            commitment_ttl: committed_transfers::DEFAULT_COMMITMENT_TTL_SEC,
+            streaming_allowances: LookupMap::new(StorageKey::StreamingAllowances),
+            treasury_fee_share_bps: 0,
+            treasury_balance: 0,
+            treasury_spends: UnorderedMap::new(StorageKey::TreasurySpends),
+            next_treasury_spend_id: 0,
+            min_incoming: LookupMap::new(StorageKey::MinIncoming),
+            faucet: None,
+            faucet_pool: 0,
+            faucet_drips: LookupMap::new(StorageKey::FaucetDrips),
+            max_transfers_per_block: 0,
+            block_transfer_counts: LookupMap::new(StorageKey::BlockTransferCounts),
+            conversions: UnorderedMap::new(StorageKey::Conversions),
+            balance_alerts: LookupMap::new(StorageKey::BalanceAlerts),
+            sub_balances: LookupMap::new(StorageKey::SubBalances),
+            sub_allocated: LookupMap::new(StorageKey::SubAllocated),
+            fee_token: None,
+            fee_token_deposits: LookupMap::new(StorageKey::FeeTokenDeposits),
+            fee_token_collected: 0,
+            fee_token_held: 0,
+            lending_contracts: LookupSet::new(StorageKey::LendingContracts),
+            collateral_locks: LookupMap::new(StorageKey::CollateralLocks),
+            collateral_locked: LookupMap::new(StorageKey::CollateralLocked),
+            legacy_registration_cost: 0,
+            legacy_supply: 0,
+            registration_paid: LookupMap::new(StorageKey::RegistrationPaid),
+        };
+        this.measure_holder_storage_usage();
+        this.measure_reward_storage_usage();
+        this.measure_transfer_count_storage_usage();
+        this.measure_hold_streak_storage_usage();
+        this.measure_receive_lock_storage_usage();
+        this.measure_demurrage_storage_usage();
+        this.measure_tier_storage_usage();
+        this.measure_activity_storage_usage();
+        this.measure_block_gap_storage_usage();
+        this.measure_block_transfer_count_storage_usage();
+        this.measure_settlement_hold_storage_usage();
+        this.measure_transfer_decision_storage_usage();
+        this.measure_account_id_storage_usage();
+        this
+    }
+
+    /// Moves `amount` from `sender_id` to `receiver_id`. Every transfer initiated by an account
+    /// goes through here, so it's the place for any bookkeeping on top of the standard. Returns
+    /// the amount the receiver actually got after fees.
+    pub(crate) fn internal_transfer(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Balance,
+        memo: Option<String>,
+    ) -> Balance {
+        self.internal_transfer_with_forwarding(sender_id, receiver_id, amount, memo, true)
+    }
+
+    /// Same as `internal_transfer` for the `ft_transfer_call` family. The receiver keeps the
+    /// tokens instead of forwarding them, since `ft_resolve_transfer` may refund them out of its
+    /// balance.
+    pub(crate) fn internal_transfer_call(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Balance,
+        memo: Option<String>,
+    ) -> Balance {
+        self.internal_transfer_with_forwarding(sender_id, receiver_id, amount, memo, false)
+    }
+
+    /// Why a transfer of `amount` from `sender_id` to `receiver_id` would fail on its own, if it
+    /// would. Demurrage must already be applied to the sender.
+    pub(crate) fn internal_check_transfer(
+        &self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Balance,
+        memo: &Option<String>,
+        forward: bool,
+    ) -> Result<(), String> {
+        check_memo(memo)?;
+        if amount == 0 {
+            return Err("The amount should be a positive number".to_string());
+        }
+        if sender_id == receiver_id {
+            return Err("Sender and receiver should be different".to_string());
+        }
+        if !self.token.accounts.contains_key(receiver_id) {
+            return Err(format!("The account {} is not registered", receiver_id));
+        }
+        match self.token.accounts.get(sender_id) {
+            None => return Err(format!("The account {} is not registered", sender_id)),
+            Some(balance) if balance < amount => {
+                return Err("The account doesn't have enough balance".to_string())
+            }
+            Some(_) => {}
+        }
+        self.check_unlocked(sender_id, amount)?;
+        self.check_within_tx_limit(sender_id, amount)?;
+        self.check_block_transfer_count(sender_id)?;
+        self.check_trusted_receiver(sender_id, receiver_id)?;
+        self.check_incoming_allowed(sender_id, receiver_id)?;
+        self.check_min_incoming(sender_id, receiver_id, amount)?;
+        self.check_block_gap(sender_id, receiver_id)?;
+        self.check_transfer_policy_allows(sender_id, receiver_id)?;
+        if forward {
+            self.check_forward_allowed(receiver_id)?;
+        }
+        Ok(())
+    }
+
+    fn internal_transfer_with_forwarding(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Balance,
+        memo: Option<String>,
+        forward: bool,
+    ) -> Balance {
+        self.assert_not_paused();
+        self.internal_apply_demurrage(sender_id);
+        self.internal_apply_demurrage(receiver_id);
+        assert_ok(self.internal_check_transfer(sender_id, receiver_id, amount, &memo, forward));
+        self.internal_count_block_transfer(sender_id);
+        self.internal_record_block_gap(sender_id, receiver_id);
+        self.internal_record_activity(sender_id);
+        self.internal_count_transfer(sender_id, receiver_id);
+        self.internal_settle_rewards(sender_id);
+        self.internal_settle_rewards(receiver_id);
+        self.internal_auto_claim_rewards(sender_id, receiver_id);
+        let fee = self.internal_take_lp_fee(sender_id, receiver_id, amount);
+        let donation = self.internal_take_donation(sender_id, receiver_id, amount);
+        let received = amount - fee - donation;
+        self.internal_transfer_deferring_event(sender_id, receiver_id, received, memo.clone());
+        self.internal_issue_receipt(sender_id, receiver_id, amount, memo.clone());
+        self.internal_record_balance(sender_id);
+        self.internal_record_balance(receiver_id);
+        self.internal_reset_hold_streak(sender_id);
+        self.internal_notify_incoming_hook(sender_id, receiver_id, received, memo);
+        let holder_id = if forward {
+            self.internal_forward(receiver_id, received)
+        } else {
+            receiver_id.clone()
+        };
+        self.internal_lock_received(&holder_id, received);
+        self.internal_hold_settlement(&holder_id, received);
+        self.internal_reward_referrer(sender_id, amount);
+        // Last, since tripping the circuit breaker pauses the contract.
+        self.internal_record_outflow(amount);
+        received
+    }
+
+    /// Creates `amount` new tokens on `account_id`. All supply increases after initialization go
+    /// through here.
+    pub(crate) fn internal_mint(&mut self, account_id: &AccountId, amount: Balance, memo: &str) {
+        self.assert_not_paused();
+        self.assert_supply_not_frozen();
+        assert!(amount > 0, "The amount should be a positive number");
+        self.internal_record_block_mint(amount);
+        self.internal_record_annual_mint(amount);
+        self.internal_apply_demurrage(account_id);
+        self.internal_settle_rewards(account_id);
+        self.token.internal_deposit(account_id, amount);
+        self.internal_record_balance(account_id);
+        self.internal_record_total_supply();
+        FtMint { owner_id: account_id, amount: &U128(amount), memo: Some(memo) }.emit();
+    }
+
+    /// Destroys `amount` tokens of `account_id`. All supply decreases go through here.
+    pub(crate) fn internal_burn(&mut self, account_id: &AccountId, amount: Balance, memo: &str) {
+        self.assert_not_paused();
+        self.assert_supply_not_frozen();
+        assert!(amount > 0, "The amount should be a positive number");
+        self.internal_apply_demurrage(account_id);
+        self.assert_unlocked(account_id, amount);
+        self.internal_settle_rewards(account_id);
+        self.token.internal_withdraw(account_id, amount);
+        self.internal_record_balance(account_id);
+        self.internal_reset_hold_streak(account_id);
+        FtBurn { owner_id: account_id, amount: &U128(amount), memo: Some(memo) }.emit();
+        self.on_tokens_burned(account_id.clone(), amount);
+    }
+
+    /// Moves `amount` from `sender_id` to `receiver_id` without any of the transfer checks or
+    /// fees. Used for the contract's own bookkeeping, e.g. fees, claims and inheritance.
+    pub(crate) fn internal_move_balance(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Balance,
+        memo: Option<String>,
+    ) {
+        self.internal_apply_demurrage(sender_id);
+        self.internal_apply_demurrage(receiver_id);
+        self.assert_unlocked(sender_id, amount);
+        self.internal_settle_rewards(sender_id);
+        self.internal_settle_rewards(receiver_id);
+        self.token.internal_transfer(sender_id, receiver_id, amount, memo);
+        self.internal_record_balance(sender_id);
+        self.internal_record_balance(receiver_id);
+        if receiver_id != &env::current_account_id() {
+            self.internal_reset_hold_streak(sender_id);
+        }
+    }
+}
+
+/// Panics with the error of `result`, if any.
+pub(crate) fn assert_ok(result: Result<(), String>) {
+    if let Err(error) = result {
+        panic!("{}", error);
+    }
+}
+
+/// Returns the current block timestamp in seconds.
+pub(crate) fn current_timestamp_sec() -> u64 {
+    env::block_timestamp() / 1_000_000_000
+}
+
+/// Refunds the attached deposit minus the cost of the storage used since `initial_storage_usage`.
+/// Panics if the attached deposit doesn't cover the storage.
+/// Requires at least 1 yoctoNEAR attached, so the call needs a full access key even when it adds
+/// no storage.
+pub(crate) fn assert_at_least_one_yocto() {
+    assert!(env::attached_deposit() >= 1, "Requires attached deposit of at least 1 yoctoNEAR");
+}
+
+pub(crate) fn refund_deposit(initial_storage_usage: StorageUsage) {
+    refund_deposit_after_fee(initial_storage_usage, 0);
+}
+
+/// Same as `refund_deposit`, for when `fee` was already paid out of the attached deposit.
+pub(crate) fn refund_deposit_after_fee(initial_storage_usage: StorageUsage, fee: Balance) {
+    refund_deposit_to(&env::predecessor_account_id(), initial_storage_usage, fee);
+}
+
+/// Same as `refund_deposit_after_fee`, refunding to `account_id` instead of the caller, e.g. in a
+/// callback that was forwarded the deposit.
+pub(crate) fn refund_deposit_to(
+    account_id: &AccountId,
+    initial_storage_usage: StorageUsage,
+    fee: Balance,
+) {
+    let storage_cost = env::storage_usage().saturating_sub(initial_storage_usage) as Balance
+        * env::storage_byte_cost();
+    let attached_deposit = env::attached_deposit() - fee;
+    assert!(
+        attached_deposit >= storage_cost,
+        "Must attach {} yoctoNEAR to cover storage",
+        storage_cost
+    );
+    let refund = attached_deposit - storage_cost;
+    if refund > 0 {
+        Promise::new(account_id.clone()).transfer(refund);
+    }
+}
+
+#[near_bindgen]
+impl FungibleTokenCore for Contract {
+    #[payable]
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) {
+        let sender_id = env::predecessor_account_id();
+        let decision_deposit = self.internal_transfer_decision_deposit(&sender_id, &receiver_id);
+        self.assert_priority_fee_attached_with(&[amount.0], decision_deposit);
+        self.assert_no_transfer_validator();
+        self.assert_no_attestation_gate();
+        if self.internal_defer_to_transfer_policy(
+            &sender_id,
+            &receiver_id,
+            amount,
+            &memo,
+            decision_deposit,
+        ) {
+            return;
+        }
+        self.internal_transfer(&sender_id, &receiver_id, amount.into(), memo);
+    }
+
+    #[payable]
+    fn ft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        self.assert_priority_fee_attached(&[amount.0]);
+        self.assert_transfer_call_gas(1);
+        self.assert_no_transfer_validator();
+        self.assert_no_attestation_gate();
+        let sender_id = env::predecessor_account_id();
+        let amount =
+            U128(self.internal_transfer_call(&sender_id, &receiver_id, amount.into(), memo));
+        let receiver_gas = env::prepaid_gas() - GAS_FOR_FT_TRANSFER_CALL;
+        // Initiating receiver's call and the callback
+        ext_ft_receiver::ext(receiver_id.clone())
+            .with_static_gas(receiver_gas)
+            .ft_on_transfer(sender_id.clone(), amount, msg)
+            .then(
+                ext_ft_resolver::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_TRANSFER)
+                    .ft_resolve_transfer(sender_id, receiver_id, amount),
+            )
+            .into()
+    }
+
+    fn ft_total_supply(&self) -> U128 {
+        self.token.ft_total_supply()
+    }
+
+    /// Reads as zero for accounts in private mode, see `ft_balance_of_authorized`.
+    fn ft_balance_of(&self, account_id: AccountId) -> U128 {
+        if self.private_balances.contains(&account_id) {
+            return 0.into();
+        }
+        self.internal_decayed_balance(&account_id).into()
+    }
+}
+
+#[near_bindgen]
+impl FungibleTokenResolver for Contract {
+    #[private]
+    fn ft_resolve_transfer(
+        &mut self,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+    ) -> U128 {
+        self.internal_apply_demurrage(&sender_id);
+        self.internal_apply_demurrage(&receiver_id);
+        self.internal_settle_rewards(&sender_id);
+        self.internal_settle_rewards(&receiver_id);
+        // The refund can't take the receiver's pinned tokens, so they're set aside meanwhile.
+        let balance = self.token.accounts.get(&receiver_id).unwrap_or(0);
+        let pinned = self.internal_pinned_balance(&receiver_id).min(balance);
+        if pinned > 0 {
+            self.token.accounts.insert(&receiver_id, &(balance - pinned));
+        }
+        let (used_amount, burned_amount) =
+            self.token.internal_ft_resolve_transfer(&sender_id, receiver_id.clone(), amount);
+        if pinned > 0 {
+            let balance = self.token.accounts.get(&receiver_id).unwrap_or(0);
+            self.token.accounts.insert(&receiver_id, &(balance + pinned));
+        }
+        self.internal_record_balance(&sender_id);
+        self.internal_record_balance(&receiver_id);
+        if used_amount < amount.0 {
+            self.internal_reset_hold_streak(&receiver_id);
+        }
+        if burned_amount > 0 {
+            self.on_tokens_burned(sender_id, burned_amount);
+        }
+        used_amount.into()
+    }
+}
+
+#[near_bindgen]
+impl StorageManagement for Contract {
+    /// Charges the registration cost for the length of `account_id`, see
+    /// `estimate_registration_cost`, and refunds the rest of the deposit. `registration_only` makes
+    /// no difference, since nothing beyond the registration cost is ever kept.
+    #[allow(unused_variables)]
+    #[payable]
+    fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        let amount = env::attached_deposit();
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let refund = if self.token.accounts.contains_key(&account_id) {
+            log!("The account is already registered, refunding the deposit");
+            amount
+        } else {
+            let cost = self.internal_registration_cost(&account_id);
+            assert!(amount >= cost, "The attached deposit is less than the registration cost");
+            self.internal_register_account(&account_id);
+            self.internal_on_account_registered(&account_id);
+            amount - cost
+        };
+        if refund > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+        self.storage_balance_of(account_id).unwrap()
+    }
+
+    /// There's never anything available to withdraw, since registration costs exactly the
+    /// storage it uses.
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let storage_balance = self
+            .storage_balance_of(account_id.clone())
+            .unwrap_or_else(|| panic!("The account {} is not registered", account_id));
+        assert!(
+            !matches!(amount, Some(amount) if amount.0 > 0),
+            "The amount is greater than the available storage balance"
+        );
+        storage_balance
+    }
+
+    /// Force-unregistering an account with a positive balance burns that balance, so it's not
+    /// allowed once the supply is frozen.
+    #[payable]
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        if self.supply_frozen {
+            let balance = self.token.accounts.get(&env::predecessor_account_id()).unwrap_or(0);
+            assert!(balance == 0, "The supply is frozen, can't burn the remaining balance");
+        }
+        assert!(
+            self.stakes.get(&env::predecessor_account_id()).is_none(),
+            "Unstake all positions before unregistering"
+        );
+        assert!(
+            self.internal_locked_balance(&env::predecessor_account_id()) == 0
+                && self.internal_held_balance(&env::predecessor_account_id()) == 0,
+            "Wait for the locked tokens to unlock before unregistering"
+        );
+        assert!(
+            self.internal_sub_allocated(&env::predecessor_account_id()) == 0,
+            "Withdraw all sub-balances before unregistering"
+        );
+        assert!(
+            self.internal_collateral_locked(&env::predecessor_account_id()) == 0,
+            "Wait for the collateral to be released before unregistering"
+        );
+        assert!(
+            self.fee_token_deposits.get(&env::predecessor_account_id()).is_none(),
+            "Withdraw the fee token deposit before unregistering"
+        );
+        if self.token_funded_accounts.contains(&env::predecessor_account_id()) {
+            return self.internal_unregister_token_funded(force);
+        }
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let balance = match self.token.accounts.get(&account_id) {
+            Some(balance) => balance,
+            None => {
+                log!("The account {} is not registered", account_id);
+                return false;
+            }
+        };
+        assert!(
+            balance == 0 || force.unwrap_or(false),
+            "Can't unregister the account with the positive balance without force"
+        );
+        let refund = self.internal_paid_registration(&account_id) + 1;
+        self.internal_settle_rewards(&account_id);
+        self.token.accounts.remove(&account_id);
+        self.token.total_supply -= balance;
+        Promise::new(account_id.clone()).transfer(refund);
+        self.internal_record_change(format!("account_closed:{}", account_id));
+        self.on_account_closed(account_id, balance);
+        true
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        self.token.storage_balance_bounds()
+    }
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        if !self.token.accounts.contains_key(&account_id) {
+            return None;
+        }
+        Some(StorageBalance {
+            total: self.internal_paid_registration(&account_id).into(),
+            available: 0.into(),
+        })
+    }
+}
+
+#[near_bindgen]
+impl FungibleTokenReceiver for Contract {
+    /// Handles other tokens sent to this contract with `ft_transfer_call`. Returns the amount to
+    /// refund to the sender.
+    fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        let token_id = env::predecessor_account_id();
+        if self.stable_peg.as_ref().map(|peg| &peg.stable_token_id) == Some(&token_id) {
+            self.internal_on_stable_transfer(sender_id, amount.0, &msg)
+        } else if self.fee_token.as_ref().map(|fee_token| &fee_token.token_id) == Some(&token_id) {
+            self.internal_on_fee_token_transfer(sender_id, amount.0, &msg)
+        } else if self.conversions.get(&token_id).is_some() {
+            self.internal_on_conversion_transfer(&token_id, sender_id, amount.0)
+        } else {
+            panic!("Unsupported token")
+        }
+    }
+}
 
 #[near_bindgen]
 impl FungibleTokenMetadataProvider for Contract {
@@ -97,14 +1310,14 @@ impl FungibleTokenMetadataProvider for Contract {
 #[cfg(all(test, not(target_arch = "wasm32")))]
 mod tests {
     use near_sdk::test_utils::{accounts, VMContextBuilder};
-    use near_sdk::MockedBlockchain;
     use near_sdk::{testing_env, Balance};
 
     use super::*;
 
-    const TOTAL_SUPPLY: Balance = 1_000_000_000_000_000;
+    pub(crate) const TOTAL_SUPPLY: Balance = 1_000_000_000_000_000;
+    pub(crate) const NANOS: u64 = 1_000_000_000;
 
-    fn get_context(predecessor_account_id: AccountId) -> VMContextBuilder {
+    pub(crate) fn get_context(predecessor_account_id: AccountId) -> VMContextBuilder {
         let mut builder = VMContextBuilder::new();
         builder
             .current_account_id(accounts(0))
@@ -117,12 +1330,36 @@ mod tests {
     fn test_new() {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
-        let contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        let contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
         testing_env!(context.is_view(true).build());
         assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
         assert_eq!(contract.ft_balance_of(accounts(1)).0, TOTAL_SUPPLY);
     }
 
+    #[test]
+    fn test_new_empty() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let metadata = FungibleTokenMetadata {
+            spec: FT_METADATA_SPEC.to_string(),
+            name: "Empty".to_string(),
+            symbol: "EMPTY".to_string(),
+            icon: None,
+            reference: None,
+            reference_hash: None,
+            decimals: 24,
+        };
+        let mut contract = Contract::new_empty(accounts(1), metadata);
+        assert!(near_sdk::test_utils::get_logs().is_empty());
+        assert_eq!(contract.ft_total_supply().0, 0);
+        assert!(contract.storage_balance_of(accounts(1)).is_some());
+        contract.add_minter(accounts(1));
+        testing_env!(context.attached_deposit(near_sdk::ONE_NEAR / 100).build());
+        let request_id = contract.propose_mint(accounts(1), 100.into(), "seed".to_string());
+        contract.approve_mint(request_id);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 100);
+    }
+
     #[test]
     #[should_panic(expected = "The contract is not initialized")]
     fn test_default() {
@@ -135,7 +1372,7 @@ mod tests {
     fn test_transfer() {
         let mut context = get_context(accounts(2));
         testing_env!(context.build());
-        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        let mut contract = Contract::new_default_meta(accounts(2), TOTAL_SUPPLY.into());
         testing_env!(context
             .storage_usage(env::storage_usage())
             .attached_deposit(contract.storage_balance_bounds().min.into())
@@ -0,0 +1,27 @@
+use near_sdk::Balance;
+
+pub use self::uint_types::U256;
+
+#[allow(clippy::all)]
+mod uint_types {
+    use uint::construct_uint;
+
+    construct_uint! {
+        /// 256-bit unsigned integer, wide enough for the product of two balances.
+        pub struct U256(4);
+    }
+}
+
+/// Computes `a * b / c` without overflowing the intermediate product. Rounds down.
+pub fn mul_div(a: Balance, b: Balance, c: Balance) -> Balance {
+    assert!(c > 0, "Division by zero");
+    (U256::from(a) * U256::from(b) / U256::from(c)).as_u128()
+}
+
+/// Basis points in 100%.
+pub const BPS_DENOMINATOR: u16 = 10_000;
+
+/// Returns `bps` basis points of `amount`, rounded down.
+pub fn bps_of(amount: Balance, bps: u16) -> Balance {
+    mul_div(amount, bps as Balance, BPS_DENOMINATOR as Balance)
+}
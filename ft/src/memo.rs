@@ -0,0 +1,44 @@
+/*!
+Limit on the length of transfer memos. Memos end up in the logs, and in the calls to incoming
+hooks and transfer validators, so unbounded memos would let anyone bloat them.
+*/
+use crate::*;
+
+/// Longest accepted memo, in bytes.
+pub const MAX_MEMO_LEN: u32 = 256;
+
+#[near_bindgen]
+impl Contract {
+    pub fn max_memo_length(&self) -> u32 {
+        MAX_MEMO_LEN
+    }
+}
+
+/// Fails if `memo` is longer than `MAX_MEMO_LEN` bytes.
+pub(crate) fn check_memo(memo: &Option<String>) -> Result<(), String> {
+    match memo {
+        Some(memo) if memo.len() > MAX_MEMO_LEN as usize => {
+            Err(format!("The memo can't be longer than {} bytes", MAX_MEMO_LEN))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    #[test]
+    #[should_panic(expected = "The memo can't be longer than 256 bytes")]
+    fn test_oversized_memo() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        testing_env!(context.attached_deposit(1).build());
+        contract.ft_transfer(accounts(2), 1.into(), Some("a".repeat(257)));
+    }
+}
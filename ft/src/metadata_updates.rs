@@ -0,0 +1,156 @@
+/*!
+Owner updates of the token metadata, and mirroring of the metadata to another contract, e.g. a
+bridge registry that needs to stay in sync with wrapped versions of the token.
+*/
+use crate::*;
+use near_sdk::is_promise_success;
+use near_sdk::json_types::Base64VecU8;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+
+const GAS_FOR_PUSH_METADATA: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_ON_METADATA_PUSHED: Gas = Gas(5_000_000_000_000);
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MetadataMirror {
+    pub contract_id: AccountId,
+    pub method_name: String,
+}
+
+#[near_bindgen]
+impl Contract {
+    #[payable]
+    pub fn update_metadata_name(&mut self, name: String) {
+        self.internal_update_metadata(|metadata| metadata.name = name);
+    }
+
+    #[payable]
+    pub fn update_metadata_symbol(&mut self, symbol: String) {
+        self.internal_update_metadata(|metadata| metadata.symbol = symbol);
+    }
+
+    #[payable]
+    pub fn update_metadata_icon(&mut self, icon: Option<String>) {
+        self.internal_update_metadata(|metadata| metadata.icon = icon);
+    }
+
+    #[payable]
+    pub fn update_metadata_reference(
+        &mut self,
+        reference: Option<String>,
+        reference_hash: Option<Base64VecU8>,
+    ) {
+        self.internal_update_metadata(|metadata| {
+            metadata.reference = reference;
+            metadata.reference_hash = reference_hash;
+        });
+    }
+
+    /// Sets how many decimals UIs should show, at most the token's decimals, or shows all of them
+    /// when `None`. Only a presentation hint, balances keep their full precision.
+    pub fn update_metadata_display_decimals(&mut self, display_decimals: Option<u8>) {
+        self.assert_owner_action();
+        if let Some(display_decimals) = display_decimals {
+            let decimals = self.metadata.get().unwrap().decimals;
+            assert!(
+                display_decimals <= decimals,
+                "The display decimals can't exceed the {} decimals of the token",
+                decimals
+            );
+        }
+        self.display_decimals = display_decimals;
+        self.metadata_version += 1;
+    }
+
+    pub fn get_display_decimals(&self) -> Option<u8> {
+        self.display_decimals
+    }
+
+    /// Goes up with every metadata update, so indexers can poll it instead of the full metadata.
+    pub fn ft_metadata_version(&self) -> u64 {
+        self.metadata_version
+    }
+
+    /// Sets the contract the metadata is pushed to after every update, or stops mirroring.
+    pub fn set_metadata_mirror_target(&mut self, target: Option<MetadataMirror>) {
+        self.assert_owner_action();
+        self.metadata_mirror_target = target;
+    }
+
+    pub fn get_metadata_mirror_target(&self) -> Option<MetadataMirror> {
+        self.metadata_mirror_target.clone()
+    }
+
+    /// Calls `target.method` with `{ "metadata": <current metadata> }`.
+    pub fn push_metadata_to(&self, target: AccountId, method: String) -> Promise {
+        self.assert_owner();
+        self.internal_push_metadata(target, method)
+    }
+
+    #[private]
+    pub fn on_metadata_pushed(&self, target: AccountId) -> bool {
+        let pushed = is_promise_success();
+        if pushed {
+            log!("Metadata pushed to @{}", target);
+        } else {
+            log!("Failed to push metadata to @{}", target);
+        }
+        pushed
+    }
+}
+
+impl Contract {
+    /// Applies `update` to the metadata as the owner and pushes the result to the mirror target.
+    /// Attached deposit must cover the added storage, the rest is refunded.
+    fn internal_update_metadata<F: FnOnce(&mut FungibleTokenMetadata)>(&mut self, update: F) {
+        self.assert_owner_action();
+        let initial_storage_usage = env::storage_usage();
+        let mut metadata = self.metadata.get().unwrap();
+        update(&mut metadata);
+        metadata.assert_valid();
+        self.metadata.set(&metadata);
+        self.metadata_version += 1;
+        refund_deposit(initial_storage_usage);
+        if let Some(mirror) = self.metadata_mirror_target.clone() {
+            self.internal_push_metadata(mirror.contract_id, mirror.method_name);
+        }
+    }
+
+    fn internal_push_metadata(&self, target: AccountId, method: String) -> Promise {
+        let args = json!({ "metadata": self.metadata.get().unwrap() });
+        Promise::new(target.clone())
+            .function_call(method, args.to_string().into_bytes(), 0, GAS_FOR_PUSH_METADATA)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_ON_METADATA_PUSHED)
+                    .on_metadata_pushed(target),
+            )
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, get_created_receipts};
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_update_pushes_to_mirror() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_metadata_mirror_target(Some(MetadataMirror {
+            contract_id: accounts(4),
+            method_name: "sync_metadata".to_string(),
+        }));
+        assert_eq!(contract.ft_metadata_version(), 1);
+        testing_env!(context.attached_deposit(ONE_NEAR / 100).build());
+        contract.update_metadata_name("HealthGo Token".to_string());
+        assert_eq!(contract.ft_metadata().name, "HealthGo Token");
+        assert_eq!(contract.ft_metadata_version(), 2);
+        assert!(get_created_receipts().iter().any(|receipt| receipt.receiver_id == accounts(4)));
+    }
+}
@@ -0,0 +1,162 @@
+/*!
+Migration of deployments of the original contract, which only stored the standard's `token` and
+`metadata`. After deploying the new code, the contract account calls `migrate` once, which keeps
+every balance, the total supply and the metadata and starts all other features at their defaults,
+like a fresh `new` would.
+
+Accounts registered before the migration keep their balances, but paid for the smaller
+registration of the original contract, which is what `storage_unregister` refunds them. Rewards
+don't track them and they aren't part of `get_holders` until their balance first changes, when
+the contract starts tracking them at its own expense. Until then their balances don't count
+towards the supply rewards are distributed over.
+
+The storage of a registration is measured on empty balances, since the migrated ones may already
+hold the temporary accounts the measurement registers.
+*/
+use crate::*;
+
+/// State layout of the original contract.
+#[derive(BorshDeserialize)]
+struct OldContract {
+    token: FungibleToken,
+    metadata: LazyOption<FungibleTokenMetadata>,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Migrates the original contract's state, with `owner_id` as the owner. Only callable by the
+    /// contract account, and only once.
+    #[init(ignore_state)]
+    #[private]
+    pub fn migrate(owner_id: AccountId) -> Self {
+        let state = env::storage_read(b"STATE").expect("The contract is not initialized");
+        let old = OldContract::try_from_slice(&state).expect("The contract was already migrated");
+        let legacy_registration_cost =
+            old.token.account_storage_usage as Balance * env::storage_byte_cost();
+        let mut this = Self::with_defaults(
+            owner_id.clone(),
+            FungibleToken::new(StorageKey::MigrationAccounts),
+            old.metadata,
+        );
+        let account_storage_usage = this.token.account_storage_usage;
+        this.token = old.token;
+        this.token.account_storage_usage = account_storage_usage;
+        this.legacy_registration_cost = legacy_registration_cost;
+        let current_account_id = env::current_account_id();
+        this.legacy_supply =
+            this.token.total_supply - this.token.accounts.get(&current_account_id).unwrap_or(0);
+        if this.token.accounts.get(&owner_id).is_none() {
+            this.internal_register_account(&owner_id);
+        }
+        this.internal_record_total_supply();
+        this
+    }
+}
+
+impl Contract {
+    /// Registration cost `account_id` paid, and gets back when unregistering.
+    pub(crate) fn internal_paid_registration(&self, account_id: &AccountId) -> Balance {
+        match self.registration_paid.get(account_id) {
+            Some(paid) => paid,
+            None if self.internal_is_untracked_legacy_account(account_id) => {
+                self.legacy_registration_cost
+            }
+            None => self.internal_registration_cost(account_id),
+        }
+    }
+
+    fn internal_is_untracked_legacy_account(&self, account_id: &AccountId) -> bool {
+        self.legacy_registration_cost > 0
+            && account_id != &env::current_account_id()
+            && self.reward_checkpoints.get(account_id).is_none()
+    }
+
+    /// Starts tracking rewards for `account_id`, registered before the migration with `balance`,
+    /// and adds it to the holders.
+    pub(crate) fn internal_track_legacy_account(
+        &mut self,
+        account_id: &AccountId,
+        balance: Balance,
+    ) {
+        if !self.internal_is_untracked_legacy_account(account_id) {
+            return;
+        }
+        self.legacy_supply -= balance;
+        self.registration_paid.insert(account_id, &self.legacy_registration_cost);
+        self.internal_init_reward_checkpoint(account_id);
+        self.holders.insert(account_id);
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_contract_standards::fungible_token::metadata::FT_METADATA_SPEC;
+    use near_sdk::borsh::BorshSerialize;
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::balance_proofs::BALANCES_PREFIX;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    #[derive(BorshSerialize)]
+    struct OriginalContract {
+        token: FungibleToken,
+        metadata: LazyOption<FungibleTokenMetadata>,
+    }
+
+    /// Deploys the original contract and returns the storage of its registrations.
+    fn deploy_original() -> StorageUsage {
+        let metadata = FungibleTokenMetadata {
+            spec: FT_METADATA_SPEC.to_string(),
+            name: "HealthGo".to_string(),
+            symbol: "HGT".to_string(),
+            icon: None,
+            reference: None,
+            reference_hash: None,
+            decimals: 24,
+        };
+        let mut token = FungibleToken::new(BALANCES_PREFIX.to_vec());
+        token.internal_register_account(&accounts(2));
+        token.internal_deposit(&accounts(2), TOTAL_SUPPLY);
+        // The account a registration is measured with.
+        token.internal_register_account(&AccountId::new_unchecked("aa".to_string()));
+        let account_storage_usage = token.account_storage_usage;
+        let original =
+            OriginalContract { token, metadata: LazyOption::new(b"m".to_vec(), Some(&metadata)) };
+        env::state_write(&original);
+        account_storage_usage
+    }
+
+    #[test]
+    #[should_panic(expected = "The contract was already migrated")]
+    fn test_migrate_once() {
+        testing_env!(get_context(accounts(1)).build());
+        deploy_original();
+        let contract = Contract::migrate(accounts(1));
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+        assert_eq!(contract.ft_metadata().symbol, "HGT");
+        assert_eq!(contract.get_owner(), accounts(1));
+        assert!(contract.storage_balance_of(accounts(1)).is_some());
+        env::state_write(&contract);
+        Contract::migrate(accounts(1));
+    }
+
+    #[test]
+    fn test_legacy_accounts() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let legacy_storage_usage = deploy_original();
+        let mut contract = Contract::migrate(accounts(1));
+        let legacy_cost = legacy_storage_usage as Balance * env::storage_byte_cost();
+        assert_eq!(contract.storage_balance_of(accounts(2)).unwrap().total.0, legacy_cost);
+        assert!(!contract.get_holders(None, None).contains(&accounts(2)));
+        assert_eq!(contract.internal_eligible_supply(), 0);
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(2)).build());
+        contract.ft_transfer(accounts(1), 1_000.into(), None);
+        assert!(contract.get_holders(None, None).contains(&accounts(2)));
+        assert_eq!(contract.internal_eligible_supply(), TOTAL_SUPPLY);
+        assert_eq!(contract.storage_balance_of(accounts(2)).unwrap().total.0, legacy_cost);
+    }
+}
@@ -0,0 +1,92 @@
+/*!
+Ceiling on the amount minted per block, across all mint paths and all minters, so a compromised
+minter can't inflate the supply in one go.
+*/
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the most that can be minted within a single block, or removes the ceiling.
+    pub fn set_max_mint_per_block(&mut self, max_mint_per_block: Option<U128>) {
+        self.assert_owner_action();
+        self.max_mint_per_block = max_mint_per_block.map(|amount| amount.0);
+    }
+
+    pub fn get_max_mint_per_block(&self) -> Option<U128> {
+        self.max_mint_per_block.map(U128)
+    }
+
+    /// How much can still be minted in the current block, or `None` if there's no ceiling.
+    pub fn get_block_mint_headroom(&self) -> Option<U128> {
+        self.max_mint_per_block.map(|max| U128(max.saturating_sub(self.minted_in_current_block())))
+    }
+}
+
+impl Contract {
+    fn minted_in_current_block(&self) -> Balance {
+        if self.minted_block_height == env::block_height() {
+            self.minted_this_block
+        } else {
+            0
+        }
+    }
+
+    /// How much can be minted right now without hitting any mint limit.
+    pub(crate) fn internal_mint_headroom(&self) -> Balance {
+        let block_headroom = self.get_block_mint_headroom().map_or(Balance::MAX, |h| h.0);
+        let annual_headroom = self.get_annual_mint_headroom().map_or(Balance::MAX, |h| h.0);
+        block_headroom.min(annual_headroom)
+    }
+
+    /// Counts `amount` towards the current block. Panics if it exceeds the ceiling.
+    pub(crate) fn internal_record_block_mint(&mut self, amount: Balance) {
+        let minted = self.minted_in_current_block() + amount;
+        if let Some(max_mint_per_block) = self.max_mint_per_block {
+            assert!(minted <= max_mint_per_block, "The mint exceeds the per-block ceiling");
+        }
+        self.minted_block_height = env::block_height();
+        self.minted_this_block = minted;
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.block_index(1).build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_max_mint_per_block(Some(100.into()));
+        contract.internal_mint(&accounts(1), 60, "mint");
+        (context, contract)
+    }
+
+    #[test]
+    fn test_headroom_resets_each_block() {
+        let (mut context, mut contract) = setup();
+        assert_eq!(contract.get_block_mint_headroom(), Some(40.into()));
+        testing_env!(context.block_index(2).build());
+        assert_eq!(contract.get_block_mint_headroom(), Some(100.into()));
+        contract.internal_mint(&accounts(1), 100, "mint");
+        assert_eq!(contract.get_block_mint_headroom(), Some(0.into()));
+    }
+
+    #[test]
+    fn test_headroom_after_lowering_ceiling() {
+        let (_, mut contract) = setup();
+        contract.set_max_mint_per_block(Some(50.into()));
+        assert_eq!(contract.get_block_mint_headroom(), Some(0.into()));
+    }
+
+    #[test]
+    #[should_panic(expected = "The mint exceeds the per-block ceiling")]
+    fn test_mint_above_ceiling() {
+        let (_, mut contract) = setup();
+        contract.internal_mint(&accounts(1), 41, "mint");
+    }
+}
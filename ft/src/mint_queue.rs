@@ -0,0 +1,203 @@
+/*!
+Two-person rule for supply expansion: minters can only propose mints, which the owner then
+approves or rejects. Requests that aren't approved within the configured TTL expire.
+
+If the owner requires collateral, approved requests are only executed once the reserves are
+checked, see `reserve_ratio`.
+*/
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+
+pub const DEFAULT_MINT_REQUEST_TTL_SEC: u64 = 7 * 24 * 60 * 60;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MintRequest {
+    pub minter_id: AccountId,
+    pub account_id: AccountId,
+    pub amount: U128,
+    pub reason: String,
+    /// Timestamp (in seconds) after which the request can no longer be approved.
+    pub expires_at: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn add_minter(&mut self, account_id: AccountId) {
+        self.assert_owner_action();
+        self.minters.insert(&account_id);
+    }
+
+    pub fn remove_minter(&mut self, account_id: AccountId) {
+        self.assert_owner_action();
+        self.minters.remove(&account_id);
+    }
+
+    pub fn get_minters(&self) -> Vec<AccountId> {
+        self.minters.to_vec()
+    }
+
+    /// Sets how long (in seconds) new mint requests stay approvable.
+    pub fn set_mint_request_ttl(&mut self, ttl: u64) {
+        self.assert_owner_action();
+        assert!(ttl > 0, "The TTL should be a positive number");
+        self.mint_request_ttl = ttl;
+    }
+
+    pub fn get_mint_request_ttl(&self) -> u64 {
+        self.mint_request_ttl
+    }
+
+    /// Queues a request to mint `amount` to `account_id` for the owner to approve. Only callable
+    /// by minters. Attached deposit must cover the storage of the request, the rest is refunded.
+    /// Returns the request id.
+    #[payable]
+    pub fn propose_mint(&mut self, account_id: AccountId, amount: U128, reason: String) -> u64 {
+        let minter_id = env::predecessor_account_id();
+        assert!(self.minters.contains(&minter_id), "Only a minter can propose mints");
+        assert!(amount.0 > 0, "The amount should be a positive number");
+        self.token.internal_unwrap_balance_of(&account_id);
+        let initial_storage_usage = env::storage_usage();
+        let request_id = self.next_mint_request_id;
+        self.next_mint_request_id += 1;
+        let request = MintRequest {
+            minter_id,
+            account_id,
+            amount,
+            reason,
+            expires_at: current_timestamp_sec() + self.mint_request_ttl,
+        };
+        self.mint_requests.insert(&request_id, &request);
+        refund_deposit(initial_storage_usage);
+        request_id
+    }
+
+    /// Executes a queued mint request, after checking the reserves if collateral is required.
+    /// Only callable by the owner. Resolves to whether the mint happened.
+    pub fn approve_mint(&mut self, request_id: u64) -> PromiseOrValue<bool> {
+        self.assert_owner_action();
+        assert!(self.mint_requests.get(&request_id).is_some(), "Mint request not found");
+        match self.internal_reserve_check(request_id) {
+            Some(check) => check.into(),
+            None => {
+                self.internal_execute_mint_request(request_id);
+                PromiseOrValue::Value(true)
+            }
+        }
+    }
+
+    /// Drops a queued mint request, whether expired or not, and refunds its storage to the minter
+    /// who proposed it. Callable by the owner or by that minter.
+    pub fn reject_mint(&mut self, request_id: u64) {
+        let request = self.mint_requests.get(&request_id).expect("Mint request not found");
+        let predecessor_id = env::predecessor_account_id();
+        assert!(
+            predecessor_id == self.owner_id || predecessor_id == request.minter_id,
+            "Only the owner or the proposing minter can reject a mint request"
+        );
+        self.internal_remove_mint_request(request_id);
+    }
+
+    /// Mint requests that can still be approved. Expired ones are left out, the owner or the
+    /// proposing minter can still reject them by ID to free the storage.
+    pub fn get_mint_requests(
+        &self,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<(u64, MintRequest)> {
+        let from_index = from_index.unwrap_or(0) as usize;
+        let limit = limit.unwrap_or(u64::MAX) as usize;
+        let now = current_timestamp_sec();
+        self.mint_requests
+            .iter()
+            .filter(|(_, request)| now < request.expires_at)
+            .skip(from_index)
+            .take(limit)
+            .collect()
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_execute_mint_request(&mut self, request_id: u64) {
+        let request = self.internal_remove_mint_request(request_id);
+        assert!(current_timestamp_sec() < request.expires_at, "The mint request has expired");
+        self.internal_mint(&request.account_id, request.amount.0, &request.reason);
+    }
+
+    /// Removes a mint request and refunds the freed storage to the minter who proposed it.
+    fn internal_remove_mint_request(&mut self, request_id: u64) -> MintRequest {
+        let initial_storage_usage = env::storage_usage();
+        let request = self.mint_requests.remove(&request_id).expect("Mint request not found");
+        let freed = initial_storage_usage.saturating_sub(env::storage_usage());
+        if freed > 0 {
+            Promise::new(request.minter_id.clone())
+                .transfer(freed as Balance * env::storage_byte_cost());
+        }
+        request
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.add_minter(accounts(2));
+        testing_env!(context
+            .attached_deposit(ONE_NEAR / 100)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.propose_mint(accounts(1), 100.into(), "grant".to_string());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_approve_mint() {
+        let (mut context, mut contract) = setup();
+        assert_eq!(contract.get_mint_requests(None, None).len(), 1);
+        testing_env!(context.attached_deposit(0).predecessor_account_id(accounts(1)).build());
+        contract.approve_mint(0);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY + 100);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, TOTAL_SUPPLY + 100);
+        assert!(contract.get_mint_requests(None, None).is_empty());
+    }
+
+    #[test]
+    fn test_reject_refunds_storage() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.attached_deposit(0).predecessor_account_id(accounts(1)).build());
+        contract.reject_mint(0);
+        assert!(contract.get_mint_requests(None, None).is_empty());
+        assert_eq!(near_sdk::test_utils::get_created_receipts().len(), 1);
+    }
+
+    #[test]
+    fn test_expired_requests_are_hidden() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.block_timestamp(NANOS).build());
+        contract.propose_mint(accounts(1), 50.into(), "grant".to_string());
+        testing_env!(context.block_timestamp(DEFAULT_MINT_REQUEST_TTL_SEC * NANOS).build());
+        let requests = contract.get_mint_requests(None, None);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].0, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "The mint request has expired")]
+    fn test_approve_expired_mint() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(DEFAULT_MINT_REQUEST_TTL_SEC * NANOS)
+            .build());
+        contract.approve_mint(0);
+    }
+}
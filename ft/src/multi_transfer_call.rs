@@ -0,0 +1,108 @@
+/*!
+`ft_transfer_call` to several receivers in one transaction, e.g. to split a payment across
+contracts. All transfers happen upfront. Then each receiver's `ft_on_transfer` is called, followed
+by its own `ft_resolve_transfer`, one receiver after the other. Each resolver only looks at the
+result of its own receiver, so a failing receiver gets refunded without affecting the others.
+*/
+use crate::*;
+
+/// Most receivers a single call can pay.
+pub const MAX_TRANSFER_CALLS: usize = 10;
+
+#[near_bindgen]
+impl Contract {
+    /// Transfers to every `(receiver_id, amount, msg)` in `calls` and calls `ft_on_transfer` on
+    /// each receiver. Resolves to the amount used by the last receiver. The prepaid gas, beyond
+    /// what the resolvers need, is split evenly between the receivers.
+    #[payable]
+    pub fn ft_transfer_call_multi(&mut self, calls: Vec<(AccountId, U128, String)>) -> Promise {
+        assert!(!calls.is_empty(), "Nothing to transfer");
+        assert!(calls.len() <= MAX_TRANSFER_CALLS, "At most {} calls", MAX_TRANSFER_CALLS);
+        let amounts: Vec<Balance> = calls.iter().map(|(_, amount, _)| amount.0).collect();
+        self.assert_priority_fee_attached(&amounts);
+        self.assert_transfer_call_gas(calls.len());
+        self.assert_no_transfer_validator();
+        self.assert_no_attestation_gate();
+        let sender_id = env::predecessor_account_id();
+        let total = calls
+            .iter()
+            .try_fold(0u128, |total, (_, amount, _)| total.checked_add(amount.0))
+            .expect("The total amount overflows");
+        assert!(
+            total <= self.token.internal_unwrap_balance_of(&sender_id),
+            "The total amount exceeds the balance"
+        );
+
+        let count = calls.len() as u64;
+        let transfers: Vec<(AccountId, U128, String)> = calls
+            .into_iter()
+            .map(|(receiver_id, amount, msg)| {
+                let amount = self.internal_transfer_call(&sender_id, &receiver_id, amount.0, None);
+                (receiver_id, amount.into(), msg)
+            })
+            .collect();
+        // Split what's left after the transfers, which cost more with the bookkeeping on top.
+        let overhead = env::used_gas().0 + GAS_FOR_FT_TRANSFER_CALL.0 * count;
+        let receiver_gas = Gas(env::prepaid_gas().0.saturating_sub(overhead) / count);
+        let mut chain: Option<Promise> = None;
+        for (receiver_id, amount, msg) in transfers {
+            let on_transfer = ext_ft_receiver::ext(receiver_id.clone())
+                .with_static_gas(receiver_gas)
+                .ft_on_transfer(sender_id.clone(), amount, msg);
+            let resolve = ext_ft_resolver::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_RESOLVE_TRANSFER)
+                .ft_resolve_transfer(sender_id.clone(), receiver_id, amount);
+            chain = Some(match chain {
+                Some(chain) => chain.then(on_transfer).then(resolve),
+                None => on_transfer.then(resolve),
+            });
+        }
+        chain.unwrap()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        for account_id in [accounts(2), accounts(3)] {
+            testing_env!(context
+                .attached_deposit(contract.storage_balance_bounds().min.into())
+                .predecessor_account_id(account_id)
+                .build());
+            contract.storage_deposit(None, None);
+        }
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_transfer_call_multi() {
+        let (_, mut contract) = setup();
+        contract.ft_transfer_call_multi(vec![
+            (accounts(2), 100.into(), "".to_string()),
+            (accounts(3), 200.into(), "".to_string()),
+        ]);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 100);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 200);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, TOTAL_SUPPLY - 300);
+    }
+
+    #[test]
+    #[should_panic(expected = "The total amount exceeds the balance")]
+    fn test_transfer_call_multi_over_balance() {
+        let (_, mut contract) = setup();
+        contract.ft_transfer_call_multi(vec![
+            (accounts(2), TOTAL_SUPPLY.into(), "".to_string()),
+            (accounts(3), 1.into(), "".to_string()),
+        ]);
+    }
+}
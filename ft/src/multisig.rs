@@ -0,0 +1,273 @@
+/*!
+Built-in M-of-N multisig owner. Once the owner enables it, the contract account itself becomes the
+owner, so privileged methods can only be called by the contract, i.e. through a proposal. Any
+signer can propose a call to one of the contract's own methods, and once `threshold` distinct
+signers approved it, the last approval makes the contract call the method on itself. Proposals
+that don't gather enough approvals within the proposal lifetime expire.
+
+Changing the signers or the threshold is a privileged call like any other, so it also needs the
+threshold. It voids all open proposals, since their approvals came from the old signer set, and
+anyone can remove them. Removing or executing a proposal refunds its storage to the proposer.
+
+Tokens the owner would receive, like clawbacks, go to the treasury while the contract account is
+the owner, so they stay accounted for. See `emergency_clawback`.
+*/
+use crate::*;
+use near_sdk::json_types::Base64VecU8;
+use near_sdk::serde::{Deserialize, Serialize};
+
+/// Most signers the multisig can have.
+pub const MAX_SIGNERS: usize = 10;
+/// Gas for a proposal's call when the proposal doesn't set it.
+const DEFAULT_PROPOSAL_GAS: Gas = Gas(50_000_000_000_000);
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Multisig {
+    pub signers: Vec<AccountId>,
+    /// Approvals a proposal needs to execute.
+    pub threshold: u32,
+    /// How long (in seconds) a new proposal stays approvable.
+    pub proposal_lifetime: u64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Proposal {
+    pub proposer_id: AccountId,
+    /// Method of this contract to call, with its JSON arguments.
+    pub method_name: String,
+    pub args: Base64VecU8,
+    /// yoctoNEAR attached to the call, paid by the contract account.
+    pub deposit: U128,
+    pub gas: Option<Gas>,
+    pub approvals: Vec<AccountId>,
+    /// Timestamp (in seconds) after which the proposal can no longer be approved.
+    pub expires_at: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the signers and the threshold, handing the ownership to the contract account the
+    /// first time. A privileged action, so afterwards it needs to go through a proposal too.
+    pub fn set_multisig(&mut self, multisig: Multisig) {
+        self.assert_owner_action();
+        let signer_count = multisig.signers.len();
+        assert!(
+            signer_count > 0 && signer_count <= MAX_SIGNERS,
+            "There should be between 1 and {} signers",
+            MAX_SIGNERS
+        );
+        let mut signers = multisig.signers.clone();
+        signers.sort();
+        signers.dedup();
+        assert!(signers.len() == signer_count, "The signers should be distinct");
+        assert!(
+            multisig.threshold > 0 && multisig.threshold as usize <= signer_count,
+            "The threshold should be between 1 and the number of signers"
+        );
+        assert!(multisig.proposal_lifetime > 0, "The proposal lifetime should be positive");
+        self.owner_id = env::current_account_id();
+        self.multisig = Some(multisig);
+        self.first_valid_proposal_id = self.next_proposal_id;
+    }
+
+    pub fn get_multisig(&self) -> Option<Multisig> {
+        self.multisig.clone()
+    }
+
+    /// Proposes calling `method_name` on this contract, approved by the proposer. Only callable by
+    /// signers. Attached deposit must cover the storage of the proposal, the rest is refunded.
+    /// Returns the proposal id.
+    #[payable]
+    pub fn create_proposal(
+        &mut self,
+        method_name: String,
+        args: Base64VecU8,
+        deposit: Option<U128>,
+        gas: Option<Gas>,
+    ) -> u64 {
+        let multisig = self.multisig.as_ref().expect("The multisig is not enabled");
+        let proposer_id = env::predecessor_account_id();
+        assert!(multisig.signers.contains(&proposer_id), "Only a signer can call this method");
+        let initial_storage_usage = env::storage_usage();
+        let proposal_id = self.next_proposal_id;
+        self.next_proposal_id += 1;
+        let proposal = Proposal {
+            proposer_id: proposer_id.clone(),
+            method_name,
+            args,
+            deposit: deposit.unwrap_or(U128(0)),
+            gas,
+            approvals: vec![],
+            expires_at: current_timestamp_sec() + multisig.proposal_lifetime,
+        };
+        self.proposals.insert(&proposal_id, &proposal);
+        refund_deposit(initial_storage_usage);
+        self.internal_approve_proposal(proposal_id, proposer_id);
+        proposal_id
+    }
+
+    /// Approves a proposal as the calling signer. Returns whether this approval executed it.
+    pub fn approve_proposal(&mut self, proposal_id: u64) -> bool {
+        let multisig = self.multisig.as_ref().expect("The multisig is not enabled");
+        let signer_id = env::predecessor_account_id();
+        assert!(multisig.signers.contains(&signer_id), "Only a signer can call this method");
+        self.internal_approve_proposal(proposal_id, signer_id)
+    }
+
+    /// Drops a proposal and refunds its storage to the proposer. Callable by the proposer, or by
+    /// anyone once it expired or was voided by a change of the signers.
+    pub fn remove_proposal(&mut self, proposal_id: u64) {
+        let proposal = self.proposals.get(&proposal_id).expect("Proposal not found");
+        assert!(
+            env::predecessor_account_id() == proposal.proposer_id
+                || current_timestamp_sec() >= proposal.expires_at
+                || proposal_id < self.first_valid_proposal_id,
+            "Only the proposer can remove a proposal before it expires"
+        );
+        self.internal_remove_proposal(proposal_id, &proposal.proposer_id);
+    }
+
+    pub fn get_proposals(
+        &self,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<(u64, Proposal)> {
+        let from_index = from_index.unwrap_or(0) as usize;
+        let limit = limit.unwrap_or(u64::MAX) as usize;
+        self.proposals.iter().skip(from_index).take(limit).collect()
+    }
+}
+
+impl Contract {
+    fn internal_approve_proposal(&mut self, proposal_id: u64, signer_id: AccountId) -> bool {
+        let mut proposal = self.proposals.get(&proposal_id).expect("Proposal not found");
+        assert!(
+            proposal_id >= self.first_valid_proposal_id,
+            "The proposal was voided by a change of the signers"
+        );
+        assert!(current_timestamp_sec() < proposal.expires_at, "The proposal has expired");
+        assert!(!proposal.approvals.contains(&signer_id), "The proposal is already approved");
+        proposal.approvals.push(signer_id);
+        let threshold = self.multisig.as_ref().unwrap().threshold as usize;
+        if proposal.approvals.len() < threshold {
+            self.proposals.insert(&proposal_id, &proposal);
+            return false;
+        }
+        self.internal_remove_proposal(proposal_id, &proposal.proposer_id);
+        Promise::new(env::current_account_id()).function_call(
+            proposal.method_name,
+            proposal.args.0,
+            proposal.deposit.0,
+            proposal.gas.unwrap_or(DEFAULT_PROPOSAL_GAS),
+        );
+        true
+    }
+
+    fn internal_remove_proposal(&mut self, proposal_id: u64, proposer_id: &AccountId) {
+        let initial_storage_usage = env::storage_usage();
+        self.proposals.remove(&proposal_id);
+        let freed = initial_storage_usage.saturating_sub(env::storage_usage());
+        if freed > 0 {
+            Promise::new(proposer_id.clone()).transfer(freed as Balance * env::storage_byte_cost());
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_multisig(Multisig {
+            signers: vec![accounts(2), accounts(3), accounts(4)],
+            threshold: 2,
+            proposal_lifetime: 100,
+        });
+        testing_env!(context
+            .attached_deposit(ONE_NEAR / 100)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.create_proposal("pause".to_string(), b"{}".to_vec().into(), None, None);
+        testing_env!(context.attached_deposit(0).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_proposal_executes_at_threshold() {
+        let (mut context, mut contract) = setup();
+        assert_eq!(contract.get_owner(), accounts(0));
+        assert_eq!(contract.get_proposals(None, None)[0].1.approvals, vec![accounts(2)]);
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        assert!(contract.approve_proposal(0));
+        assert!(contract.get_proposals(None, None).is_empty());
+        // The executed call comes from the contract itself.
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.pause();
+        assert!(contract.is_paused());
+    }
+
+    #[test]
+    #[should_panic(expected = "The proposal is already approved")]
+    fn test_double_approval() {
+        let (_, mut contract) = setup();
+        contract.approve_proposal(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "The proposal has expired")]
+    fn test_expired_proposal() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(100 * NANOS)
+            .build());
+        contract.approve_proposal(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "The proposal was voided by a change of the signers")]
+    fn test_signer_change_voids_proposals() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.set_multisig(Multisig {
+            signers: vec![accounts(2), accounts(3)],
+            threshold: 2,
+            proposal_lifetime: 100,
+        });
+        testing_env!(context.predecessor_account_id(accounts(5)).build());
+        contract.remove_proposal(0);
+        assert_eq!(near_sdk::test_utils::get_created_receipts().len(), 1);
+        testing_env!(context
+            .attached_deposit(ONE_NEAR / 100)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.create_proposal("pause".to_string(), b"{}".to_vec().into(), None, None);
+        testing_env!(context.attached_deposit(0).build());
+        assert!(contract.get_proposals(None, None).iter().all(|(id, _)| *id == 1));
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.set_multisig(Multisig {
+            signers: vec![accounts(2), accounts(3)],
+            threshold: 2,
+            proposal_lifetime: 100,
+        });
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.approve_proposal(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the owner can call this method")]
+    fn test_former_owner_is_locked_out() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.set_guardian(None);
+    }
+}
@@ -0,0 +1,93 @@
+/*!
+Recovery of this token sent to the contract account by mistake, e.g. with a plain `ft_transfer`.
+The contract account also holds tokens on behalf of others: stakes, undistributed rewards, unsold
+auto-LP fees, unclaimed presale tokens, bridged tokens and the stabilization pool. Those are
+tracked separately and can't be recovered.
+*/
+use crate::events::emit_event;
+use crate::*;
+use near_sdk::serde_json::json;
+
+#[near_bindgen]
+impl Contract {
+    /// Moves `amount` of the stray tokens on the contract account to `to`.
+    pub fn recover_own_tokens(&mut self, to: AccountId, amount: U128) {
+        self.assert_owner_action();
+        assert!(amount.0 > 0, "The amount should be a positive number");
+        assert!(
+            amount.0 <= self.get_recoverable_own_tokens().0,
+            "The amount exceeds the recoverable balance"
+        );
+        self.internal_move_balance(
+            &env::current_account_id(),
+            &to,
+            amount.0,
+            Some("recovery".to_string()),
+        );
+        emit_event("own_tokens_recovered", json!({ "receiver_id": to, "amount": amount }));
+    }
+
+    /// Tokens on the contract account that aren't held on behalf of anyone.
+    pub fn get_recoverable_own_tokens(&self) -> U128 {
+        let balance = self.token.accounts.get(&env::current_account_id()).unwrap_or(0);
+        balance.saturating_sub(self.internal_held_for_others()).into()
+    }
+}
+
+impl Contract {
+    /// Tokens the contract account holds on behalf of others.
+    fn internal_held_for_others(&self) -> Balance {
+        let auto_lp_accumulated = self.auto_lp.as_ref().map_or(0, |auto_lp| auto_lp.accumulated.0);
+        self.total_staked
+            + self.rewards_pool
+            + auto_lp_accumulated
+            + self.sale_tokens_held
+            + self.bridge_locked
+            + self.stabilization_pool
+            + self.airdrop_pool
+            + self.insurance_fund
+            + self.dust_pool
+            + self.htlc_locked
+            + self.treasury_balance
+            + self.faucet_pool
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::staking::LockTier;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_lock_tiers(vec![LockTier { duration: 100, multiplier_bps: 10_000 }]);
+        testing_env!(context.attached_deposit(ONE_NEAR / 10).build());
+        contract.stake_locked(500.into(), 0);
+        testing_env!(context.attached_deposit(1).build());
+        contract.ft_transfer(env::current_account_id(), 200.into(), None);
+        testing_env!(context.attached_deposit(0).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_recover_stray_tokens() {
+        let (_, mut contract) = setup();
+        assert_eq!(contract.get_recoverable_own_tokens().0, 200);
+        contract.recover_own_tokens(accounts(1), 200.into());
+        assert_eq!(contract.get_recoverable_own_tokens().0, 0);
+        assert_eq!(contract.ft_balance_of(env::current_account_id()).0, 500);
+    }
+
+    #[test]
+    #[should_panic(expected = "The amount exceeds the recoverable balance")]
+    fn test_recover_staked_tokens() {
+        let (_, mut contract) = setup();
+        contract.recover_own_tokens(accounts(1), 201.into());
+    }
+}
@@ -0,0 +1,87 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the account that can pause the contract in an emergency, alongside the owner.
+    pub fn set_guardian(&mut self, guardian_id: Option<AccountId>) {
+        self.assert_owner_action();
+        self.guardian_id = guardian_id;
+    }
+
+    pub fn get_guardian(&self) -> Option<AccountId> {
+        self.guardian_id.clone()
+    }
+
+    /// Stops all transfers, mints and burns until the owner unpauses.
+    pub fn pause(&mut self) {
+        self.assert_owner_or_guardian();
+        self.paused = true;
+    }
+
+    /// Stops all transfers, mints and burns until `unpause_ts` (in seconds), e.g. for a planned
+    /// migration window. The owner can still unpause earlier.
+    pub fn pause_until(&mut self, unpause_ts: u64) {
+        self.assert_owner_or_guardian();
+        assert!(!self.paused, "The contract is already paused until unpaused");
+        assert!(unpause_ts > current_timestamp_sec(), "The unpause time should be in the future");
+        self.paused_until = Some(unpause_ts);
+    }
+
+    pub fn unpause(&mut self) {
+        self.assert_owner_action();
+        self.paused = false;
+        if let Some(breaker) = self.circuit_breaker.as_mut() {
+            breaker.tripped = false;
+        }
+        self.paused_until = None;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused || self.get_paused_until().is_some()
+    }
+
+    /// Timestamp (in seconds) a scheduled pause ends at, if any.
+    pub fn get_paused_until(&self) -> Option<u64> {
+        self.paused_until.filter(|ts| current_timestamp_sec() < *ts)
+    }
+}
+
+impl Contract {
+    pub(crate) fn assert_owner_or_guardian(&self) {
+        let predecessor_id = env::predecessor_account_id();
+        assert!(
+            predecessor_id == self.owner_id || Some(&predecessor_id) == self.guardian_id.as_ref(),
+            "Only the owner or the guardian can call this method"
+        );
+    }
+
+    pub(crate) fn assert_not_paused(&self) {
+        assert!(!self.is_paused(), "The contract is paused");
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_scheduled_pause_ends_by_itself() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.pause_until(100);
+        assert!(contract.is_paused());
+        assert_eq!(contract.get_paused_until(), Some(100));
+        testing_env!(context.block_timestamp(100 * NANOS).build());
+        assert!(!contract.is_paused());
+        assert_eq!(contract.get_paused_until(), None);
+
+        contract.pause_until(200);
+        contract.unpause();
+        assert!(!contract.is_paused());
+    }
+}
@@ -0,0 +1,86 @@
+/*!
+Proof-of-personhood gating of the airdrop and the faucet, against one person claiming with many
+accounts. While a registry is configured, an account can only claim an airdrop or drip from the
+faucet once the registry's `is_human(account_id)` confirmed it is a unique human.
+
+Positive checks are cached per account for `ttl_seconds`, so later claims complete right away.
+Otherwise the claim asks the registry and completes in the callback. Negative answers aren't
+cached, so an account can claim as soon as it registers with the registry.
+*/
+use crate::*;
+use near_sdk::ext_contract;
+use near_sdk::serde::{Deserialize, Serialize};
+
+const GAS_FOR_IS_HUMAN: Gas = Gas(5_000_000_000_000);
+
+/// Interface the personhood registry is expected to implement.
+#[ext_contract(ext_personhood_registry)]
+#[allow(dead_code)]
+pub trait PersonhoodRegistry {
+    fn is_human(&self, account_id: AccountId) -> bool;
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PersonhoodGate {
+    pub registry_id: AccountId,
+    /// How long (in seconds) a positive check stays valid.
+    pub ttl_seconds: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Enables gating, or disables it when `gate` is `None`. Changing the gate drops the cache.
+    pub fn set_personhood_gate(&mut self, gate: Option<PersonhoodGate>) {
+        self.assert_owner_action();
+        self.personhood_epoch += 1;
+        self.personhood_gate = gate;
+    }
+
+    pub fn get_personhood_gate(&self) -> Option<PersonhoodGate> {
+        self.personhood_gate.clone()
+    }
+
+    /// Whether `account_id` has a cached confirmation that hasn't expired yet.
+    pub fn is_verified_human(&self, account_id: AccountId) -> bool {
+        matches!(
+            self.verified_humans.get(&account_id),
+            Some((epoch, until)) if epoch == self.personhood_epoch && until > current_timestamp_sec()
+        )
+    }
+}
+
+impl Contract {
+    /// The registry call confirming `account_id`, or `None` if there's no gate or it's already
+    /// confirmed.
+    pub(crate) fn internal_personhood_check(&self, account_id: &AccountId) -> Option<Promise> {
+        let gate = self.personhood_gate.as_ref()?;
+        if self.is_verified_human(account_id.clone()) {
+            return None;
+        }
+        Some(
+            ext_personhood_registry::ext(gate.registry_id.clone())
+                .with_static_gas(GAS_FOR_IS_HUMAN)
+                .is_human(account_id.clone()),
+        )
+    }
+
+    /// Caches a positive answer of the registry. Returns whether `account_id` is confirmed.
+    pub(crate) fn internal_record_personhood(
+        &mut self,
+        account_id: &AccountId,
+        is_human: bool,
+    ) -> bool {
+        let ttl_seconds = match self.personhood_gate.as_ref() {
+            Some(gate) => gate.ttl_seconds,
+            None => return true,
+        };
+        if !is_human {
+            log!("@{} isn't registered as a unique human", account_id);
+            return false;
+        }
+        let until = current_timestamp_sec() + ttl_seconds;
+        self.verified_humans.insert(account_id, &(self.personhood_epoch, until));
+        true
+    }
+}
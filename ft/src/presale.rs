@@ -0,0 +1,266 @@
+/*!
+Presale with a soft cap and a hard cap. Accounts contribute NEAR during the sale window; once the
+sale is finalized, either the soft cap was met and contributors claim their pro-rata share of the
+tokens for sale, or it wasn't and contributors get their NEAR back.
+
+Claims and refunds are pulled by each contributor rather than pushed at finalization, so
+finalizing costs the same gas no matter how many accounts contributed. If the sale can't be
+finalized, e.g. because minting is paused or capped, anyone can abandon it `SALE_GRACE_SEC` after
+its end and contributors get their NEAR back as if it had failed.
+*/
+use crate::math::mul_div;
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+
+/// How long after its end a sale can still be finalized before it can be abandoned, one week.
+pub const SALE_GRACE_SEC: u64 = 7 * 24 * 60 * 60;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum SaleStatus {
+    Active,
+    Succeeded,
+    Failed,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Sale {
+    /// Timestamp (in seconds) at which contributions open.
+    pub start: u64,
+    /// Timestamp (in seconds) at which contributions close.
+    pub end: u64,
+    /// Minimum amount of NEAR to raise for the sale to succeed.
+    pub soft_cap: U128,
+    /// Maximum amount of NEAR the sale accepts.
+    pub hard_cap: U128,
+    /// Tokens split between contributors pro-rata if the sale succeeds.
+    pub tokens_for_sale: U128,
+    pub total_raised: U128,
+    pub status: SaleStatus,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Sets up the sale. Only one sale can be configured per contract.
+    pub fn configure_sale(
+        &mut self,
+        start: u64,
+        end: u64,
+        soft_cap: U128,
+        hard_cap: U128,
+        tokens_for_sale: U128,
+    ) {
+        self.assert_owner_action();
+        assert!(self.sale.is_none(), "The sale is already configured");
+        assert!(start < end, "The sale must end after it starts");
+        assert!(soft_cap.0 <= hard_cap.0, "The soft cap can't exceed the hard cap");
+        assert!(hard_cap.0 > 0, "The hard cap should be a positive number");
+        assert!(tokens_for_sale.0 > 0, "The tokens for sale should be a positive number");
+        self.sale = Some(Sale {
+            start,
+            end,
+            soft_cap,
+            hard_cap,
+            tokens_for_sale,
+            total_raised: 0.into(),
+            status: SaleStatus::Active,
+        });
+    }
+
+    pub fn get_sale(&self) -> Option<Sale> {
+        self.sale.clone()
+    }
+
+    pub fn sale_contribution_of(&self, account_id: AccountId) -> U128 {
+        self.sale_contributions.get(&account_id).unwrap_or(0).into()
+    }
+
+    /// Contributes the attached NEAR to the sale. The caller must be registered with the token
+    /// to be able to claim later. Contributions that would exceed the hard cap are rejected.
+    #[payable]
+    pub fn contribute(&mut self) {
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        let amount = env::attached_deposit();
+        assert!(amount > 0, "Attach NEAR to contribute");
+        self.token.internal_unwrap_balance_of(&account_id);
+        let sale = self.sale.as_mut().expect("No sale is configured");
+        let now = current_timestamp_sec();
+        assert!(
+            sale.status == SaleStatus::Active && now >= sale.start && now < sale.end,
+            "The sale is not open"
+        );
+        let total_raised = sale.total_raised.0 + amount;
+        assert!(total_raised <= sale.hard_cap.0, "The contribution exceeds the hard cap");
+        sale.total_raised = total_raised.into();
+        let contribution = self.sale_contributions.get(&account_id).unwrap_or(0);
+        self.sale_contributions.insert(&account_id, &(contribution + amount));
+    }
+
+    /// Closes the sale once it has ended or reached the hard cap. If the soft cap was met, the
+    /// tokens for sale are minted to the contract for contributors to claim and the raised NEAR
+    /// goes to the owner. Otherwise contributors can claim refunds.
+    pub fn finalize_sale(&mut self) -> SaleStatus {
+        let current_account_id = env::current_account_id();
+        let sale = self.sale.as_mut().expect("No sale is configured");
+        assert!(sale.status == SaleStatus::Active, "The sale is already finalized");
+        assert!(
+            current_timestamp_sec() >= sale.end || sale.total_raised == sale.hard_cap,
+            "The sale is still running"
+        );
+        if sale.total_raised.0 >= sale.soft_cap.0 && sale.total_raised.0 > 0 {
+            sale.status = SaleStatus::Succeeded;
+            let (tokens_for_sale, total_raised) = (sale.tokens_for_sale.0, sale.total_raised.0);
+            self.internal_register_contract_account();
+            self.internal_mint(&current_account_id, tokens_for_sale, "presale");
+            self.sale_tokens_held = tokens_for_sale;
+            Promise::new(self.owner_id.clone()).transfer(total_raised);
+            SaleStatus::Succeeded
+        } else {
+            sale.status = SaleStatus::Failed;
+            SaleStatus::Failed
+        }
+    }
+
+    /// Fails a sale that wasn't finalized within `SALE_GRACE_SEC` of its end, so contributors can
+    /// claim refunds.
+    pub fn abandon_sale(&mut self) {
+        let sale = self.sale.as_mut().expect("No sale is configured");
+        assert!(sale.status == SaleStatus::Active, "The sale is already finalized");
+        assert!(
+            current_timestamp_sec() >= sale.end + SALE_GRACE_SEC,
+            "The sale can still be finalized"
+        );
+        sale.status = SaleStatus::Failed;
+    }
+
+    /// Transfers the caller's pro-rata share of the tokens for sale after a successful sale.
+    pub fn claim_sale_tokens(&mut self) -> U128 {
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        let sale = self.sale.as_ref().expect("No sale is configured");
+        assert!(sale.status == SaleStatus::Succeeded, "The sale hasn't succeeded");
+        let contribution = self.sale_contributions.remove(&account_id).expect("Nothing to claim");
+        let amount = mul_div(sale.tokens_for_sale.0, contribution, sale.total_raised.0);
+        if amount > 0 {
+            self.sale_tokens_held -= amount;
+            self.internal_move_balance(
+                &env::current_account_id(),
+                &account_id,
+                amount,
+                Some("presale".to_string()),
+            );
+            self.internal_lock_received(&account_id, amount);
+        }
+        amount.into()
+    }
+
+    /// Returns the caller's contribution after a failed sale.
+    pub fn refund_contribution(&mut self) -> Promise {
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        let sale = self.sale.as_ref().expect("No sale is configured");
+        assert!(sale.status == SaleStatus::Failed, "The sale hasn't failed");
+        let contribution = self.sale_contributions.remove(&account_id).expect("Nothing to refund");
+        Promise::new(account_id).transfer(contribution)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    const TOKENS_FOR_SALE: Balance = 1_000;
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.configure_sale(
+            0,
+            100,
+            (2 * ONE_NEAR).into(),
+            (4 * ONE_NEAR).into(),
+            TOKENS_FOR_SALE.into(),
+        );
+        for account_id in [accounts(2), accounts(3)] {
+            testing_env!(context
+                .attached_deposit(contract.storage_balance_bounds().min.into())
+                .predecessor_account_id(account_id)
+                .build());
+            contract.storage_deposit(None, None);
+        }
+        (context, contract)
+    }
+
+    fn contribute(
+        context: &mut near_sdk::test_utils::VMContextBuilder,
+        contract: &mut Contract,
+        account_id: AccountId,
+        amount: Balance,
+    ) {
+        testing_env!(context.attached_deposit(amount).predecessor_account_id(account_id).build());
+        contract.contribute();
+    }
+
+    #[test]
+    fn test_successful_sale() {
+        let (mut context, mut contract) = setup();
+        contribute(&mut context, &mut contract, accounts(2), ONE_NEAR);
+        contribute(&mut context, &mut contract, accounts(3), 3 * ONE_NEAR);
+        testing_env!(context.attached_deposit(0).build());
+        assert_eq!(contract.finalize_sale(), SaleStatus::Succeeded);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        assert_eq!(contract.claim_sale_tokens().0, TOKENS_FOR_SALE / 4);
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        assert_eq!(contract.claim_sale_tokens().0, TOKENS_FOR_SALE * 3 / 4);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY + TOKENS_FOR_SALE);
+    }
+
+    #[test]
+    fn test_failed_sale() {
+        let (mut context, mut contract) = setup();
+        contribute(&mut context, &mut contract, accounts(2), ONE_NEAR);
+        testing_env!(context.attached_deposit(0).block_timestamp(100 * NANOS).build());
+        assert_eq!(contract.finalize_sale(), SaleStatus::Failed);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.refund_contribution();
+        assert_eq!(contract.sale_contribution_of(accounts(2)).0, 0);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+    }
+
+    #[test]
+    fn test_abandoned_sale() {
+        let (mut context, mut contract) = setup();
+        contribute(&mut context, &mut contract, accounts(2), 3 * ONE_NEAR);
+        testing_env!(context
+            .attached_deposit(0)
+            .block_timestamp((100 + SALE_GRACE_SEC) * NANOS)
+            .build());
+        contract.abandon_sale();
+        contract.refund_contribution();
+        assert_eq!(contract.sale_contribution_of(accounts(2)).0, 0);
+        assert_eq!(near_sdk::test_utils::get_created_receipts().len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "The sale can still be finalized")]
+    fn test_abandon_within_grace() {
+        let (mut context, mut contract) = setup();
+        contribute(&mut context, &mut contract, accounts(2), 3 * ONE_NEAR);
+        testing_env!(context.attached_deposit(0).block_timestamp(100 * NANOS).build());
+        contract.abandon_sale();
+    }
+
+    #[test]
+    #[should_panic(expected = "The contribution exceeds the hard cap")]
+    fn test_contribution_above_hard_cap() {
+        let (mut context, mut contract) = setup();
+        contribute(&mut context, &mut contract, accounts(2), 5 * ONE_NEAR);
+    }
+}
@@ -0,0 +1,173 @@
+/*!
+Optional NEAR fee on transfers, forwarded to an operations account, e.g. to fund the network
+operations behind the token. The fee is tiered by the transfer amount and comes on top of any
+token fee and of storage.
+
+The fee replaces the 1 yoctoNEAR guard rather than adding to it. When a fee applies, the sender
+attaches at least the fee, which proves a full access key just like the yoctoNEAR does, and the
+rest is refunded. When no fee applies, exactly 1 yoctoNEAR is still required. Allowance spends,
+which attach a deposit for storage anyway, pay the fee out of that deposit. The storage of
+settlement holds is paid the same way.
+*/
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+
+/// Most tiers a schedule can have.
+pub const MAX_PRIORITY_FEE_TIERS: usize = 10;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PriorityFeeSchedule {
+    /// Account the fees are forwarded to.
+    pub operations_id: AccountId,
+    /// `(min_amount, fee)` pairs in increasing order of `min_amount`. A transfer pays the fee in
+    /// yoctoNEAR of the last tier it reaches, and nothing below the first.
+    pub tiers: Vec<(U128, U128)>,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the fee schedule, or disables the fee when `None`.
+    pub fn set_priority_fee_schedule(&mut self, schedule: Option<PriorityFeeSchedule>) {
+        self.assert_owner_action();
+        if let Some(schedule) = schedule.as_ref() {
+            assert!(
+                schedule.tiers.len() <= MAX_PRIORITY_FEE_TIERS,
+                "At most {} tiers",
+                MAX_PRIORITY_FEE_TIERS
+            );
+            assert!(
+                schedule.tiers.windows(2).all(|tiers| tiers[0].0 .0 < tiers[1].0 .0),
+                "The tiers should be in increasing order of the amount"
+            );
+        }
+        self.priority_fee_schedule = schedule;
+    }
+
+    pub fn get_priority_fee_schedule(&self) -> Option<PriorityFeeSchedule> {
+        self.priority_fee_schedule.clone()
+    }
+
+    /// NEAR fee in yoctoNEAR for a transfer of `amount`.
+    pub fn priority_fee_for(&self, amount: U128) -> U128 {
+        self.internal_priority_fee(&[amount.0]).into()
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_priority_fee(&self, amounts: &[Balance]) -> Balance {
+        let schedule = match self.priority_fee_schedule.as_ref() {
+            Some(schedule) => schedule,
+            None => return 0,
+        };
+        amounts
+            .iter()
+            .map(|amount| {
+                schedule
+                    .tiers
+                    .iter()
+                    .rev()
+                    .find(|(min_amount, _)| *amount >= min_amount.0)
+                    .map_or(0, |(_, fee)| fee.0)
+            })
+            .sum()
+    }
+
+    /// Stands in for `assert_one_yocto` on transfers of `amounts`: charges the priority fee and the
+    /// storage of the settlement holds out of the attached deposit and refunds the rest, or
+    /// requires exactly 1 yoctoNEAR if there's neither.
+    pub(crate) fn assert_priority_fee_attached(&self, amounts: &[Balance]) {
+        self.assert_priority_fee_attached_with(amounts, 0);
+    }
+
+    /// Like `assert_priority_fee_attached`, with `storage_deposit` more kept for storage the
+    /// call adds.
+    pub(crate) fn assert_priority_fee_attached_with(
+        &self,
+        amounts: &[Balance],
+        storage_deposit: Balance,
+    ) {
+        let fee = self.internal_priority_fee(amounts);
+        let storage_deposit = self.internal_hold_deposit(amounts) + storage_deposit;
+        if fee == 0 && storage_deposit == 0 {
+            assert_one_yocto();
+            return;
+        }
+        assert!(
+            env::attached_deposit() >= fee + storage_deposit,
+            "Requires attached deposit of at least {} yoctoNEAR for the priority fee and the \
+             storage",
+            fee + storage_deposit
+        );
+        self.internal_pay_priority_fee(fee);
+        let refund = env::attached_deposit() - fee - storage_deposit;
+        if refund > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
+
+    /// Forwards `fee` out of the attached deposit to the operations account.
+    pub(crate) fn internal_pay_priority_fee(&self, fee: Balance) {
+        if fee == 0 {
+            return;
+        }
+        assert!(
+            env::attached_deposit() >= fee,
+            "Requires attached deposit of at least {} yoctoNEAR for the priority fee",
+            fee
+        );
+        let schedule = self.priority_fee_schedule.as_ref().unwrap();
+        Promise::new(schedule.operations_id.clone()).transfer(fee);
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_priority_fee_schedule(Some(PriorityFeeSchedule {
+            operations_id: accounts(4),
+            tiers: vec![(1_000.into(), 100.into()), (10_000.into(), 500.into())],
+        }));
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_fee_by_tier() {
+        let (mut context, mut contract) = setup();
+        assert_eq!(contract.priority_fee_for(999.into()).0, 0);
+        assert_eq!(contract.priority_fee_for(9_999.into()).0, 100);
+        assert_eq!(contract.priority_fee_for(10_000.into()).0, 500);
+        // Below the first tier, the usual yoctoNEAR is enough.
+        testing_env!(context.attached_deposit(1).build());
+        contract.ft_transfer(accounts(2), 999.into(), None);
+        // Overpaying is refunded.
+        testing_env!(context.attached_deposit(1_000).build());
+        contract.ft_transfer(accounts(2), 10_000.into(), None);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 10_999);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Requires attached deposit of at least 100 yoctoNEAR for the priority fee"
+    )]
+    fn test_one_yocto_is_not_enough_with_fee() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.attached_deposit(1).build());
+        contract.ft_transfer(accounts(2), 1_000.into(), None);
+    }
+}
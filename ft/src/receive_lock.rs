@@ -0,0 +1,133 @@
+/*!
+Minimum hold for received tokens, to deter quick flips of airdrops and presale allocations. While
+enabled, tokens an account receives through a transfer, a presale claim or an airdrop claim can't
+be sent on right away. The locked amount decays linearly to zero over `receive_lock_seconds`, and
+a new receipt adds to what's still locked and restarts the period.
+
+The owner, the contract account and the accounts on the owner's exemption list, e.g. exchanges
+and pools, never get their received tokens locked. Unlike the time locks, this is never set up
+per account.
+*/
+use crate::math::mul_div;
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// Sets how long received tokens stay (partly) locked, in seconds. Zero disables locking new
+    /// receipts, the existing locks keep decaying.
+    pub fn set_receive_lock_seconds(&mut self, receive_lock_seconds: u64) {
+        self.assert_owner_action();
+        self.receive_lock_seconds = receive_lock_seconds;
+    }
+
+    pub fn get_receive_lock_seconds(&self) -> u64 {
+        self.receive_lock_seconds
+    }
+
+    pub fn add_receive_lock_exempt(&mut self, account_id: AccountId) {
+        self.assert_owner_action();
+        self.receive_lock_exempt.insert(&account_id);
+    }
+
+    pub fn remove_receive_lock_exempt(&mut self, account_id: AccountId) {
+        self.assert_owner_action();
+        self.receive_lock_exempt.remove(&account_id);
+    }
+
+    pub fn is_receive_lock_exempt(&self, account_id: AccountId) -> bool {
+        account_id == self.owner_id
+            || account_id == env::current_account_id()
+            || self.receive_lock_exempt.contains(&account_id)
+    }
+
+    /// Amount of `account_id`'s received tokens that can't be sent yet.
+    pub fn receive_locked_balance_of(&self, account_id: AccountId) -> U128 {
+        self.internal_receive_locked_balance(&account_id).into()
+    }
+}
+
+impl Contract {
+    /// Locks `amount` just received by `account_id`, if enabled.
+    pub(crate) fn internal_lock_received(&mut self, account_id: &AccountId, amount: Balance) {
+        if self.receive_lock_seconds == 0
+            || amount == 0
+            || self.is_receive_lock_exempt(account_id.clone())
+        {
+            return;
+        }
+        let now = current_timestamp_sec();
+        let locked = self.internal_receive_locked_balance(account_id) + amount;
+        self.received_locks.insert(account_id, &(locked, now, now + self.receive_lock_seconds));
+    }
+
+    /// Amount of `account_id`'s received tokens still locked.
+    pub(crate) fn internal_receive_locked_balance(&self, account_id: &AccountId) -> Balance {
+        let (amount, locked_at, locked_until) = match self.received_locks.get(account_id) {
+            Some(lock) => lock,
+            None => return 0,
+        };
+        let now = current_timestamp_sec();
+        if now >= locked_until {
+            return 0;
+        }
+        mul_div(amount, (locked_until - now) as u128, (locked_until - locked_at) as u128)
+    }
+
+    /// Adds the storage of a lock entry for the longest possible account ID to the storage
+    /// required to register an account.
+    pub(crate) fn measure_receive_lock_storage_usage(&mut self) {
+        let initial_storage_usage = env::storage_usage();
+        let tmp_account_id = AccountId::new_unchecked("a".repeat(64));
+        self.received_locks.insert(&tmp_account_id, &(0, 0, 0));
+        self.token.account_storage_usage += env::storage_usage() - initial_storage_usage;
+        self.received_locks.remove(&tmp_account_id);
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_receive_lock_seconds(100);
+        for account_id in [accounts(2), accounts(3)] {
+            testing_env!(context
+                .attached_deposit(contract.storage_balance_bounds().min.into())
+                .predecessor_account_id(account_id)
+                .build());
+            contract.storage_deposit(None, None);
+        }
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), 1_000.into(), None);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_received_lock_decays() {
+        let (mut context, mut contract) = setup();
+        assert_eq!(contract.receive_locked_balance_of(accounts(2)).0, 1_000);
+        testing_env!(context.block_timestamp(25 * NANOS).build());
+        assert_eq!(contract.receive_locked_balance_of(accounts(2)).0, 750);
+        contract.ft_transfer(accounts(3), 150.into(), None);
+        assert_eq!(contract.receive_locked_balance_of(accounts(3)).0, 150);
+        // The owner is exempt.
+        contract.ft_transfer(accounts(1), 100.into(), None);
+        assert_eq!(contract.receive_locked_balance_of(accounts(1)).0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "The amount exceeds the unlocked balance of 250")]
+    fn test_locked_received_tokens() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.block_timestamp(25 * NANOS).build());
+        contract.ft_transfer(accounts(3), 251.into(), None);
+    }
+}
@@ -0,0 +1,149 @@
+/*!
+Referral rewards. An account can name the account that referred it, and every transfer it sends
+then mints a configured share of the amount to the referrer, until the program's total cap is
+reached. Rewards only go one level up, and self-referral and referral loops are rejected.
+*/
+use crate::math::{bps_of, BPS_DENOMINATOR};
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+
+/// How far up the referral chain `set_referrer` looks for a loop.
+const MAX_REFERRAL_DEPTH: usize = 16;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReferralProgram {
+    /// Share of each transfer minted to the sender's referrer, in basis points.
+    pub bps: u16,
+    /// Maximum amount minted as referral rewards in total.
+    pub cap: U128,
+    /// Amount minted as referral rewards so far.
+    pub total_rewarded: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the reward share and the total cap. Rewards already paid count towards the new cap.
+    pub fn set_referral_program(&mut self, bps: u16, cap: U128) {
+        self.assert_owner_action();
+        assert!(bps <= BPS_DENOMINATOR, "The share can't exceed {} bps", BPS_DENOMINATOR);
+        self.referral_program.bps = bps;
+        self.referral_program.cap = cap;
+    }
+
+    pub fn get_referral_program(&self) -> ReferralProgram {
+        self.referral_program.clone()
+    }
+
+    /// Records `referrer_id` as the caller's referrer. It can only be set once.
+    #[payable]
+    pub fn set_referrer(&mut self, referrer_id: AccountId) {
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        assert_ne!(account_id, referrer_id, "Can't refer yourself");
+        assert!(self.referrers.get(&account_id).is_none(), "The referrer is already set");
+        self.token.internal_unwrap_balance_of(&referrer_id);
+        let mut ancestor_id = referrer_id.clone();
+        for _ in 0..MAX_REFERRAL_DEPTH {
+            match self.referrers.get(&ancestor_id) {
+                Some(next_id) => {
+                    assert_ne!(next_id, account_id, "Referral loops are not allowed");
+                    ancestor_id = next_id;
+                }
+                None => break,
+            }
+        }
+        let initial_storage_usage = env::storage_usage();
+        self.referrers.insert(&account_id, &referrer_id);
+        if self.referral_earnings.get(&referrer_id).is_none() {
+            self.referral_earnings.insert(&referrer_id, &0);
+        }
+        refund_deposit(initial_storage_usage);
+    }
+
+    pub fn referrer_of(&self, account_id: AccountId) -> Option<AccountId> {
+        self.referrers.get(&account_id)
+    }
+
+    pub fn referral_earnings_of(&self, account_id: AccountId) -> U128 {
+        self.referral_earnings.get(&account_id).unwrap_or(0).into()
+    }
+}
+
+impl Contract {
+    /// Mints the referral reward for a transfer of `amount` sent by `sender_id`. Skipped once the
+    /// cap is reached, when the supply is frozen or if the referrer is no longer registered, and
+    /// reduced to what the mint limits still allow.
+    pub(crate) fn internal_reward_referrer(&mut self, sender_id: &AccountId, amount: Balance) {
+        if self.referral_program.bps == 0 || self.supply_frozen {
+            return;
+        }
+        let referrer_id = match self.referrers.get(sender_id) {
+            Some(referrer_id) => referrer_id,
+            None => return,
+        };
+        if self.token.accounts.get(&referrer_id).is_none() {
+            return;
+        }
+        let remaining =
+            self.referral_program.cap.0.saturating_sub(self.referral_program.total_rewarded.0);
+        let reward = bps_of(amount, self.referral_program.bps)
+            .min(remaining)
+            .min(self.internal_mint_headroom());
+        if reward == 0 {
+            return;
+        }
+        self.referral_program.total_rewarded =
+            (self.referral_program.total_rewarded.0 + reward).into();
+        let earnings = self.referral_earnings.get(&referrer_id).unwrap_or(0);
+        self.referral_earnings.insert(&referrer_id, &(earnings + reward));
+        self.internal_mint(&referrer_id, reward, "referral");
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_referral_program(100, 15.into());
+        testing_env!(context
+            .attached_deposit(ONE_NEAR / 10)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.set_referrer(accounts(2));
+        testing_env!(context.attached_deposit(1).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_referral_rewards_up_to_cap() {
+        let (_, mut contract) = setup();
+        contract.ft_transfer(accounts(2), 1_000.into(), None);
+        assert_eq!(contract.referral_earnings_of(accounts(2)).0, 10);
+        contract.ft_transfer(accounts(2), 1_000.into(), None);
+        assert_eq!(contract.referral_earnings_of(accounts(2)).0, 15);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 2_015);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY + 15);
+    }
+
+    #[test]
+    #[should_panic(expected = "Referral loops are not allowed")]
+    fn test_referral_loop() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .attached_deposit(ONE_NEAR / 10)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.set_referrer(accounts(1));
+    }
+}
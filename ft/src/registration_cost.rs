@@ -0,0 +1,115 @@
+/*!
+Registration priced by the length of the account ID. Every registration stores the account ID in
+several places, so shorter IDs use less storage than the 64 characters the standard's flat
+minimum is measured for. `storage_deposit` charges the exact cost for the given ID and refunds the
+rest of the attached deposit, and `storage_unregister` refunds the same amount.
+
+The storage per character of the account ID is measured at initialization by registering a
+short and a long temporary ID. `storage_balance_bounds` still reports the cost of the longest ID,
+which is enough for any account.
+*/
+use crate::*;
+
+/// Shortest valid account ID.
+const MIN_ACCOUNT_ID_LEN: usize = 2;
+/// Longest valid account ID, the one the standard measures the account storage for.
+const MAX_ACCOUNT_ID_LEN: usize = 64;
+
+#[near_bindgen]
+impl Contract {
+    /// Storage cost in yoctoNEAR that `storage_deposit` charges for registering `account_id`.
+    pub fn estimate_registration_cost(&self, account_id: AccountId) -> U128 {
+        self.internal_registration_cost(&account_id).into()
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_registration_cost(&self, account_id: &AccountId) -> Balance {
+        let unused_id_bytes = (MAX_ACCOUNT_ID_LEN - account_id.as_str().len()) as StorageUsage;
+        let storage_usage =
+            self.token.account_storage_usage - unused_id_bytes * self.storage_per_account_id_byte;
+        storage_usage as Balance * env::storage_byte_cost()
+    }
+
+    /// Measures how much storage a registration uses per character of the account ID. Must run
+    /// after everything a registration stores is included in the account storage usage.
+    pub(crate) fn measure_account_id_storage_usage(&mut self) {
+        let short_usage = self.measure_registration_storage_usage(MIN_ACCOUNT_ID_LEN);
+        let long_usage = self.measure_registration_storage_usage(MAX_ACCOUNT_ID_LEN);
+        self.storage_per_account_id_byte =
+            (long_usage - short_usage) / (MAX_ACCOUNT_ID_LEN - MIN_ACCOUNT_ID_LEN) as StorageUsage;
+    }
+
+    fn measure_registration_storage_usage(&mut self, account_id_len: usize) -> StorageUsage {
+        let initial_storage_usage = env::storage_usage();
+        let tmp_account_id = AccountId::new_unchecked("a".repeat(account_id_len));
+        self.internal_register_account(&tmp_account_id);
+        self.internal_count_transfer(&tmp_account_id, &tmp_account_id);
+        self.received_locks.insert(&tmp_account_id, &(0, 0, 0));
+        self.last_demurrage_ts.insert(&tmp_account_id, &0);
+        self.tiers.insert(&tmp_account_id, &0);
+        self.last_activity.insert(&tmp_account_id, &0);
+        self.last_send_block.insert(&tmp_account_id, &0);
+        self.last_receive_block.insert(&tmp_account_id, &0);
+        self.block_transfer_counts.insert(&tmp_account_id, &(0, 0));
+        let storage_usage = env::storage_usage() - initial_storage_usage;
+        self.token.accounts.remove(&tmp_account_id);
+        self.holders.remove(&tmp_account_id);
+        self.reward_checkpoints.remove(&tmp_account_id);
+        self.transfers_sent.remove(&tmp_account_id);
+        self.transfers_received.remove(&tmp_account_id);
+        self.hold_since.remove(&tmp_account_id);
+        self.received_locks.remove(&tmp_account_id);
+        self.last_demurrage_ts.remove(&tmp_account_id);
+        self.tiers.remove(&tmp_account_id);
+        self.last_activity.remove(&tmp_account_id);
+        self.last_send_block.remove(&tmp_account_id);
+        self.last_receive_block.remove(&tmp_account_id);
+        self.block_transfer_counts.remove(&tmp_account_id);
+        storage_usage
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_registration_cost_by_id_length() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        let long_id = AccountId::new_unchecked("a".repeat(64));
+        assert_eq!(
+            contract.estimate_registration_cost(long_id),
+            contract.storage_balance_bounds().min
+        );
+        let cost = contract.estimate_registration_cost(accounts(2));
+        assert!(cost.0 < contract.storage_balance_bounds().min.0);
+
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        let initial_storage_usage = env::storage_usage();
+        contract.storage_deposit(None, None);
+        // The change log entry is stored at the contract's expense.
+        contract.change_log.remove(&contract.get_state_version());
+        contract.internal_count_transfer(&accounts(2), &accounts(2));
+        contract.received_locks.insert(&accounts(2), &(0, 0, 0));
+        contract.last_demurrage_ts.insert(&accounts(2), &0);
+        contract.tiers.insert(&accounts(2), &0);
+        contract.last_activity.insert(&accounts(2), &0);
+        contract.last_send_block.insert(&accounts(2), &0);
+        contract.last_receive_block.insert(&accounts(2), &0);
+        contract.block_transfer_counts.insert(&accounts(2), &(0, 0));
+        let storage_cost =
+            (env::storage_usage() - initial_storage_usage) as Balance * env::storage_byte_cost();
+        assert_eq!(storage_cost, cost.0);
+        assert_eq!(contract.storage_balance_of(accounts(2)).unwrap().total, cost);
+    }
+}
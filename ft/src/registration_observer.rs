@@ -0,0 +1,69 @@
+/*!
+Notifications of new registrations for onboarding flows. While the owner configures an observer,
+every account registered through `storage_deposit` or `storage_deposit_in_token` triggers an
+`on_account_registered(account_id)` call on it.
+
+Like the incoming hooks, the call is fire-and-forget in its own receipt, so a failing observer
+never blocks a registration. Registering callers need to attach `GAS_FOR_REGISTRATION_OBSERVER` on
+top of the usual gas while an observer is configured.
+*/
+use crate::*;
+use near_sdk::ext_contract;
+
+pub const GAS_FOR_REGISTRATION_OBSERVER: Gas = Gas(5_000_000_000_000);
+
+/// Interface the observer is expected to implement.
+#[ext_contract(ext_registration_observer)]
+#[allow(dead_code)]
+pub trait RegistrationObserver {
+    fn on_account_registered(&mut self, account_id: AccountId);
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the contract notified of new registrations, or stops notifying when `None`.
+    pub fn set_registration_observer(&mut self, observer_id: Option<AccountId>) {
+        self.assert_owner_action();
+        self.registration_observer = observer_id;
+    }
+
+    pub fn get_registration_observer(&self) -> Option<AccountId> {
+        self.registration_observer.clone()
+    }
+}
+
+impl Contract {
+    /// Records the registration of `account_id` and notifies the observer, if any.
+    pub(crate) fn internal_on_account_registered(&mut self, account_id: &AccountId) {
+        self.internal_record_change(format!("account_registered:{}", account_id));
+        if let Some(observer_id) = self.registration_observer.clone() {
+            ext_registration_observer::ext(observer_id)
+                .with_static_gas(GAS_FOR_REGISTRATION_OBSERVER)
+                .on_account_registered(account_id.clone());
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, get_created_receipts};
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_registration_notifies_observer() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_registration_observer(Some(accounts(3)));
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        let receipts = get_created_receipts();
+        assert!(receipts.iter().any(|receipt| receipt.receiver_id == accounts(3)));
+    }
+}
@@ -0,0 +1,164 @@
+/*!
+Gasless transfers through relayers. A sender registers an ed25519 relay key once, then signs
+transfer authorizations off-chain. Any relayer can submit them with `ft_transfer_relayed`, paying
+the gas and getting the signed `fee` in tokens from the sender in return.
+
+The signed message is
+`"<token contract>:relay:<sender>:<receiver>:<amount>:<fee>:<nonce>:<memo>"`, with an empty memo
+for none. `nonce` is the sender's current relay nonce, which goes up with every relayed transfer,
+so each authorization can be used once. The nonce starts at the block timestamp in nanoseconds
+when the key is first registered, so authorizations signed before an account was closed can't be
+replayed after it re-registers.
+*/
+use crate::signatures::{ed25519_key_bytes, verify_ed25519};
+use crate::*;
+use near_sdk::json_types::Base64VecU8;
+use near_sdk::PublicKey;
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the key the caller signs relayed transfers with, or stops relaying when `None`.
+    /// Requires at least 1 yoctoNEAR attached, which must also cover the added storage, the rest
+    /// is refunded.
+    #[payable]
+    pub fn set_relay_key(&mut self, public_key: Option<PublicKey>) {
+        assert_at_least_one_yocto();
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        self.token.internal_unwrap_balance_of(&account_id);
+        let initial_storage_usage = env::storage_usage();
+        let nonce =
+            self.relay_keys.get(&account_id).map_or(env::block_timestamp(), |(_, nonce)| nonce);
+        self.relay_keys.insert(&account_id, &(public_key.as_ref().map(ed25519_key_bytes), nonce));
+        refund_deposit(initial_storage_usage);
+    }
+
+    /// Nonce the next relayed transfer of `account_id` has to be signed with.
+    pub fn get_relay_nonce(&self, account_id: AccountId) -> Option<u64> {
+        self.relay_keys.get(&account_id).map(|(_, nonce)| nonce)
+    }
+
+    /// Transfers `amount` from `sender` to `receiver` and `fee` from `sender` to the caller, as
+    /// authorized by `signature` of the sender's relay key.
+    #[payable]
+    pub fn ft_transfer_relayed(
+        &mut self,
+        sender: AccountId,
+        receiver: AccountId,
+        amount: U128,
+        memo: Option<String>,
+        fee: U128,
+        signature: Base64VecU8,
+    ) {
+        self.assert_priority_fee_attached(&[amount.0]);
+        self.assert_no_transfer_validator();
+        self.assert_no_attestation_gate();
+        let (key, nonce) = self.relay_keys.get(&sender).unwrap_or_default();
+        let key = key.expect("The sender has no relay key");
+        let message = format!(
+            "{}:relay:{}:{}:{}:{}:{}:{}",
+            env::current_account_id(),
+            sender,
+            receiver,
+            amount.0,
+            fee.0,
+            nonce,
+            memo.as_deref().unwrap_or("")
+        );
+        assert!(
+            verify_ed25519(&key, message.as_bytes(), &signature.0),
+            "The signature doesn't match the sender"
+        );
+        self.relay_keys.insert(&sender, &(Some(key), nonce + 1));
+        let total = amount.0.checked_add(fee.0).expect("The total amount overflows");
+        assert!(
+            total <= self.token.internal_unwrap_balance_of(&sender),
+            "The balance doesn't cover the amount and the fee"
+        );
+        self.internal_transfer(&sender, &receiver, amount.0, memo);
+        if fee.0 > 0 {
+            let relayer_id = env::predecessor_account_id();
+            self.internal_transfer(&sender, &relayer_id, fee.0, Some("relay fee".to_string()));
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use ed25519_dalek::{Keypair, SecretKey, Signer};
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+    use std::convert::TryFrom;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn keypair() -> Keypair {
+        let secret = SecretKey::from_bytes(&[1; 32]).unwrap();
+        let public = (&secret).into();
+        Keypair { secret, public }
+    }
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        for account_id in [accounts(2), accounts(3)] {
+            testing_env!(context
+                .attached_deposit(contract.storage_balance_bounds().min.into())
+                .predecessor_account_id(account_id)
+                .build());
+            contract.storage_deposit(None, None);
+        }
+        testing_env!(context
+            .attached_deposit(ONE_NEAR / 100)
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(5)
+            .build());
+        let public_key = [&[0u8][..], &keypair().public.to_bytes()].concat();
+        contract.set_relay_key(Some(PublicKey::try_from(public_key).unwrap()));
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(3)).build());
+        (context, contract)
+    }
+
+    fn relay(contract: &mut Contract, nonce: u64) {
+        let message =
+            format!("{}:relay:{}:{}:100:10:{}:", accounts(0), accounts(1), accounts(2), nonce);
+        let signature = keypair().sign(message.as_bytes()).to_bytes().to_vec();
+        contract.ft_transfer_relayed(
+            accounts(1),
+            accounts(2),
+            100.into(),
+            None,
+            10.into(),
+            signature.into(),
+        );
+    }
+
+    #[test]
+    fn test_relayed_transfer_pays_the_relayer() {
+        let (_, mut contract) = setup();
+        assert_eq!(contract.get_relay_nonce(accounts(1)), Some(5));
+        relay(&mut contract, 5);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 100);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 10);
+        assert_eq!(contract.get_relay_nonce(accounts(1)), Some(6));
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of at least 1 yoctoNEAR")]
+    fn test_key_swap_requires_deposit() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.attached_deposit(0).predecessor_account_id(accounts(1)).build());
+        let public_key = [&[0u8][..], &[2u8; 32][..]].concat();
+        contract.set_relay_key(Some(PublicKey::try_from(public_key).unwrap()));
+    }
+
+    #[test]
+    #[should_panic(expected = "The signature doesn't match the sender")]
+    fn test_replayed_authorization() {
+        let (_, mut contract) = setup();
+        relay(&mut contract, 5);
+        relay(&mut contract, 5);
+    }
+}
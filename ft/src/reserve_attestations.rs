@@ -0,0 +1,125 @@
+/*!
+Proof of reserves for an asset-backed deployment. A custodian configured by the owner signs how
+much it holds off-chain, and anyone can submit the signed attestation. The latest one is stored
+next to the circulating supply at the time, so holders can check the backing on-chain.
+
+The custodian signs `"<token contract>:<external_reserve>:<timestamp>"` with its ed25519 key,
+where `timestamp` is in seconds. Attestations older than `MAX_ATTESTATION_AGE_SEC`, from the future
+or not newer than the latest one are rejected.
+*/
+use crate::signatures::{ed25519_key_bytes, verify_ed25519};
+use crate::*;
+use near_sdk::json_types::Base64VecU8;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::PublicKey;
+
+pub const MAX_ATTESTATION_AGE_SEC: u64 = 24 * 60 * 60;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReserveAttestation {
+    pub external_reserve: U128,
+    /// Total supply when the attestation was recorded.
+    pub circulating_supply: U128,
+    /// Timestamp (in seconds) signed by the custodian.
+    pub timestamp: u64,
+    /// Timestamp (in seconds) at which the attestation was recorded.
+    pub recorded_at: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the key of the custodian signing attestations, or removes it.
+    pub fn set_reserve_custodian(&mut self, public_key: Option<PublicKey>) {
+        self.assert_owner_action();
+        self.reserve_custodian_key = public_key.as_ref().map(ed25519_key_bytes);
+    }
+
+    /// Records an attestation signed by the custodian.
+    pub fn attest_reserves(
+        &mut self,
+        external_reserve: U128,
+        timestamp: u64,
+        signature: Base64VecU8,
+    ) -> ReserveAttestation {
+        let key = self.reserve_custodian_key.as_ref().expect("No custodian is configured");
+        let message = format!("{}:{}:{}", env::current_account_id(), external_reserve.0, timestamp);
+        assert!(
+            verify_ed25519(key, message.as_bytes(), &signature.0),
+            "The signature doesn't match the custodian"
+        );
+        let now = current_timestamp_sec();
+        assert!(timestamp <= now, "The attestation is from the future");
+        assert!(timestamp + MAX_ATTESTATION_AGE_SEC >= now, "The attestation is stale");
+        if let Some(latest) = self.latest_attestation.as_ref() {
+            assert!(timestamp > latest.timestamp, "The attestation is older than the latest one");
+        }
+        let attestation = ReserveAttestation {
+            external_reserve,
+            circulating_supply: self.token.total_supply.into(),
+            timestamp,
+            recorded_at: now,
+        };
+        self.latest_attestation = Some(attestation.clone());
+        attestation
+    }
+
+    pub fn get_latest_attestation(&self) -> Option<ReserveAttestation> {
+        self.latest_attestation.clone()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use ed25519_dalek::{Keypair, SecretKey, Signer};
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+    use std::convert::TryFrom;
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    fn custodian() -> Keypair {
+        let secret = SecretKey::from_bytes(&[7; 32]).unwrap();
+        let public = (&secret).into();
+        Keypair { secret, public }
+    }
+
+    fn sign(external_reserve: Balance, timestamp: u64) -> Base64VecU8 {
+        let message = format!("{}:{}:{}", env::current_account_id(), external_reserve, timestamp);
+        custodian().sign(message.as_bytes()).to_bytes().to_vec().into()
+    }
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.block_timestamp(1_000 * NANOS).build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        let public_key = [&[0u8][..], &custodian().public.to_bytes()].concat();
+        contract.set_reserve_custodian(Some(PublicKey::try_from(public_key).unwrap()));
+        contract.attest_reserves(TOTAL_SUPPLY.into(), 900, sign(TOTAL_SUPPLY, 900));
+        (context, contract)
+    }
+
+    #[test]
+    fn test_attest_reserves() {
+        let (_, contract) = setup();
+        let attestation = contract.get_latest_attestation().unwrap();
+        assert_eq!(attestation.external_reserve.0, TOTAL_SUPPLY);
+        assert_eq!(attestation.circulating_supply.0, TOTAL_SUPPLY);
+        assert_eq!(attestation.recorded_at, 1_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "The attestation is older than the latest one")]
+    fn test_out_of_order_attestation() {
+        let (_, mut contract) = setup();
+        contract.attest_reserves(TOTAL_SUPPLY.into(), 800, sign(TOTAL_SUPPLY, 800));
+    }
+
+    #[test]
+    #[should_panic(expected = "The signature doesn't match the custodian")]
+    fn test_forged_attestation() {
+        let (_, mut contract) = setup();
+        contract.attest_reserves((2 * TOTAL_SUPPLY).into(), 950, sign(TOTAL_SUPPLY, 950));
+    }
+}
@@ -0,0 +1,156 @@
+/*!
+Collateral backing for queued mints. While the owner configures a requirement, approving a mint
+request first reads how much of the collateral token this contract holds, with the collateral
+contract's `ft_balance_of`, and only mints in the callback if the supply after the mint stays
+within `collateral * 10_000 / min_reserve_ratio_bps`.
+
+The request stays queued until the callback, which checks against the supply at that time, so
+mints completing in between are accounted for and approving the same request twice mints once. A
+request that fails the check stays queued and can be approved again once more collateral arrives.
+*/
+use crate::math::{mul_div, BPS_DENOMINATOR};
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{ext_contract, PromiseError};
+
+const GAS_FOR_COLLATERAL_BALANCE: Gas = Gas(5_000_000_000_000);
+const GAS_FOR_ON_RESERVES_CHECKED: Gas = Gas(20_000_000_000_000);
+
+/// Part of the NEP-141 interface of the collateral token.
+#[ext_contract(ext_collateral_token)]
+#[allow(dead_code)]
+pub trait CollateralToken {
+    fn ft_balance_of(&self, account_id: AccountId) -> U128;
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReserveRequirement {
+    pub collateral_contract: AccountId,
+    /// Collateral required per token of supply, in basis points. Can exceed 100% for
+    /// over-collateralization.
+    pub min_reserve_ratio_bps: u32,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Requires collateral for approved mints, or stops requiring it when `None`.
+    pub fn set_reserve_requirement(&mut self, requirement: Option<ReserveRequirement>) {
+        self.assert_owner_action();
+        if let Some(requirement) = requirement.as_ref() {
+            assert!(
+                requirement.min_reserve_ratio_bps > 0,
+                "The reserve ratio should be a positive number"
+            );
+        }
+        self.reserve_requirement = requirement;
+    }
+
+    pub fn get_reserve_requirement(&self) -> Option<ReserveRequirement> {
+        self.reserve_requirement.clone()
+    }
+
+    /// Executes the mint request if the collateral covers the supply after it.
+    #[private]
+    pub fn on_reserves_checked(
+        &mut self,
+        request_id: u64,
+        #[callback_result] collateral: Result<U128, PromiseError>,
+    ) -> bool {
+        let collateral = match collateral {
+            Ok(collateral) => collateral.0,
+            Err(_) => {
+                log!("Couldn't read the collateral, mint request {} stays queued", request_id);
+                return false;
+            }
+        };
+        let request = match self.mint_requests.get(&request_id) {
+            Some(request) => request,
+            None => {
+                log!("Mint request {} was already executed or rejected", request_id);
+                return false;
+            }
+        };
+        if !self.internal_reserves_cover(collateral, request.amount.0) {
+            log!("The collateral doesn't cover mint request {}, it stays queued", request_id);
+            return false;
+        }
+        self.internal_execute_mint_request(request_id);
+        true
+    }
+}
+
+impl Contract {
+    /// The call reading the collateral, or `None` if no collateral is required.
+    pub(crate) fn internal_reserve_check(&self, request_id: u64) -> Option<Promise> {
+        let requirement = self.reserve_requirement.as_ref()?;
+        Some(
+            ext_collateral_token::ext(requirement.collateral_contract.clone())
+                .with_static_gas(GAS_FOR_COLLATERAL_BALANCE)
+                .ft_balance_of(env::current_account_id())
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(GAS_FOR_ON_RESERVES_CHECKED)
+                        .on_reserves_checked(request_id),
+                ),
+        )
+    }
+
+    /// Whether `collateral` backs the supply after minting `amount`.
+    fn internal_reserves_cover(&self, collateral: Balance, amount: Balance) -> bool {
+        let ratio_bps = match self.reserve_requirement.as_ref() {
+            Some(requirement) => requirement.min_reserve_ratio_bps,
+            None => return true,
+        };
+        let max_supply = mul_div(collateral, BPS_DENOMINATOR as Balance, ratio_bps as Balance);
+        self.token.total_supply.checked_add(amount).is_some_and(|supply| supply <= max_supply)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.add_minter(accounts(2));
+        contract.set_reserve_requirement(Some(ReserveRequirement {
+            collateral_contract: accounts(4),
+            min_reserve_ratio_bps: 20_000,
+        }));
+        testing_env!(context
+            .attached_deposit(ONE_NEAR / 100)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.propose_mint(accounts(1), 100.into(), "grant".to_string());
+        testing_env!(context
+            .attached_deposit(0)
+            .predecessor_account_id(env::current_account_id())
+            .build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_mint_within_reserves() {
+        let (_, mut contract) = setup();
+        let collateral = 2 * (TOTAL_SUPPLY + 100);
+        assert!(contract.on_reserves_checked(0, Ok(collateral.into())));
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY + 100);
+        assert!(!contract.on_reserves_checked(0, Ok(collateral.into())));
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY + 100);
+    }
+
+    #[test]
+    fn test_mint_beyond_reserves() {
+        let (_, mut contract) = setup();
+        assert!(!contract.on_reserves_checked(0, Ok((2 * (TOTAL_SUPPLY + 99)).into())));
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+        assert_eq!(contract.get_mint_requests(None, None).len(), 1);
+    }
+}
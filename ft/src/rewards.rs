@@ -0,0 +1,270 @@
+/*!
+Dividend-style rewards. Anyone can distribute tokens from their balance to all holders, themselves
+included, pro-rata to their balances at the time of the distribution. The contract account's own
+balance, which holds the undistributed rewards among other things, doesn't earn rewards.
+
+Rewards are tracked with a global reward-per-token accumulator and a checkpoint per account, so a
+distribution costs the same no matter how many holders there are. An account's checkpoint has to
+be settled before every change to its balance, which is why all balance changes go through
+`internal_transfer`, `internal_move_balance`, `internal_mint` or `internal_burn`.
+
+Pending rewards are paid out by `claim_rewards`, to the caller or to another registered account
+such as a cold wallet, or automatically whenever the account sends or
+receives tokens if the owner enabled auto-claim.
+*/
+use crate::math::mul_div;
+use crate::*;
+
+/// Scale of the reward-per-token accumulator.
+const REWARD_PRECISION: Balance = 1_000_000_000_000_000_000_000_000;
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct RewardCheckpoint {
+    /// Value of the accumulator when the account was last settled.
+    pub reward_per_token_paid: Balance,
+    /// Rewards earned but not yet paid out.
+    pub pending: Balance,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Distributes `amount` of the caller's tokens to all holders pro-rata to their balances.
+    #[payable]
+    pub fn distribute_rewards(&mut self, amount: U128) {
+        assert_one_yocto();
+        assert!(amount.0 > 0, "The amount should be a positive number");
+        let account_id = env::predecessor_account_id();
+        let current_account_id = env::current_account_id();
+        self.internal_register_contract_account();
+        self.internal_move_balance(
+            &account_id,
+            &current_account_id,
+            amount.0,
+            Some("rewards".to_string()),
+        );
+        assert!(self.internal_eligible_supply() > 0, "Nobody to distribute the rewards to");
+        self.internal_add_rewards(amount.0);
+    }
+
+    /// Pays out the caller's pending rewards to `to`, or to the caller when `None`. `to` must be
+    /// registered and accept incoming transfers.
+    pub fn claim_rewards(&mut self, to: Option<AccountId>) -> U128 {
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        let to = self.internal_reward_destination(&account_id, to);
+        self.internal_claim_rewards(&account_id, &to).into()
+    }
+
+    pub fn pending_rewards_of(&self, account_id: AccountId) -> U128 {
+        let balance = self.token.accounts.get(&account_id).unwrap_or(0);
+        self.reward_checkpoints
+            .get(&account_id)
+            .map_or(0, |checkpoint| {
+                checkpoint.pending
+                    + mul_div(
+                        balance,
+                        self.reward_per_token - checkpoint.reward_per_token_paid,
+                        REWARD_PRECISION,
+                    )
+            })
+            .into()
+    }
+
+    /// Enables or disables paying out pending rewards on every transfer.
+    pub fn set_auto_claim_rewards(&mut self, enabled: bool) {
+        self.assert_owner_action();
+        self.auto_claim_rewards = enabled;
+    }
+
+    pub fn get_auto_claim_rewards(&self) -> bool {
+        self.auto_claim_rewards
+    }
+}
+
+impl Contract {
+    /// Credits `amount`, already held by the contract account, to all holders pro-rata to their
+    /// balances. Goes to the dust pool if there's nobody to credit.
+    pub(crate) fn internal_add_rewards(&mut self, amount: Balance) {
+        let eligible_supply = self.internal_eligible_supply();
+        if eligible_supply == 0 {
+            self.dust_pool += amount;
+            return;
+        }
+        let reward_per_token = mul_div(amount, REWARD_PRECISION, eligible_supply);
+        // What the accumulator can't credit to anyone goes to the dust pool.
+        let credited = mul_div(reward_per_token, eligible_supply, REWARD_PRECISION);
+        self.reward_per_token += reward_per_token;
+        self.rewards_pool += credited;
+        self.dust_pool += amount - credited;
+    }
+
+    pub(crate) fn internal_eligible_supply(&self) -> Balance {
+        let current_account_id = env::current_account_id();
+        self.token.total_supply
+            - self.token.accounts.get(&current_account_id).unwrap_or(0)
+            - self.legacy_supply
+    }
+
+    /// Adds the rewards earned by `account_id` since its last settlement to its pending rewards.
+    /// Must be called before any change to its balance.
+    pub(crate) fn internal_settle_rewards(&mut self, account_id: &AccountId) {
+        if account_id == &env::current_account_id() {
+            return;
+        }
+        let balance = match self.token.accounts.get(account_id) {
+            Some(balance) => balance,
+            None => return,
+        };
+        let mut checkpoint = match self.reward_checkpoints.get(account_id) {
+            Some(checkpoint) => checkpoint,
+            None => {
+                self.internal_track_legacy_account(account_id, balance);
+                return;
+            }
+        };
+        if checkpoint.reward_per_token_paid == self.reward_per_token {
+            return;
+        }
+        checkpoint.pending += mul_div(
+            balance,
+            self.reward_per_token - checkpoint.reward_per_token_paid,
+            REWARD_PRECISION,
+        );
+        checkpoint.reward_per_token_paid = self.reward_per_token;
+        self.reward_checkpoints.insert(account_id, &checkpoint);
+    }
+
+    /// Account the rewards of `account_id` are paid to: `to` if given, which must be registered and
+    /// accept incoming transfers, otherwise `account_id` itself.
+    pub(crate) fn internal_reward_destination(
+        &self,
+        account_id: &AccountId,
+        to: Option<AccountId>,
+    ) -> AccountId {
+        match to {
+            Some(to) if &to != account_id => {
+                self.token.internal_unwrap_balance_of(&to);
+                assert_ok(self.check_incoming_allowed(account_id, &to));
+                to
+            }
+            _ => account_id.clone(),
+        }
+    }
+
+    /// Settles `account_id` and pays out its pending rewards plus the hold streak boost on them to
+    /// `to`. Returns the amount paid.
+    pub(crate) fn internal_claim_rewards(
+        &mut self,
+        account_id: &AccountId,
+        to: &AccountId,
+    ) -> Balance {
+        self.internal_apply_demurrage(account_id);
+        self.internal_settle_rewards(account_id);
+        if to != account_id {
+            self.internal_apply_demurrage(to);
+            self.internal_settle_rewards(to);
+        }
+        let mut checkpoint = match self.reward_checkpoints.get(account_id) {
+            Some(checkpoint) if checkpoint.pending > 0 => checkpoint,
+            _ => return 0,
+        };
+        let amount = checkpoint.pending;
+        checkpoint.pending = 0;
+        self.reward_checkpoints.insert(account_id, &checkpoint);
+        self.rewards_pool -= amount;
+        self.token.internal_transfer(
+            &env::current_account_id(),
+            to,
+            amount,
+            Some("rewards".to_string()),
+        );
+        self.internal_record_balance(to);
+        amount + self.internal_mint_hold_boost(account_id, to, amount)
+    }
+
+    /// Pays out the pending rewards of both sides of a transfer if auto-claim is enabled.
+    pub(crate) fn internal_auto_claim_rewards(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+    ) {
+        if self.auto_claim_rewards {
+            self.internal_claim_rewards(sender_id, sender_id);
+            self.internal_claim_rewards(receiver_id, receiver_id);
+        }
+    }
+
+    /// Adds the storage of a reward checkpoint for the longest possible account ID to the storage
+    /// required to register an account.
+    pub(crate) fn measure_reward_storage_usage(&mut self) {
+        let initial_storage_usage = env::storage_usage();
+        let tmp_account_id = AccountId::new_unchecked("a".repeat(64));
+        self.internal_init_reward_checkpoint(&tmp_account_id);
+        self.token.account_storage_usage += env::storage_usage() - initial_storage_usage;
+        self.reward_checkpoints.remove(&tmp_account_id);
+    }
+
+    /// Starts tracking rewards for a newly registered account.
+    pub(crate) fn internal_init_reward_checkpoint(&mut self, account_id: &AccountId) {
+        if self.reward_checkpoints.get(account_id).is_none() {
+            let checkpoint =
+                RewardCheckpoint { reward_per_token_paid: self.reward_per_token, pending: 0 };
+            self.reward_checkpoints.insert(account_id, &checkpoint);
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        for account_id in [accounts(2), accounts(3)] {
+            testing_env!(context
+                .attached_deposit(contract.storage_balance_bounds().min.into())
+                .predecessor_account_id(account_id)
+                .build());
+            contract.storage_deposit(None, None);
+        }
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), (TOTAL_SUPPLY / 2).into(), None);
+        (context, contract)
+    }
+
+    #[test]
+    fn test_distribute_and_claim() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.distribute_rewards(1_000.into());
+        assert_eq!(contract.pending_rewards_of(accounts(1)).0, 500);
+        assert_eq!(contract.pending_rewards_of(accounts(2)).0, 499);
+
+        testing_env!(context.attached_deposit(0).predecessor_account_id(accounts(1)).build());
+        assert_eq!(contract.claim_rewards(None).0, 500);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, TOTAL_SUPPLY / 2 + 500);
+        assert_eq!(contract.pending_rewards_of(accounts(1)).0, 0);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        assert_eq!(contract.claim_rewards(Some(accounts(3))).0, 499);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 499);
+    }
+
+    #[test]
+    fn test_auto_claim_on_transfer() {
+        let (mut context, mut contract) = setup();
+        contract.distribute_rewards(1_000.into());
+        contract.set_auto_claim_rewards(true);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.ft_transfer(accounts(3), 10.into(), None);
+        assert_eq!(contract.pending_rewards_of(accounts(2)).0, 0);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY / 2 + 500 - 10);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 10);
+    }
+}
@@ -0,0 +1,211 @@
+/*!
+Compliance holds on large incoming transfers. When configured, a transfer of at least the
+threshold is credited to the receiver right away, but the amount stays locked for the hold period,
+like a bank's deposit hold. This applies to every kind of transfer, `ft_transfer_call` and
+allowance spends included. If the receiver forwards its incoming transfers, the hold applies to
+the account the tokens were forwarded to.
+
+The sender pays for the storage of the hold, on top of the 1 yoctoNEAR or the priority fee it
+attaches, see `settlement_hold_deposit_for`.
+
+Holds are enforced together with the time locks, and matured holds are spendable right away.
+`settle_pending` only removes matured holds from storage.
+*/
+use crate::events::emit_event;
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+
+/// Most holds pending on a single account.
+pub const MAX_PENDING_SETTLEMENTS: usize = 20;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SettlementHold {
+    /// Smallest transfer that gets held.
+    pub threshold: U128,
+    /// How long (in seconds) held transfers stay locked.
+    pub hold_seconds: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Enables holds on large transfers, or disables them for new transfers when `hold` is `None`.
+    pub fn set_settlement_hold(&mut self, hold: Option<SettlementHold>) {
+        self.assert_owner_action();
+        if let Some(hold) = hold.as_ref() {
+            assert!(hold.threshold.0 > 0, "The threshold should be a positive number");
+        }
+        self.settlement_hold = hold;
+    }
+
+    pub fn get_settlement_hold(&self) -> Option<SettlementHold> {
+        self.settlement_hold.clone()
+    }
+
+    /// Holds on `account_id`'s balance as `(amount, release timestamp in seconds)`, matured ones
+    /// included until they're settled.
+    pub fn pending_settlement_of(&self, account_id: AccountId) -> Vec<(U128, u64)> {
+        self.pending_settlement
+            .get(&account_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(amount, release_ts)| (amount.into(), release_ts))
+            .collect()
+    }
+
+    /// NEAR the sender of a transfer of `amount` attaches for the storage of the hold it puts on
+    /// the receiver.
+    pub fn settlement_hold_deposit_for(&self, amount: U128) -> U128 {
+        self.internal_hold_deposit(&[amount.0]).into()
+    }
+
+    /// Removes the matured holds of `account_id`. Returns the amount they held.
+    pub fn settle_pending(&mut self, account_id: AccountId) -> U128 {
+        let holds = self.pending_settlement.get(&account_id).unwrap_or_default();
+        let now = current_timestamp_sec();
+        let (matured, pending): (Vec<_>, Vec<_>) =
+            holds.into_iter().partition(|(_, release_ts)| *release_ts <= now);
+        let settled: Balance = matured.iter().map(|(amount, _)| amount).sum();
+        if pending.is_empty() {
+            self.pending_settlement.remove(&account_id);
+        } else {
+            self.pending_settlement.insert(&account_id, &pending);
+        }
+        if settled > 0 {
+            emit_event(
+                "settlement_released",
+                json!({ "account_id": account_id, "amount": U128(settled) }),
+            );
+        }
+        settled.into()
+    }
+}
+
+impl Contract {
+    /// Holds `amount` just received by `holder_id` if it's large enough.
+    pub(crate) fn internal_hold_settlement(&mut self, holder_id: &AccountId, amount: Balance) {
+        let hold = match self.settlement_hold.as_ref() {
+            Some(hold) if amount >= hold.threshold.0 => hold,
+            _ => return,
+        };
+        let release_ts = current_timestamp_sec() + hold.hold_seconds;
+        let holder_id = holder_id.clone();
+        self.settle_pending(holder_id.clone());
+        let mut holds = self.pending_settlement.get(&holder_id).unwrap_or_default();
+        assert!(
+            holds.len() < MAX_PENDING_SETTLEMENTS,
+            "The receiver has too many pending settlements"
+        );
+        holds.push((amount, release_ts));
+        self.pending_settlement.insert(&holder_id, &holds);
+        emit_event(
+            "settlement_held",
+            json!({ "account_id": holder_id, "amount": U128(amount), "release_ts": release_ts }),
+        );
+    }
+
+    /// NEAR covering the storage of the holds that transfers of `amounts` can put on their
+    /// receivers.
+    pub(crate) fn internal_hold_deposit(&self, amounts: &[Balance]) -> Balance {
+        let threshold = match self.settlement_hold.as_ref() {
+            Some(hold) => hold.threshold.0,
+            None => return 0,
+        };
+        let holds = amounts.iter().filter(|amount| **amount >= threshold).count() as Balance;
+        holds * self.settlement_hold_storage_usage as Balance * env::storage_byte_cost()
+    }
+
+    pub(crate) fn measure_settlement_hold_storage_usage(&mut self) {
+        let initial_storage_usage = env::storage_usage();
+        let tmp_account_id = AccountId::new_unchecked("a".repeat(64));
+        self.pending_settlement.insert(&tmp_account_id, &vec![(0, 0)]);
+        self.settlement_hold_storage_usage = env::storage_usage() - initial_storage_usage;
+        self.pending_settlement.remove(&tmp_account_id);
+    }
+
+    /// Amount of `account_id`'s balance still on hold.
+    pub(crate) fn internal_held_balance(&self, account_id: &AccountId) -> Balance {
+        let now = current_timestamp_sec();
+        self.pending_settlement.get(account_id).map_or(0, |holds| {
+            holds.iter().filter(|(_, release_ts)| *release_ts > now).map(|(amount, _)| amount).sum()
+        })
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_settlement_hold(Some(SettlementHold {
+            threshold: 1_000.into(),
+            hold_seconds: 100,
+        }));
+        for account_id in [accounts(2), accounts(3)] {
+            testing_env!(context
+                .attached_deposit(contract.storage_balance_bounds().min.into())
+                .predecessor_account_id(account_id)
+                .build());
+            contract.storage_deposit(None, None);
+        }
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), 999.into(), None);
+        let deposit = contract.settlement_hold_deposit_for(1_000.into()).0;
+        assert!(deposit > 0);
+        testing_env!(context.attached_deposit(deposit).build());
+        contract.ft_transfer(accounts(2), 1_000.into(), None);
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(2)).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_hold_matures() {
+        let (mut context, mut contract) = setup();
+        assert_eq!(contract.pending_settlement_of(accounts(2)), vec![(1_000.into(), 100)]);
+        contract.ft_transfer(accounts(3), 999.into(), None);
+        testing_env!(context.block_timestamp(100 * NANOS).build());
+        assert_eq!(contract.settle_pending(accounts(2)).0, 1_000);
+        assert!(contract.pending_settlement_of(accounts(2)).is_empty());
+        let deposit = contract.settlement_hold_deposit_for(1_000.into()).0;
+        testing_env!(context.attached_deposit(deposit).build());
+        contract.ft_transfer(accounts(3), 1_000.into(), None);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 1_999);
+    }
+
+    #[test]
+    fn test_transfer_call_is_held() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .attached_deposit(contract.settlement_hold_deposit_for(1_000.into()).0)
+            .predecessor_account_id(accounts(1))
+            .prepaid_gas(Gas(300_000_000_000_000))
+            .build());
+        contract.ft_transfer_call(accounts(3), 1_000.into(), None, String::new());
+        assert_eq!(contract.pending_settlement_of(accounts(3)), vec![(1_000.into(), 100)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "for the priority fee and the storage")]
+    fn test_hold_storage_is_charged() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(3), 1_000.into(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "The amount exceeds the unlocked balance")]
+    fn test_held_tokens_cant_be_spent() {
+        let (mut context, mut contract) = setup();
+        let deposit = contract.settlement_hold_deposit_for(1_000.into()).0;
+        testing_env!(context.attached_deposit(deposit).build());
+        contract.ft_transfer(accounts(3), 1_000.into(), None);
+    }
+}
@@ -0,0 +1,15 @@
+use ed25519_dalek::Verifier;
+use near_sdk::{CurveType, PublicKey};
+
+/// Returns the raw 32 bytes of an ed25519 `public_key`. Panics for other curves.
+pub(crate) fn ed25519_key_bytes(public_key: &PublicKey) -> Vec<u8> {
+    assert!(public_key.curve_type() == CurveType::ED25519, "Only ed25519 keys are supported");
+    public_key.as_bytes()[1..].to_vec()
+}
+
+/// Whether `signature` is a valid ed25519 signature of `message` by the raw `public_key`.
+pub(crate) fn verify_ed25519(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    let public_key = ed25519_dalek::PublicKey::from_bytes(public_key).expect("Invalid public key");
+    let signature = ed25519_dalek::Signature::from_bytes(signature).expect("Invalid signature");
+    public_key.verify(message, &signature).is_ok()
+}
@@ -0,0 +1,145 @@
+/*!
+Oracle-driven supply adjustment for a soft peg. The configured oracle reports the target and the
+current price, and the supply moves towards the peg: above the target, new tokens are minted into
+the stabilization pool; below it, tokens are burned from the pool. The adjustment is the
+circulating supply times the relative price deviation, scaled by the responsiveness and capped per
+call. Contractions are limited to what the pool holds, expansions to what the mint limits allow.
+
+The pool is held by the contract account and doesn't count as circulating supply.
+*/
+use crate::events::emit_event;
+use crate::math::{bps_of, mul_div, BPS_DENOMINATOR};
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Stabilizer {
+    /// Account reporting the prices.
+    pub oracle_id: AccountId,
+    /// Share of the price deviation applied to the supply per call, in basis points.
+    pub responsiveness_bps: u16,
+    /// Largest adjustment a single call can make.
+    pub max_adjustment: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Configures the supply adjustment, or disables it when `stabilizer` is `None`. The pool is
+    /// kept either way.
+    pub fn set_stabilizer(&mut self, stabilizer: Option<Stabilizer>) {
+        self.assert_owner_action();
+        if let Some(stabilizer) = stabilizer.as_ref() {
+            assert!(
+                stabilizer.responsiveness_bps <= BPS_DENOMINATOR,
+                "The responsiveness can't exceed {} bps",
+                BPS_DENOMINATOR
+            );
+        }
+        self.stabilizer = stabilizer;
+    }
+
+    pub fn get_stabilizer(&self) -> Option<Stabilizer> {
+        self.stabilizer.clone()
+    }
+
+    pub fn get_stabilization_pool(&self) -> U128 {
+        self.stabilization_pool.into()
+    }
+
+    /// Total supply minus the stabilization pool and the insurance fund.
+    pub fn get_circulating_supply(&self) -> U128 {
+        (self.token.total_supply - self.stabilization_pool - self.insurance_fund).into()
+    }
+
+    /// Mints into or burns from the pool depending on how far `current_price` is from
+    /// `target_price`. Only callable by the oracle. Returns the amount minted or burned.
+    pub fn adjust_supply(&mut self, target_price: U128, current_price: U128) -> U128 {
+        let stabilizer = self.stabilizer.clone().expect("The stabilizer is not configured");
+        assert_eq!(
+            env::predecessor_account_id(),
+            stabilizer.oracle_id,
+            "Only the oracle can call this method"
+        );
+        assert!(target_price.0 > 0, "The target price should be a positive number");
+        let deviation = target_price.0.max(current_price.0) - target_price.0.min(current_price.0);
+        let circulating_supply = self.get_circulating_supply().0;
+        let adjustment = bps_of(
+            mul_div(circulating_supply, deviation, target_price.0),
+            stabilizer.responsiveness_bps,
+        )
+        .min(stabilizer.max_adjustment.0);
+        let current_account_id = env::current_account_id();
+        let (amount, direction) = if current_price.0 > target_price.0 {
+            let amount = adjustment.min(self.internal_mint_headroom());
+            if amount > 0 {
+                self.internal_register_contract_account();
+                self.internal_mint(&current_account_id, amount, "supply expansion");
+                self.stabilization_pool += amount;
+            }
+            (amount, "expansion")
+        } else {
+            let amount = adjustment.min(self.stabilization_pool);
+            if amount > 0 {
+                self.internal_burn(&current_account_id, amount, "supply contraction");
+                self.stabilization_pool -= amount;
+            }
+            (amount, "contraction")
+        };
+        emit_event(
+            "supply_adjusted",
+            json!({
+                "direction": direction,
+                "amount": U128(amount),
+                "target_price": target_price,
+                "current_price": current_price,
+            }),
+        );
+        amount.into()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_stabilizer(Some(Stabilizer {
+            oracle_id: accounts(2),
+            responsiveness_bps: 5_000,
+            max_adjustment: (TOTAL_SUPPLY / 100).into(),
+        }));
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_expand_and_contract() {
+        let (_, mut contract) = setup();
+        // 1% above the peg at 50% responsiveness.
+        assert_eq!(contract.adjust_supply(100.into(), 101.into()).0, TOTAL_SUPPLY / 200);
+        assert_eq!(contract.get_stabilization_pool().0, TOTAL_SUPPLY / 200);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY + TOTAL_SUPPLY / 200);
+        assert_eq!(contract.get_circulating_supply().0, TOTAL_SUPPLY);
+        // Capped per call, then limited to the pool.
+        assert_eq!(contract.adjust_supply(100.into(), 50.into()).0, TOTAL_SUPPLY / 200);
+        assert_eq!(contract.get_stabilization_pool().0, 0);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the oracle can call this method")]
+    fn test_adjust_by_non_oracle() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.adjust_supply(100.into(), 101.into());
+    }
+}
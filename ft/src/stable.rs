@@ -0,0 +1,208 @@
+/*!
+Fixed-rate conversion between this token and a paired stable token.
+
+Redeeming burns this token and sends stable tokens out of the contract's reserve. Minting works the
+other way around: the stable token is sent to this contract with
+`ft_transfer_call(receiver_id: <this contract>, msg: "mint_from_stable")`, and tokens are only
+minted from `ft_on_transfer`, i.e. once the stable token has been received.
+*/
+use crate::*;
+use near_contract_standards::fungible_token::core::ext_ft_core;
+use near_sdk::is_promise_success;
+use near_sdk::serde::{Deserialize, Serialize};
+
+const GAS_FOR_STABLE_TRANSFER: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_ON_STABLE_REDEEMED: Gas = Gas(10_000_000_000_000);
+
+/// `ft_transfer_call` message that mints this token for the transferred stable tokens.
+pub const MINT_FROM_STABLE_MSG: &str = "mint_from_stable";
+/// `ft_transfer_call` message that tops up the reserve without minting anything.
+pub const FUND_RESERVE_MSG: &str = "fund_reserve";
+
+/// One unit of this token is worth `rate_numerator / rate_denominator` units of the stable token.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StablePeg {
+    pub stable_token_id: AccountId,
+    pub rate_numerator: U128,
+    pub rate_denominator: U128,
+}
+
+impl StablePeg {
+    fn stable_for(&self, amount: Balance) -> Balance {
+        amount.checked_mul(self.rate_numerator.0).expect("Conversion overflow")
+            / self.rate_denominator.0
+    }
+
+    fn tokens_for(&self, stable_amount: Balance) -> Balance {
+        stable_amount.checked_mul(self.rate_denominator.0).expect("Conversion overflow")
+            / self.rate_numerator.0
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Configures the paired stable token and the conversion rate. The stable token can't be
+    /// changed while the contract still holds a reserve of it.
+    pub fn set_stable_peg(
+        &mut self,
+        stable_token_id: AccountId,
+        rate_numerator: U128,
+        rate_denominator: U128,
+    ) {
+        self.assert_owner_action();
+        assert!(rate_numerator.0 > 0 && rate_denominator.0 > 0, "The rate should be positive");
+        if let Some(peg) = &self.stable_peg {
+            assert!(
+                peg.stable_token_id == stable_token_id || self.stable_reserve == 0,
+                "Can't change the stable token while holding a reserve"
+            );
+        }
+        self.stable_peg = Some(StablePeg { stable_token_id, rate_numerator, rate_denominator });
+    }
+
+    pub fn get_stable_peg(&self) -> Option<StablePeg> {
+        self.stable_peg.clone()
+    }
+
+    pub fn get_stable_reserve(&self) -> U128 {
+        self.stable_reserve.into()
+    }
+
+    /// Burns `amount` of the caller's tokens and sends the equivalent amount of the stable token
+    /// from the reserve. Panics if that's less than `min_out`. The burn is reverted if the stable
+    /// transfer fails, e.g. because the caller isn't registered with the stable token.
+    #[payable]
+    pub fn redeem_for_stable(&mut self, amount: U128, min_out: U128) -> Promise {
+        assert_one_yocto();
+        let peg = self.stable_peg.clone().expect("No stable token is configured");
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        let stable_amount = peg.stable_for(amount.0);
+        assert!(stable_amount > 0, "The amount is too small to redeem");
+        assert!(stable_amount >= min_out.0, "The stable amount is less than min_out");
+        assert!(stable_amount <= self.stable_reserve, "Not enough stable reserve");
+        self.internal_burn(&account_id, amount.0, "redeem_for_stable");
+        self.stable_reserve -= stable_amount;
+        ext_ft_core::ext(peg.stable_token_id)
+            .with_attached_deposit(1)
+            .with_static_gas(GAS_FOR_STABLE_TRANSFER)
+            .ft_transfer(account_id.clone(), stable_amount.into(), None)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_ON_STABLE_REDEEMED)
+                    .on_stable_redeemed(account_id, amount, stable_amount.into()),
+            )
+    }
+
+    /// Restores the burned tokens and the reserve if the stable transfer failed.
+    #[private]
+    pub fn on_stable_redeemed(
+        &mut self,
+        account_id: AccountId,
+        amount: U128,
+        stable_amount: U128,
+    ) -> bool {
+        if is_promise_success() {
+            return true;
+        }
+        self.stable_reserve += stable_amount.0;
+        self.internal_apply_demurrage(&account_id);
+        self.internal_settle_rewards(&account_id);
+        self.token.internal_deposit(&account_id, amount.0);
+        self.total_burned -= amount.0;
+        self.internal_record_balance(&account_id);
+        self.internal_record_total_supply();
+        FtMint { owner_id: &account_id, amount: &amount, memo: Some("refund") }.emit();
+        false
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_on_stable_transfer(
+        &mut self,
+        sender_id: AccountId,
+        stable_amount: Balance,
+        msg: &str,
+    ) -> PromiseOrValue<U128> {
+        match msg {
+            MINT_FROM_STABLE_MSG => {
+                let peg = self.stable_peg.as_ref().unwrap();
+                let amount = peg.tokens_for(stable_amount);
+                self.internal_mint(&sender_id, amount, MINT_FROM_STABLE_MSG);
+            }
+            FUND_RESERVE_MSG => {}
+            _ => panic!("Unsupported message"),
+        }
+        self.stable_reserve += stable_amount;
+        PromiseOrValue::Value(0.into())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        // 1 token = 2 stable units.
+        contract.set_stable_peg(accounts(3), 2.into(), 1.into());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_mint_from_stable() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.ft_on_transfer(accounts(1), 100.into(), MINT_FROM_STABLE_MSG.to_string());
+        assert_eq!(contract.get_stable_reserve().0, 100);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, TOTAL_SUPPLY + 50);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY + 50);
+    }
+
+    #[test]
+    fn test_redeem_for_stable() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.ft_on_transfer(accounts(2), 100.into(), FUND_RESERVE_MSG.to_string());
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(1).build());
+        contract.redeem_for_stable(30.into(), 60.into());
+        assert_eq!(contract.get_stable_reserve().0, 40);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY - 30);
+    }
+
+    #[test]
+    fn test_failed_redeem_is_restored() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.ft_on_transfer(accounts(2), 100.into(), FUND_RESERVE_MSG.to_string());
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(1).build());
+        contract.redeem_for_stable(30.into(), 60.into());
+        assert_eq!(contract.get_total_burned().0, 30);
+        testing_env!(
+            context.predecessor_account_id(accounts(0)).attached_deposit(0).build(),
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![near_sdk::PromiseResult::Failed]
+        );
+        assert!(!contract.on_stable_redeemed(accounts(1), 30.into(), 60.into()));
+        assert_eq!(contract.get_stable_reserve().0, 100);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+        assert_eq!(contract.get_total_burned().0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough stable reserve")]
+    fn test_redeem_more_than_reserve() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(1).build());
+        contract.redeem_for_stable(30.into(), 0.into());
+    }
+}
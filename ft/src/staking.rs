@@ -0,0 +1,300 @@
+/*!
+Staking with lock tiers. A stake is locked for the duration of the chosen tier and earns
+`base_apr * multiplier` over that duration. An account can hold several positions, each with its
+own tier and unlock time.
+
+Staked tokens are held by the contract account. Rewards are minted when the position is unstaked,
+after the lock expired, and are cut down to what the mint limits allow at that point, so the
+principal can always be withdrawn.
+
+Once the owner sets a penalty, locked stakes can also be withdrawn early with `emergency_unstake`.
+The withdrawn stake earns no reward and loses the penalty, which goes to the insurance fund if one
+is configured and is burned otherwise. Once the supply is frozen, a penalty that would be burned
+stays on the contract account instead.
+*/
+use crate::events::emit_event;
+use crate::math::{bps_of, mul_div, BPS_DENOMINATOR};
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+
+const SECONDS_PER_YEAR: u64 = 365 * 24 * 60 * 60;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LockTier {
+    /// Lock duration in seconds.
+    pub duration: u64,
+    /// Reward multiplier in basis points, e.g. 15000 for 1.5x.
+    pub multiplier_bps: u32,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StakePosition {
+    pub id: u64,
+    pub amount: U128,
+    /// Effective APR of the position in basis points, fixed when staking.
+    pub apr_bps: u32,
+    /// Timestamp (in seconds) at which the stake was locked.
+    pub start: u64,
+    /// Timestamp (in seconds) from which the stake can be withdrawn.
+    pub unlock_at: u64,
+}
+
+impl StakePosition {
+    /// Reward earned over the whole lock.
+    fn reward(&self) -> Balance {
+        let duration = (self.unlock_at - self.start) as Balance;
+        mul_div(
+            mul_div(self.amount.0, self.apr_bps as Balance, BPS_DENOMINATOR as Balance),
+            duration,
+            SECONDS_PER_YEAR as Balance,
+        )
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Replaces the lock tiers. Existing positions keep the terms they were created with.
+    pub fn set_lock_tiers(&mut self, tiers: Vec<LockTier>) {
+        self.assert_owner_action();
+        assert!(tiers.len() <= u8::MAX as usize, "Too many tiers");
+        for tier in &tiers {
+            assert!(tier.duration > 0, "The lock duration should be a positive number");
+        }
+        self.lock_tiers = tiers;
+    }
+
+    pub fn get_lock_tiers(&self) -> Vec<LockTier> {
+        self.lock_tiers.clone()
+    }
+
+    pub fn set_staking_apr(&mut self, base_apr_bps: u16) {
+        self.assert_owner_action();
+        self.base_apr_bps = base_apr_bps;
+    }
+
+    pub fn get_staking_apr(&self) -> u16 {
+        self.base_apr_bps
+    }
+
+    /// Locks `amount` of the caller's tokens for the duration of `duration_tier`. Attached
+    /// deposit must cover the added storage, the rest is refunded. Returns the position ID.
+    #[payable]
+    pub fn stake_locked(&mut self, amount: U128, duration_tier: u8) -> u64 {
+        self.assert_not_paused();
+        assert!(amount.0 > 0, "The amount should be a positive number");
+        let tier = self.lock_tiers.get(duration_tier as usize).cloned().expect("Unknown tier");
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        let initial_storage_usage = env::storage_usage();
+        self.internal_register_contract_account();
+        self.internal_move_balance(
+            &account_id,
+            &env::current_account_id(),
+            amount.0,
+            Some("stake".to_string()),
+        );
+        self.total_staked += amount.0;
+        let now = current_timestamp_sec();
+        let id = self.next_stake_id;
+        self.next_stake_id += 1;
+        let apr_bps =
+            (self.base_apr_bps as u64 * tier.multiplier_bps as u64 / BPS_DENOMINATOR as u64) as u32;
+        let mut positions = self.stakes.get(&account_id).unwrap_or_default();
+        positions.push(StakePosition {
+            id,
+            amount,
+            apr_bps,
+            start: now,
+            unlock_at: now + tier.duration,
+        });
+        self.stakes.insert(&account_id, &positions);
+        refund_deposit(initial_storage_usage);
+        id
+    }
+
+    /// Withdraws an unlocked position, with its reward paid to `to` or to the caller when `None`.
+    /// Returns the reward paid.
+    pub fn unstake(&mut self, position_id: u64, to: Option<AccountId>) -> U128 {
+        self.assert_not_paused();
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        let to = self.internal_reward_destination(&account_id, to);
+        let mut positions = self.stakes.get(&account_id).expect("No stakes");
+        let index =
+            positions.iter().position(|p| p.id == position_id).expect("No such stake position");
+        assert!(current_timestamp_sec() >= positions[index].unlock_at, "The stake is still locked");
+        let position = positions.swap_remove(index);
+        if positions.is_empty() {
+            self.stakes.remove(&account_id);
+        } else {
+            self.stakes.insert(&account_id, &positions);
+        }
+        self.total_staked -= position.amount.0;
+        self.internal_move_balance(
+            &env::current_account_id(),
+            &account_id,
+            position.amount.0,
+            Some("unstake".to_string()),
+        );
+        let reward = if self.supply_frozen {
+            0
+        } else {
+            position.reward().min(self.internal_mint_headroom())
+        };
+        if reward > 0 {
+            self.internal_mint(&to, reward, "staking reward");
+        }
+        (reward + self.internal_mint_hold_boost(&account_id, &to, reward)).into()
+    }
+
+    /// Sets the share of the stake lost on an emergency unstake, or disables emergency unstakes
+    /// when `None`.
+    pub fn set_emergency_unstake_penalty(&mut self, penalty_bps: Option<u16>) {
+        self.assert_owner_action();
+        if let Some(penalty_bps) = penalty_bps {
+            assert!(penalty_bps <= BPS_DENOMINATOR, "The penalty can't exceed 100%");
+        }
+        self.emergency_unstake_penalty_bps = penalty_bps;
+    }
+
+    pub fn get_emergency_unstake_penalty(&self) -> Option<u16> {
+        self.emergency_unstake_penalty_bps
+    }
+
+    /// Withdraws `amount` out of the caller's locked positions before they unlock, starting with
+    /// the one that unlocks first, minus the penalty. The withdrawn part forfeits its reward, the
+    /// rest of the positions keep their terms. Returns the amount paid out.
+    pub fn emergency_unstake(&mut self, amount: U128) -> U128 {
+        self.assert_not_paused();
+        let penalty_bps =
+            self.emergency_unstake_penalty_bps.expect("Emergency unstakes are disabled");
+        assert!(amount.0 > 0, "The amount should be a positive number");
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        let now = current_timestamp_sec();
+        let mut positions = self.stakes.get(&account_id).expect("No stakes");
+        positions.sort_by_key(|position| position.unlock_at);
+        let mut remaining = amount.0;
+        for position in positions.iter_mut().filter(|position| position.unlock_at > now) {
+            let taken = remaining.min(position.amount.0);
+            position.amount = (position.amount.0 - taken).into();
+            remaining -= taken;
+        }
+        assert_eq!(remaining, 0, "Not enough locked stake");
+        positions.retain(|position| position.amount.0 > 0);
+        if positions.is_empty() {
+            self.stakes.remove(&account_id);
+        } else {
+            self.stakes.insert(&account_id, &positions);
+        }
+        self.total_staked -= amount.0;
+        let penalty = bps_of(amount.0, penalty_bps);
+        let current_account_id = env::current_account_id();
+        if penalty < amount.0 {
+            self.internal_move_balance(
+                &current_account_id,
+                &account_id,
+                amount.0 - penalty,
+                Some("emergency unstake".to_string()),
+            );
+        }
+        if penalty > 0 {
+            if self.insurance.is_some() {
+                self.insurance_fund += penalty;
+            } else if !self.supply_frozen {
+                self.internal_burn(&current_account_id, penalty, "emergency unstake penalty");
+            }
+        }
+        emit_event(
+            "emergency_unstake",
+            json!({
+                "account_id": account_id,
+                "amount": amount,
+                "penalty": U128(penalty),
+            }),
+        );
+        (amount.0 - penalty).into()
+    }
+
+    pub fn stake_positions_of(&self, account_id: AccountId) -> Vec<StakePosition> {
+        self.stakes.get(&account_id).unwrap_or_default()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_staking_apr(1_000);
+        contract.set_lock_tiers(vec![
+            LockTier { duration: 73 * DAY, multiplier_bps: 10_000 },
+            LockTier { duration: 365 * DAY, multiplier_bps: 20_000 },
+        ]);
+        testing_env!(context.attached_deposit(ONE_NEAR / 10).build());
+        contract.stake_locked(1_000_000.into(), 0);
+        contract.stake_locked(1_000_000.into(), 1);
+        (context, contract)
+    }
+
+    #[test]
+    fn test_stake_and_unstake_positions() {
+        let (mut context, mut contract) = setup();
+        let positions = contract.stake_positions_of(accounts(1));
+        assert_eq!(positions.len(), 2);
+        assert_eq!(positions[1].unlock_at, 365 * DAY);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, TOTAL_SUPPLY - 2_000_000);
+
+        testing_env!(context.attached_deposit(0).block_timestamp(365 * DAY * NANOS).build());
+        assert_eq!(contract.unstake(0, None).0, 20_000);
+        assert_eq!(contract.unstake(1, None).0, 200_000);
+        assert!(contract.stake_positions_of(accounts(1)).is_empty());
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, TOTAL_SUPPLY + 220_000);
+    }
+
+    #[test]
+    fn test_emergency_unstake() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.attached_deposit(0).build());
+        contract.set_emergency_unstake_penalty(Some(1_000));
+        assert_eq!(contract.emergency_unstake(1_500_000.into()).0, 1_350_000);
+        let positions = contract.stake_positions_of(accounts(1));
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].amount.0, 500_000);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY - 150_000);
+
+        testing_env!(context.block_timestamp(365 * DAY * NANOS).build());
+        assert_eq!(contract.unstake(1, None).0, 100_000);
+    }
+
+    #[test]
+    fn test_emergency_unstake_while_frozen() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.attached_deposit(0).build());
+        contract.set_emergency_unstake_penalty(Some(1_000));
+        contract.freeze_supply();
+        assert_eq!(contract.emergency_unstake(1_000_000.into()).0, 900_000);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+        assert_eq!(contract.ft_balance_of(accounts(0)).0, 1_100_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "The stake is still locked")]
+    fn test_unstake_before_unlock() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.attached_deposit(0).block_timestamp(100 * DAY * NANOS).build());
+        contract.unstake(1, None);
+    }
+}
@@ -0,0 +1,168 @@
+/*!
+Registration paid in the token instead of NEAR, so new users can be onboarded without holding any
+NEAR. A registered account pays a fee in tokens to register `account_id`, and the contract covers
+the actual storage from a NEAR reserve funded in advance.
+
+The fee is the registration cost in NEAR, the same `storage_balance_bounds` asks for, converted at
+the configured price, and is either burned or sent to the treasury. The reserve is debited by the
+registration cost and credited back the same amount when a token-funded account unregisters, in
+which case the storage NEAR goes back to the reserve rather than to the account.
+*/
+use crate::math::mul_div;
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::ONE_NEAR;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageTokenFee {
+    /// Tokens charged per 1 NEAR of storage cost.
+    pub price_per_near: U128,
+    /// Account receiving the fee. The fee is burned if not set.
+    pub treasury_id: Option<AccountId>,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Enables registration paid in tokens, or disables it when `fee` is `None`.
+    pub fn set_storage_token_fee(&mut self, fee: Option<StorageTokenFee>) {
+        self.assert_owner_action();
+        if let Some(fee) = fee.as_ref() {
+            assert!(fee.price_per_near.0 > 0, "The price should be a positive number");
+            if let Some(treasury_id) = fee.treasury_id.as_ref() {
+                self.token.internal_unwrap_balance_of(treasury_id);
+            }
+        }
+        self.storage_token_fee = fee;
+    }
+
+    pub fn get_storage_token_fee(&self) -> Option<StorageTokenFee> {
+        self.storage_token_fee.clone()
+    }
+
+    /// Adds the attached NEAR to the reserve used for registrations paid in tokens.
+    #[payable]
+    pub fn fund_storage_reserve(&mut self) {
+        let amount = env::attached_deposit();
+        assert!(amount > 0, "Attach NEAR to fund the reserve");
+        self.storage_reserve += amount;
+    }
+
+    pub fn withdraw_storage_reserve(&mut self, amount: U128) -> Promise {
+        self.assert_owner_action();
+        assert!(amount.0 <= self.storage_reserve, "Not enough NEAR in the storage reserve");
+        self.storage_reserve -= amount.0;
+        Promise::new(self.owner_id.clone()).transfer(amount.0)
+    }
+
+    pub fn get_storage_reserve(&self) -> U128 {
+        self.storage_reserve.into()
+    }
+
+    /// Registers `account_id`, paying for its storage with the caller's tokens. `token_amount` is
+    /// the most the caller is willing to pay. Returns the fee actually charged.
+    #[payable]
+    pub fn storage_deposit_in_token(&mut self, account_id: AccountId, token_amount: U128) -> U128 {
+        assert_one_yocto();
+        let fee = self.storage_token_fee.clone().expect("Registration in tokens is disabled");
+        let payer_id = env::predecessor_account_id();
+        self.internal_record_activity(&payer_id);
+        assert!(
+            self.token.accounts.get(&account_id).is_none(),
+            "The account is already registered"
+        );
+        self.internal_register_account(&account_id);
+        self.token_funded_accounts.insert(&account_id);
+        let storage_cost = self.internal_registration_cost(&account_id);
+        assert!(storage_cost <= self.storage_reserve, "Not enough NEAR in the storage reserve");
+        self.storage_reserve -= storage_cost;
+        self.internal_on_account_registered(&account_id);
+
+        let token_fee = mul_div(storage_cost, fee.price_per_near.0, ONE_NEAR);
+        assert!(token_fee <= token_amount.0, "The fee is {} tokens", token_fee);
+        if token_fee > 0 {
+            match fee.treasury_id {
+                Some(treasury_id) => self.internal_move_balance(
+                    &payer_id,
+                    &treasury_id,
+                    token_fee,
+                    Some("storage fee".to_string()),
+                ),
+                None => self.internal_burn(&payer_id, token_fee, "storage fee"),
+            }
+        }
+        token_fee.into()
+    }
+}
+
+impl Contract {
+    /// Unregisters a token-funded account, returning the released storage NEAR to the reserve.
+    pub(crate) fn internal_unregister_token_funded(&mut self, force: Option<bool>) -> bool {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let balance = self.token.internal_unwrap_balance_of(&account_id);
+        assert!(
+            balance == 0 || force.unwrap_or(false),
+            "Can't unregister the account with the positive balance without force"
+        );
+        self.storage_reserve += self.internal_registration_cost(&account_id);
+        self.internal_settle_rewards(&account_id);
+        self.token.accounts.remove(&account_id);
+        self.token.total_supply -= balance;
+        self.token_funded_accounts.remove(&account_id);
+        self.on_account_closed(account_id.clone(), balance);
+        self.internal_record_change(format!("account_closed:{}", account_id));
+        true
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    const PRICE_PER_NEAR: Balance = 1_000_000;
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_storage_token_fee(Some(StorageTokenFee {
+            price_per_near: PRICE_PER_NEAR.into(),
+            treasury_id: None,
+        }));
+        testing_env!(context.attached_deposit(ONE_NEAR).build());
+        contract.fund_storage_reserve();
+        testing_env!(context.attached_deposit(1).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_register_and_unregister_in_token() {
+        let (mut context, mut contract) = setup();
+        let fee = contract.storage_deposit_in_token(accounts(2), PRICE_PER_NEAR.into()).0;
+        assert!(fee > 0);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY - fee);
+        assert!(contract.storage_balance_of(accounts(2)).is_some());
+        assert_eq!(
+            contract.get_storage_reserve().0,
+            ONE_NEAR - contract.estimate_registration_cost(accounts(2)).0
+        );
+        contract.ft_transfer(accounts(2), 10.into(), None);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.ft_transfer(accounts(1), 10.into(), None);
+        assert!(contract.storage_unregister(None));
+        assert_eq!(contract.get_storage_reserve().0, ONE_NEAR);
+    }
+
+    #[test]
+    #[should_panic(expected = "The fee is")]
+    fn test_fee_above_max() {
+        let (_, mut contract) = setup();
+        contract.storage_deposit_in_token(accounts(2), 1.into());
+    }
+}
@@ -0,0 +1,152 @@
+/*!
+Streaming allowances for subscription-style pulls. Instead of a fixed amount, an owner approves a
+spender for `amount_per_period` in every period of `period_sec` seconds, counted from the approval,
+and `ft_transfer_from` refuses pulls above what is left of the current period. Unspent amounts
+don't carry over to the next period, which bounds the owner's exposure over any stretch of time.
+
+A streaming allowance replaces the plain allowance of the spender and vice versa. The owner's
+allowance cap limits the amount per period.
+*/
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StreamingAllowance {
+    pub amount_per_period: U128,
+    pub period_sec: u64,
+    /// Start of the current period, in seconds.
+    pub period_start: u64,
+    /// Amount spent in the current period.
+    pub spent: U128,
+}
+
+impl StreamingAllowance {
+    /// Rolls the allowance over to the period containing `now`.
+    fn at(mut self, now: u64) -> Self {
+        if now >= self.period_start + self.period_sec {
+            let elapsed_periods = (now - self.period_start) / self.period_sec;
+            self.period_start += elapsed_periods * self.period_sec;
+            self.spent = U128(0);
+        }
+        self
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Lets `spender_id` transfer up to `amount_per_period` out of the caller's account in every
+    /// period of `period_sec` seconds, replacing any previous allowance. Zero revokes it. Requires
+    /// at least 1 yoctoNEAR attached, which must also cover the added storage, the rest is
+    /// refunded.
+    #[payable]
+    pub fn ft_approve_streaming(
+        &mut self,
+        spender_id: AccountId,
+        amount_per_period: U128,
+        period_sec: u64,
+    ) {
+        assert_at_least_one_yocto();
+        assert!(period_sec > 0, "The period should be a positive number");
+        let owner_id = env::predecessor_account_id();
+        self.internal_record_activity(&owner_id);
+        self.token.internal_unwrap_balance_of(&owner_id);
+        let initial_storage_usage = env::storage_usage();
+        self.internal_set_allowance(&owner_id, spender_id.clone(), 0);
+        let cap = self.internal_max_allowance(&owner_id);
+        assert!(amount_per_period.0 <= cap, "The allowance exceeds the cap of {}", cap);
+        if amount_per_period.0 > 0 {
+            let allowance = StreamingAllowance {
+                amount_per_period,
+                period_sec,
+                period_start: current_timestamp_sec(),
+                spent: U128(0),
+            };
+            self.streaming_allowances.insert(&(owner_id, spender_id), &allowance);
+        }
+        refund_deposit(initial_storage_usage);
+    }
+
+    pub fn ft_streaming_allowance(
+        &self,
+        owner_id: AccountId,
+        spender_id: AccountId,
+    ) -> Option<StreamingAllowance> {
+        self.streaming_allowances
+            .get(&(owner_id, spender_id))
+            .map(|allowance| allowance.at(current_timestamp_sec()))
+    }
+
+    /// Amount `spender_id` can still transfer out of `owner_id`'s account in the current period.
+    pub fn ft_streaming_allowance_remaining(
+        &self,
+        owner_id: AccountId,
+        spender_id: AccountId,
+    ) -> U128 {
+        let cap = self.internal_max_allowance(&owner_id);
+        self.ft_streaming_allowance(owner_id, spender_id)
+            .map_or(0, |allowance| {
+                allowance.amount_per_period.0.min(cap).saturating_sub(allowance.spent.0)
+            })
+            .into()
+    }
+}
+
+impl Contract {
+    /// Spends `amount` of the streaming allowance `(owner_id, spender_id)` in the current period.
+    pub(crate) fn internal_spend_streaming_allowance(
+        &mut self,
+        key: &(AccountId, AccountId),
+        allowance: StreamingAllowance,
+        amount: Balance,
+    ) {
+        let mut allowance = allowance.at(current_timestamp_sec());
+        let limit = allowance.amount_per_period.0.min(self.internal_max_allowance(&key.0));
+        assert!(
+            amount <= limit.saturating_sub(allowance.spent.0),
+            "The allowance for this period is too low"
+        );
+        allowance.spent = U128(allowance.spent.0 + amount);
+        self.streaming_allowances.insert(key, &allowance);
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(ONE_NEAR / 100).build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.ft_approve_streaming(accounts(2), 10.into(), DAY);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.storage_deposit(None, None);
+        (context, contract)
+    }
+
+    #[test]
+    fn test_streaming_allowance_renews_every_period() {
+        let (mut context, mut contract) = setup();
+        contract.ft_transfer_from(accounts(1), accounts(2), 7.into(), None);
+        assert_eq!(contract.ft_streaming_allowance_remaining(accounts(1), accounts(2)).0, 3);
+        testing_env!(context.block_timestamp(DAY * NANOS).build());
+        assert_eq!(contract.ft_streaming_allowance_remaining(accounts(1), accounts(2)).0, 10);
+        contract.ft_transfer_from(accounts(1), accounts(2), 10.into(), None);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 17);
+    }
+
+    #[test]
+    #[should_panic(expected = "The allowance for this period is too low")]
+    fn test_streaming_allowance_period_cap() {
+        let (_, mut contract) = setup();
+        contract.ft_transfer_from(accounts(1), accounts(2), 7.into(), None);
+        contract.ft_transfer_from(accounts(1), accounts(2), 4.into(), None);
+    }
+}
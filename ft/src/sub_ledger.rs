@@ -0,0 +1,174 @@
+/*!
+Sub-ledger for custodians tracking customer balances under a single account. The account moves
+tokens from its own balance into named sub-balances with `sub_deposit`, between them with
+`sub_transfer`, and back with `sub_withdraw`. Sub-balances never leave the account: they're
+locked like time-locked tokens, so the account's balance never falls below the sum of its
+sub-balances, and only the unallocated part above it can be sent. Demurrage only decays the
+unallocated part, `ft_transfer_call` refunds stop short of the sub-balances, and forced moves like
+clawbacks are refused like any other transfer of locked tokens until the account withdraws them.
+
+Storage freed by emptying a sub-balance is refunded to the account. An account can't unregister
+while it has sub-balances.
+*/
+use crate::*;
+
+/// Longest accepted sub-balance name.
+pub const MAX_SUB_ID_LEN: usize = 64;
+
+#[near_bindgen]
+impl Contract {
+    /// Moves `amount` of the caller's unallocated balance to its sub-balance `sub_id`. Attached
+    /// deposit must cover the added storage, the rest is refunded.
+    #[payable]
+    pub fn sub_deposit(&mut self, sub_id: String, amount: U128) {
+        assert_valid_sub_id(&sub_id);
+        assert!(amount.0 > 0, "The amount should be a positive number");
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        self.token.internal_unwrap_balance_of(&account_id);
+        self.internal_apply_demurrage(&account_id);
+        let unlocked = self.internal_unlocked_balance(&account_id);
+        assert!(amount.0 <= unlocked, "The amount exceeds the unlocked balance of {}", unlocked);
+        let initial_storage_usage = env::storage_usage();
+        self.internal_credit_sub_balance(&account_id, sub_id, amount.0);
+        let allocated = self.internal_sub_allocated(&account_id);
+        self.sub_allocated.insert(&account_id, &(allocated + amount.0));
+        refund_deposit(initial_storage_usage);
+    }
+
+    /// Moves `amount` from the caller's sub-balance `sub_id` back to its unallocated balance.
+    #[payable]
+    pub fn sub_withdraw(&mut self, sub_id: String, amount: U128) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        let initial_storage_usage = env::storage_usage();
+        self.internal_debit_sub_balance(&account_id, sub_id, amount.0);
+        let allocated = self.internal_sub_allocated(&account_id) - amount.0;
+        if allocated == 0 {
+            self.sub_allocated.remove(&account_id);
+        } else {
+            self.sub_allocated.insert(&account_id, &allocated);
+        }
+        refund_freed_storage(account_id, initial_storage_usage);
+    }
+
+    /// Moves `amount` between two sub-balances of the caller. Attached deposit must cover the
+    /// added storage, the rest is refunded.
+    #[payable]
+    pub fn sub_transfer(&mut self, from_sub_id: String, to_sub_id: String, amount: U128) {
+        assert_valid_sub_id(&to_sub_id);
+        assert_ne!(from_sub_id, to_sub_id, "The sub-balances should be different");
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        let initial_storage_usage = env::storage_usage();
+        self.internal_debit_sub_balance(&account_id, from_sub_id, amount.0);
+        self.internal_credit_sub_balance(&account_id, to_sub_id, amount.0);
+        refund_deposit(initial_storage_usage);
+        refund_freed_storage(account_id, initial_storage_usage);
+    }
+
+    pub fn sub_balance_of(&self, account_id: AccountId, sub_id: String) -> U128 {
+        self.sub_balances.get(&(account_id, sub_id)).unwrap_or(0).into()
+    }
+
+    /// Sum of the sub-balances of `account_id`.
+    pub fn sub_allocated_of(&self, account_id: AccountId) -> U128 {
+        self.internal_sub_allocated(&account_id).into()
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_sub_allocated(&self, account_id: &AccountId) -> Balance {
+        self.sub_allocated.get(account_id).unwrap_or(0)
+    }
+
+    fn internal_credit_sub_balance(
+        &mut self,
+        account_id: &AccountId,
+        sub_id: String,
+        amount: Balance,
+    ) {
+        let key = (account_id.clone(), sub_id);
+        let balance = self.sub_balances.get(&key).unwrap_or(0);
+        self.sub_balances.insert(&key, &(balance + amount));
+    }
+
+    fn internal_debit_sub_balance(
+        &mut self,
+        account_id: &AccountId,
+        sub_id: String,
+        amount: Balance,
+    ) {
+        assert!(amount > 0, "The amount should be a positive number");
+        let key = (account_id.clone(), sub_id);
+        let balance = self.sub_balances.get(&key).unwrap_or(0);
+        assert!(amount <= balance, "The sub-balance is too low");
+        if balance == amount {
+            self.sub_balances.remove(&key);
+        } else {
+            self.sub_balances.insert(&key, &(balance - amount));
+        }
+    }
+}
+
+/// Refunds `account_id` the storage freed since `initial_storage_usage`, if any.
+fn refund_freed_storage(account_id: AccountId, initial_storage_usage: StorageUsage) {
+    let freed = initial_storage_usage.saturating_sub(env::storage_usage());
+    if freed > 0 {
+        Promise::new(account_id).transfer(freed as Balance * env::storage_byte_cost());
+    }
+}
+
+fn assert_valid_sub_id(sub_id: &str) {
+    assert!(
+        !sub_id.is_empty() && sub_id.len() <= MAX_SUB_ID_LEN,
+        "The sub-balance name should be between 1 and {} bytes long",
+        MAX_SUB_ID_LEN
+    );
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(ONE_NEAR / 100).build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.sub_deposit("alice".to_string(), 100.into());
+        contract.sub_deposit("carol".to_string(), 50.into());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_sub_ledger() {
+        let (mut context, mut contract) = setup();
+        contract.sub_transfer("alice".to_string(), "carol".to_string(), 30.into());
+        assert_eq!(contract.sub_balance_of(accounts(1), "alice".to_string()).0, 70);
+        assert_eq!(contract.sub_balance_of(accounts(1), "carol".to_string()).0, 80);
+        testing_env!(context.attached_deposit(1).build());
+        contract.sub_withdraw("carol".to_string(), 80.into());
+        assert_eq!(contract.sub_balance_of(accounts(1), "carol".to_string()).0, 0);
+        assert_eq!(contract.sub_allocated_of(accounts(1)).0, 70);
+        assert_eq!(near_sdk::test_utils::get_created_receipts().len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "The amount exceeds the unlocked balance")]
+    fn test_sub_balances_are_locked() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), (TOTAL_SUPPLY - 150).into(), None);
+        contract.ft_transfer(accounts(2), 1.into(), None);
+    }
+}
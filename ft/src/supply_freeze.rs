@@ -0,0 +1,77 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// Permanently prevents any further mint or burn. Transfers keep working. There is no way to
+    /// undo this.
+    pub fn freeze_supply(&mut self) {
+        self.assert_owner();
+        assert!(!self.supply_frozen, "The supply is already frozen");
+        self.supply_frozen = true;
+        log!("The supply is frozen at {}", self.token.total_supply);
+    }
+
+    pub fn is_supply_frozen(&self) -> bool {
+        self.supply_frozen
+    }
+}
+
+impl Contract {
+    pub(crate) fn assert_supply_not_frozen(&self) {
+        assert!(!self.supply_frozen, "The supply is frozen");
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.freeze_supply();
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        (context, contract)
+    }
+
+    #[test]
+    fn test_transfer_after_freeze() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), 10.into(), None);
+        assert!(contract.is_supply_frozen());
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 10);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+    }
+
+    #[test]
+    #[should_panic(expected = "The supply is frozen")]
+    fn test_mint_after_freeze() {
+        let (_, mut contract) = setup();
+        contract.internal_mint(&accounts(2), 10, "mint");
+    }
+
+    #[test]
+    #[should_panic(expected = "The supply is frozen")]
+    fn test_burn_after_freeze() {
+        let (_, mut contract) = setup();
+        contract.internal_burn(&accounts(1), 10, "burn");
+    }
+
+    #[test]
+    #[should_panic(expected = "The supply is already frozen")]
+    fn test_freeze_twice() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.attached_deposit(0).predecessor_account_id(accounts(1)).build());
+        contract.freeze_supply();
+    }
+}
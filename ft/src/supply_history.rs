@@ -0,0 +1,104 @@
+/*!
+Checkpoints of the total supply for analytics. Every change of the total supply, i.e. mints, burns
+and accounts closed with a balance, appends the new supply with its timestamp, so dashboards can
+chart it without replaying the events. Once `supply_history_capacity` checkpoints are kept, every
+new one evicts the oldest.
+*/
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+
+pub const DEFAULT_SUPPLY_HISTORY_CAPACITY: u64 = 100;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SupplyCheckpoint {
+    /// Timestamp of the change in seconds.
+    pub timestamp: u64,
+    pub total_supply: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the number of checkpoints kept. Zero stops recording. Lowering it evicts the extra
+    /// checkpoints on the next change of the supply.
+    pub fn set_supply_history_capacity(&mut self, capacity: u64) {
+        self.assert_owner_action();
+        self.supply_history_capacity = capacity;
+    }
+
+    pub fn get_supply_history_capacity(&self) -> u64 {
+        self.supply_history_capacity
+    }
+
+    /// Returns the kept checkpoints of the total supply, oldest first.
+    pub fn total_supply_history(
+        &self,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<SupplyCheckpoint> {
+        let start = self.supply_history_first + from_index.unwrap_or(0);
+        let next = self.supply_history_next;
+        let end = limit.map_or(next, |limit| next.min(start + limit));
+        (start..end).filter_map(|seq| self.supply_history.get(&seq)).collect()
+    }
+}
+
+impl Contract {
+    /// Appends a checkpoint if the total supply changed since the last one. Must be called after
+    /// every change to the total supply.
+    pub(crate) fn internal_record_total_supply(&mut self) {
+        if self.supply_history_capacity == 0 {
+            return;
+        }
+        let total_supply = self.token.total_supply;
+        if self.supply_history_next > self.supply_history_first {
+            let last = self.supply_history.get(&(self.supply_history_next - 1));
+            if matches!(last, Some(last) if last.total_supply.0 == total_supply) {
+                return;
+            }
+        }
+        let checkpoint = SupplyCheckpoint {
+            timestamp: current_timestamp_sec(),
+            total_supply: total_supply.into(),
+        };
+        self.supply_history.insert(&self.supply_history_next, &checkpoint);
+        self.supply_history_next += 1;
+        while self.supply_history_next - self.supply_history_first > self.supply_history_capacity {
+            self.supply_history.remove(&self.supply_history_first);
+            self.supply_history_first += 1;
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_supply_changes_are_recorded_and_evicted() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_supply_history_capacity(2);
+        for (second, amount) in [(1, 10), (2, 20)] {
+            testing_env!(context.block_timestamp(second * NANOS).build());
+            contract.internal_mint(&accounts(1), amount, "test");
+        }
+        testing_env!(context.block_timestamp(3 * NANOS).build());
+        contract.internal_burn(&accounts(1), 30, "test");
+        // Transfers don't change the supply.
+        contract.internal_record_total_supply();
+        assert_eq!(
+            contract.total_supply_history(None, None),
+            vec![
+                SupplyCheckpoint { timestamp: 2, total_supply: (TOTAL_SUPPLY + 30).into() },
+                SupplyCheckpoint { timestamp: 3, total_supply: TOTAL_SUPPLY.into() },
+            ]
+        );
+        assert_eq!(contract.total_supply_history(Some(1), Some(5)).len(), 1);
+    }
+}
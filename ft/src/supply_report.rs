@@ -0,0 +1,71 @@
+/*!
+Periodic supply reports for indexers. Once the owner sets an interval, anyone can call
+`emit_supply_report` at most once per interval to emit a `supply_report` event with the total and
+circulating supply, the number of holders and the total burned so far, so indexers get a regular
+heartbeat of these metrics without recomputing them from raw events.
+*/
+use crate::events::emit_event;
+use crate::*;
+use near_sdk::serde_json::json;
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the minimum time (in seconds) between reports, or disables them when `None`.
+    pub fn set_supply_report_interval(&mut self, interval: Option<u64>) {
+        self.assert_owner_action();
+        self.supply_report_interval = interval;
+    }
+
+    pub fn get_supply_report_interval(&self) -> Option<u64> {
+        self.supply_report_interval
+    }
+
+    /// Total amount burned since deployment, including the balances of force-closed accounts.
+    pub fn get_total_burned(&self) -> U128 {
+        self.total_burned.into()
+    }
+
+    /// Emits a supply report, unless the last one is more recent than the interval.
+    pub fn emit_supply_report(&mut self) {
+        let interval = self.supply_report_interval.expect("Supply reports are disabled");
+        let now = current_timestamp_sec();
+        assert!(
+            self.last_report_ts == 0 || now >= self.last_report_ts + interval,
+            "The next report can be emitted at {}",
+            self.last_report_ts + interval
+        );
+        self.last_report_ts = now;
+        emit_event(
+            "supply_report",
+            json!({
+                "timestamp": now,
+                "total_supply": U128(self.token.total_supply),
+                "circulating_supply": self.get_circulating_supply(),
+                "holders": self.holders.len(),
+                "total_burned": U128(self.total_burned),
+            }),
+        );
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, get_logs};
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    #[test]
+    #[should_panic(expected = "The next report can be emitted at 1100")]
+    fn test_report_interval() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.block_timestamp(1_000 * NANOS).build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_supply_report_interval(Some(100));
+        contract.emit_supply_report();
+        assert!(get_logs().last().unwrap().contains("\"event\":\"supply_report\""));
+        testing_env!(context.block_timestamp(1_099 * NANOS).build());
+        contract.emit_supply_report();
+    }
+}
@@ -0,0 +1,100 @@
+/*!
+Balance tiers. The owner sets ascending thresholds, and an account's tier is the number of
+thresholds its balance reaches, so tier 0 is below the first one. The tier is stored and updated
+after every balance change, transfers, mints and burns alike, and a `tier_changed` event is
+emitted whenever it moves, so external systems can react without polling.
+
+Changing the thresholds doesn't touch the stored tiers, each account moves to its new tier on its
+next balance change. The contract account has no tier.
+*/
+use crate::events::emit_event;
+use crate::*;
+use near_sdk::serde_json::json;
+
+/// Most thresholds, i.e. the highest tier.
+pub const MAX_TIERS: usize = 10;
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the tier thresholds, in ascending order. An empty list disables tiers.
+    pub fn set_tier_thresholds(&mut self, thresholds: Vec<U128>) {
+        self.assert_owner_action();
+        assert!(thresholds.len() <= MAX_TIERS, "There can be at most {} tiers", MAX_TIERS);
+        assert!(
+            thresholds.windows(2).all(|pair| pair[0].0 < pair[1].0),
+            "The thresholds should be strictly ascending"
+        );
+        self.tier_thresholds = thresholds.into_iter().map(|threshold| threshold.0).collect();
+    }
+
+    pub fn get_tier_thresholds(&self) -> Vec<U128> {
+        self.tier_thresholds.iter().map(|threshold| U128(*threshold)).collect()
+    }
+
+    pub fn tier_of(&self, account_id: AccountId) -> u8 {
+        self.tiers.get(&account_id).unwrap_or(0)
+    }
+}
+
+impl Contract {
+    /// Updates the stored tier of `account_id` after a balance change.
+    pub(crate) fn internal_update_tier(&mut self, account_id: &AccountId) {
+        if account_id == &env::current_account_id() {
+            return;
+        }
+        let balance = self.token.accounts.get(account_id).unwrap_or(0);
+        let tier = self.tier_thresholds.iter().filter(|threshold| balance >= **threshold).count();
+        let tier = tier as u8;
+        let previous_tier = self.tier_of(account_id.clone());
+        if tier == previous_tier {
+            return;
+        }
+        if tier == 0 {
+            self.tiers.remove(account_id);
+        } else {
+            self.tiers.insert(account_id, &tier);
+        }
+        emit_event(
+            "tier_changed",
+            json!({ "account_id": account_id, "old_tier": previous_tier, "new_tier": tier }),
+        );
+    }
+
+    pub(crate) fn measure_tier_storage_usage(&mut self) {
+        let initial_storage_usage = env::storage_usage();
+        let tmp_account_id = AccountId::new_unchecked("a".repeat(64));
+        self.tiers.insert(&tmp_account_id, &0);
+        self.token.account_storage_usage += env::storage_usage() - initial_storage_usage;
+        self.tiers.remove(&tmp_account_id);
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, get_logs};
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_tiers_follow_balance() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_tier_thresholds(vec![100.into(), 1_000.into()]);
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), 99.into(), None);
+        assert_eq!(contract.tier_of(accounts(2)), 0);
+        contract.ft_transfer(accounts(2), 1_000.into(), None);
+        assert_eq!(contract.tier_of(accounts(2)), 2);
+        assert!(get_logs().iter().any(|log| log.contains("\"new_tier\":2")));
+        contract.internal_burn(&accounts(2), 1_000, "burn");
+        assert_eq!(contract.tier_of(accounts(2)), 0);
+    }
+}
@@ -0,0 +1,170 @@
+/*!
+Time locks on balances. A locked amount stays on the account and keeps earning rewards, but can't
+be transferred, burned or otherwise moved out until its unlock time. An account can have several
+locks, which together form a graduated unlock schedule.
+
+The owner's initial supply can be locked this way at initialization by passing an
+`unlock_schedule` of `(unlock timestamp in seconds, amount)` pairs to `new`.
+*/
+use crate::*;
+
+/// Most unlock points an account can have.
+pub const MAX_UNLOCK_POINTS: usize = 100;
+
+#[near_bindgen]
+impl Contract {
+    /// Amount of `account_id`'s balance that is still locked.
+    pub fn locked_balance_of(&self, account_id: AccountId) -> U128 {
+        self.internal_locked_balance(&account_id).into()
+    }
+
+    /// Timestamp (in seconds) and amount of the next unlock of `account_id`, if any.
+    pub fn next_unlock_of(&self, account_id: AccountId) -> Option<(u64, U128)> {
+        let now = current_timestamp_sec();
+        self.time_locks.get(&account_id).and_then(|locks| {
+            locks
+                .into_iter()
+                .find(|(unlock_ts, _)| *unlock_ts > now)
+                .map(|(ts, amount)| (ts, amount.into()))
+        })
+    }
+}
+
+impl Contract {
+    /// Locks parts of `account_id`'s balance until the given timestamps. The schedule must be
+    /// sorted by timestamp and can't lock more than the account holds.
+    pub(crate) fn internal_set_unlock_schedule(
+        &mut self,
+        account_id: &AccountId,
+        schedule: Vec<(u64, U128)>,
+    ) {
+        assert!(schedule.len() <= MAX_UNLOCK_POINTS, "At most {} unlock points", MAX_UNLOCK_POINTS);
+        let mut total: Balance = 0;
+        let mut previous_ts = 0;
+        for (unlock_ts, amount) in schedule.iter() {
+            assert!(*unlock_ts > previous_ts, "The unlock points should be sorted by timestamp");
+            assert!(amount.0 > 0, "The unlock amounts should be positive numbers");
+            previous_ts = *unlock_ts;
+            total = total.checked_add(amount.0).expect("The unlock amounts overflow");
+        }
+        assert!(
+            total <= self.token.accounts.get(account_id).unwrap_or(0),
+            "Can't lock more than the account holds"
+        );
+        let locks: Vec<(u64, Balance)> =
+            schedule.into_iter().map(|(unlock_ts, amount)| (unlock_ts, amount.0)).collect();
+        self.time_locks.insert(account_id, &locks);
+    }
+
+    /// Amount of `account_id`'s balance still under a time lock or a vote lock.
+    pub(crate) fn internal_locked_balance(&self, account_id: &AccountId) -> Balance {
+        let now = current_timestamp_sec();
+        let time_locked = self.time_locks.get(account_id).map_or(0, |locks| {
+            locks.iter().filter(|(unlock_ts, _)| *unlock_ts > now).map(|(_, amount)| amount).sum()
+        });
+        let vote_locked =
+            self.internal_active_vote_lock(account_id).map_or(0, |lock| lock.amount.0);
+        time_locked + vote_locked
+    }
+
+    /// Checks `account_id` can move `amount` out without touching its locked balance or the
+    /// settlement holds on it.
+    pub(crate) fn assert_unlocked(&self, account_id: &AccountId, amount: Balance) {
+        assert_ok(self.check_unlocked(account_id, amount));
+    }
+
+    pub(crate) fn check_unlocked(
+        &self,
+        account_id: &AccountId,
+        amount: Balance,
+    ) -> Result<(), String> {
+        if self.internal_total_locked_balance(account_id) == 0 {
+            return Ok(());
+        }
+        let unlocked = self.internal_unlocked_balance(account_id);
+        if unlocked < amount {
+            return Err(format!("The amount exceeds the unlocked balance of {}", unlocked));
+        }
+        Ok(())
+    }
+
+    /// Balance of `account_id` that isn't locked or on hold.
+    pub(crate) fn internal_unlocked_balance(&self, account_id: &AccountId) -> Balance {
+        let balance = self.token.accounts.get(account_id).unwrap_or(0);
+        balance.saturating_sub(self.internal_total_locked_balance(account_id))
+    }
+
+    /// Part of `account_id`'s balance that's locked independently of the transfers it received:
+    /// time and vote locks, sub-balances and collateral. `ft_resolve_transfer` refunds can't take
+    /// it.
+    pub(crate) fn internal_pinned_balance(&self, account_id: &AccountId) -> Balance {
+        self.internal_locked_balance(account_id)
+            + self.internal_sub_allocated(account_id)
+            + self.internal_collateral_locked(account_id)
+    }
+
+    fn internal_total_locked_balance(&self, account_id: &AccountId) -> Balance {
+        self.internal_locked_balance(account_id)
+            + self.internal_held_balance(account_id)
+            + self.internal_receive_locked_balance(account_id)
+            + self.internal_sub_allocated(account_id)
+            + self.internal_collateral_locked(account_id)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_contract_standards::fungible_token::metadata::FT_METADATA_SPEC;
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let metadata = FungibleTokenMetadata {
+            spec: FT_METADATA_SPEC.to_string(),
+            name: "HealthGo".to_string(),
+            symbol: "HGT".to_string(),
+            icon: None,
+            reference: None,
+            reference_hash: None,
+            decimals: 24,
+        };
+        let schedule = vec![(100, (TOTAL_SUPPLY / 4).into()), (200, (TOTAL_SUPPLY / 4).into())];
+        let mut contract =
+            Contract::new(accounts(1), TOTAL_SUPPLY.into(), metadata, Some(schedule), None);
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_graduated_unlock() {
+        let (mut context, mut contract) = setup();
+        assert_eq!(contract.locked_balance_of(accounts(1)).0, TOTAL_SUPPLY / 2);
+        assert_eq!(contract.next_unlock_of(accounts(1)), Some((100, (TOTAL_SUPPLY / 4).into())));
+        contract.ft_transfer(accounts(2), (TOTAL_SUPPLY / 2).into(), None);
+
+        testing_env!(context.block_timestamp(100 * NANOS).build());
+        assert_eq!(contract.next_unlock_of(accounts(1)), Some((200, (TOTAL_SUPPLY / 4).into())));
+        contract.ft_transfer(accounts(2), (TOTAL_SUPPLY / 4).into(), None);
+
+        testing_env!(context.block_timestamp(200 * NANOS).build());
+        assert_eq!(contract.next_unlock_of(accounts(1)), None);
+        assert_eq!(contract.locked_balance_of(accounts(1)).0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "The amount exceeds the unlocked balance")]
+    fn test_transfer_of_locked_tokens() {
+        let (_, mut contract) = setup();
+        contract.ft_transfer(accounts(2), (TOTAL_SUPPLY / 2 + 1).into(), None);
+    }
+}
@@ -0,0 +1,66 @@
+/*!
+The token as an access credential. `has_min_balance` lets other contracts gate their own features
+on holding a minimum balance through a cross-contract view, and `premium_action` shows the same
+gating inside this contract, with a threshold the owner sets.
+
+Balances are read like `ft_balance_of` reports them, so accounts that made their balance private
+never pass the check.
+*/
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// Whether `account_id` holds at least `min`.
+    pub fn has_min_balance(&self, account_id: AccountId, min: U128) -> bool {
+        self.ft_balance_of(account_id).0 >= min.0
+    }
+
+    /// Sets the balance `premium_action` requires, or disables it when `None`.
+    pub fn set_premium_threshold(&mut self, threshold: Option<U128>) {
+        self.assert_owner_action();
+        self.premium_threshold = threshold.map(|threshold| threshold.0);
+    }
+
+    pub fn get_premium_threshold(&self) -> Option<U128> {
+        self.premium_threshold.map(U128)
+    }
+
+    /// Sample premium feature, only callable by accounts holding the premium threshold.
+    pub fn premium_action(&self) {
+        let threshold = self.premium_threshold.expect("Premium features are disabled");
+        self.assert_min_balance(threshold);
+        log!("Premium action by @{}", env::predecessor_account_id());
+    }
+}
+
+impl Contract {
+    pub(crate) fn assert_min_balance(&self, min: Balance) {
+        assert!(
+            self.has_min_balance(env::predecessor_account_id(), min.into()),
+            "Requires a balance of at least {}",
+            min
+        );
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    #[test]
+    #[should_panic(expected = "Requires a balance of at least 100")]
+    fn test_premium_action_below_threshold() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_premium_threshold(Some(100.into()));
+        contract.premium_action();
+        assert!(!contract.has_min_balance(accounts(2), 1.into()));
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.premium_action();
+    }
+}
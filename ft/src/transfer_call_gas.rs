@@ -0,0 +1,62 @@
+/*!
+Minimum gas for `ft_transfer_call`. Without enough gas the receiver or the resolver runs out
+halfway and the transfer ends up in a confusing state, so calls with less than the transfer and
+callback overhead plus the gas reserved for the receiver are rejected upfront.
+*/
+use crate::*;
+
+/// Gas reserved for the receiver's `ft_on_transfer` by default.
+pub const DEFAULT_TRANSFER_CALL_GAS: Gas = Gas(10_000_000_000_000);
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the least gas the receiver's `ft_on_transfer` has to get.
+    pub fn set_transfer_call_gas(&mut self, transfer_call_gas: u64) {
+        self.assert_owner_action();
+        self.transfer_call_gas = Gas(transfer_call_gas);
+    }
+
+    pub fn get_transfer_call_gas(&self) -> u64 {
+        self.transfer_call_gas.0
+    }
+
+    /// Least prepaid gas `ft_transfer_call` accepts.
+    pub fn min_transfer_call_gas(&self) -> u64 {
+        GAS_FOR_FT_TRANSFER_CALL.0 + self.transfer_call_gas.0
+    }
+}
+
+impl Contract {
+    /// Checks there's enough prepaid gas for `count` transfer calls.
+    pub(crate) fn assert_transfer_call_gas(&self, count: usize) {
+        let min_gas = self.min_transfer_call_gas() * count as u64;
+        assert!(
+            env::prepaid_gas().0 >= min_gas,
+            "Not enough gas attached, the transfer call requires at least {} gas",
+            min_gas
+        );
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    #[test]
+    #[should_panic(expected = "Not enough gas attached")]
+    fn test_transfer_call_with_too_little_gas() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_transfer_call_gas(50_000_000_000_000);
+        testing_env!(context
+            .attached_deposit(1)
+            .prepaid_gas(Gas(contract.min_transfer_call_gas() - 1))
+            .build());
+        contract.ft_transfer_call(accounts(2), 100.into(), None, "".to_string());
+    }
+}
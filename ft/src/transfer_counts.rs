@@ -0,0 +1,70 @@
+/*!
+Number of transfers each account has sent and received, as a cheap on-chain activity metric for
+integrations, e.g. reputation or anti-sybil checks, that would otherwise have to scan the logs.
+Only transfers initiated by accounts are counted, not the contract's own bookkeeping. The counters
+are included in the storage cost of registration.
+*/
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// Number of transfers `account_id` has sent and received.
+    pub fn account_activity(&self, account_id: AccountId) -> (u64, u64) {
+        (
+            self.transfers_sent.get(&account_id).unwrap_or(0),
+            self.transfers_received.get(&account_id).unwrap_or(0),
+        )
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_count_transfer(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+    ) {
+        let sent = self.transfers_sent.get(sender_id).unwrap_or(0);
+        self.transfers_sent.insert(sender_id, &(sent + 1));
+        let received = self.transfers_received.get(receiver_id).unwrap_or(0);
+        self.transfers_received.insert(receiver_id, &(received + 1));
+    }
+
+    /// Adds the storage of both counters for the longest possible account ID to the storage
+    /// required to register an account.
+    pub(crate) fn measure_transfer_count_storage_usage(&mut self) {
+        let initial_storage_usage = env::storage_usage();
+        let tmp_account_id = AccountId::new_unchecked("a".repeat(64));
+        self.internal_count_transfer(&tmp_account_id, &tmp_account_id);
+        self.token.account_storage_usage += env::storage_usage() - initial_storage_usage;
+        self.transfers_sent.remove(&tmp_account_id);
+        self.transfers_received.remove(&tmp_account_id);
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_account_activity() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), 100.into(), None);
+        contract.ft_transfer(accounts(2), 100.into(), None);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.ft_transfer(accounts(1), 50.into(), None);
+        assert_eq!(contract.account_activity(accounts(1)), (2, 1));
+        assert_eq!(contract.account_activity(accounts(2)), (1, 2));
+    }
+}
@@ -0,0 +1,349 @@
+/*!
+Per-account transfer policies. An account can designate a policy contract that approves its
+outgoing transfers through `is_transfer_allowed(sender_id, receiver_id, amount)`. Accounts without
+a policy transfer as usual.
+
+`ft_transfer` from an account with a policy asks the policy contract and completes the transfer in
+the callback. Decisions are cached per (sender, receiver) for the policy's `cache_ttl_seconds`, so
+later transfers to the same receiver complete right away. The other transfer methods only go
+through with a cached approval and are rejected otherwise. Setting a policy again, even the same
+one, drops the cached decisions.
+
+Nothing is locked while the policy contract is being asked, so the transfer is aborted if the
+sender no longer has the balance when the callback runs. A failed call to the policy contract
+aborts the transfer without caching anything.
+
+The sender pays for the storage of a cached decision on top of the 1 yoctoNEAR or the priority fee
+it attaches to `ft_transfer`, see `transfer_decision_deposit_for`. The deposit is refunded if
+nothing gets cached. An expired decision keeps its storage until the next decision for the same
+receiver replaces it.
+*/
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{ext_contract, PromiseError};
+
+const GAS_FOR_IS_TRANSFER_ALLOWED: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_ON_TRANSFER_POLICY_CHECKED: Gas = Gas(20_000_000_000_000);
+
+/// Interface the policy contract is expected to implement.
+#[ext_contract(ext_transfer_policy)]
+#[allow(dead_code)]
+pub trait TransferPolicyContract {
+    fn is_transfer_allowed(
+        &self,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+    ) -> bool;
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TransferPolicy {
+    pub policy_id: AccountId,
+    /// How long (in seconds) a decision of the policy contract is reused.
+    pub cache_ttl_seconds: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the policy approving the caller's outgoing transfers, or removes it when `None`.
+    /// Attached deposit must cover the added storage, the rest is refunded.
+    #[payable]
+    pub fn set_transfer_policy(&mut self, policy: Option<TransferPolicy>) {
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        self.token.internal_unwrap_balance_of(&account_id);
+        let initial_storage_usage = env::storage_usage();
+        match policy {
+            Some(policy) => {
+                let nonce = self.next_transfer_policy_nonce;
+                self.next_transfer_policy_nonce += 1;
+                self.transfer_policies.insert(&account_id, &(policy, nonce))
+            }
+            None => self.transfer_policies.remove(&account_id),
+        };
+        refund_deposit(initial_storage_usage);
+    }
+
+    pub fn get_transfer_policy(&self, account_id: AccountId) -> Option<TransferPolicy> {
+        self.transfer_policies.get(&account_id).map(|(policy, _)| policy)
+    }
+
+    /// The cached decision of the sender's policy on transfers to `receiver_id`, if any.
+    pub fn get_cached_transfer_decision(
+        &self,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+    ) -> Option<bool> {
+        self.internal_cached_transfer_decision(&sender_id, &receiver_id)
+    }
+
+    /// NEAR the sender attaches to `ft_transfer` on top of the usual for the storage of the
+    /// decision its policy is asked for, 0 if nothing would be asked.
+    pub fn transfer_decision_deposit_for(
+        &self,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+    ) -> U128 {
+        self.internal_transfer_decision_deposit(&sender_id, &receiver_id).into()
+    }
+
+    /// Caches the policy's decision and completes the transfer if it was approved. The storage
+    /// deposit goes back to the sender if the decision isn't cached.
+    #[private]
+    #[allow(clippy::too_many_arguments)]
+    pub fn on_transfer_policy_checked(
+        &mut self,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+        policy_nonce: u64,
+        decision_deposit: U128,
+        #[callback_result] allowed: Result<bool, PromiseError>,
+    ) -> bool {
+        let refund_deposit = || {
+            if decision_deposit.0 > 0 {
+                Promise::new(sender_id.clone()).transfer(decision_deposit.0);
+            }
+        };
+        let allowed = match allowed {
+            Ok(allowed) => allowed,
+            Err(_) => {
+                log!("The transfer policy of @{} could not be checked", sender_id);
+                refund_deposit();
+                return false;
+            }
+        };
+        match self.transfer_policies.get(&sender_id) {
+            Some((policy, nonce)) if nonce == policy_nonce => {
+                let until = current_timestamp_sec() + policy.cache_ttl_seconds;
+                self.transfer_decisions
+                    .insert(&(sender_id.clone(), receiver_id.clone()), &(nonce, allowed, until));
+            }
+            _ => {
+                log!("The transfer policy of @{} has changed", sender_id);
+                refund_deposit();
+                return false;
+            }
+        }
+        if !allowed {
+            log!("The transfer from @{} to @{} was denied by its policy", sender_id, receiver_id);
+            return false;
+        }
+        if self.token.accounts.get(&sender_id).unwrap_or(0) < amount.0 {
+            log!("The transfer from @{} was aborted, the balance is too low", sender_id);
+            return false;
+        }
+        self.internal_transfer(&sender_id, &receiver_id, amount.0, memo);
+        true
+    }
+}
+
+impl Contract {
+    /// Asks the sender's policy contract about the transfer if it has a policy without a cached
+    /// decision for `receiver_id`. Returns whether the transfer was deferred to the callback.
+    pub(crate) fn internal_defer_to_transfer_policy(
+        &self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: U128,
+        memo: &Option<String>,
+        decision_deposit: Balance,
+    ) -> bool {
+        let (policy, nonce) = match self.transfer_policies.get(sender_id) {
+            Some(policy) => policy,
+            None => return false,
+        };
+        if self.internal_cached_transfer_decision(sender_id, receiver_id).is_some() {
+            return false;
+        }
+        ext_transfer_policy::ext(policy.policy_id)
+            .with_static_gas(GAS_FOR_IS_TRANSFER_ALLOWED)
+            .is_transfer_allowed(sender_id.clone(), receiver_id.clone(), amount)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_ON_TRANSFER_POLICY_CHECKED)
+                    .on_transfer_policy_checked(
+                        sender_id.clone(),
+                        receiver_id.clone(),
+                        amount,
+                        memo.clone(),
+                        nonce,
+                        decision_deposit.into(),
+                    ),
+            );
+        true
+    }
+
+    pub(crate) fn check_transfer_policy_allows(
+        &self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+    ) -> Result<(), String> {
+        if !self.transfer_policies.contains_key(sender_id) {
+            return Ok(());
+        }
+        match self.internal_cached_transfer_decision(sender_id, receiver_id) {
+            Some(true) => Ok(()),
+            Some(false) => Err(format!(
+                "The transfer policy of @{} denies transfers to @{}",
+                sender_id, receiver_id
+            )),
+            None => Err(format!(
+                "The transfer policy of @{} has to approve transfers to @{} first, use ft_transfer",
+                sender_id, receiver_id
+            )),
+        }
+    }
+
+    /// NEAR covering the storage of the decision `ft_transfer` would ask the sender's policy for.
+    /// A decision replacing an earlier one for the same receiver takes no new storage.
+    pub(crate) fn internal_transfer_decision_deposit(
+        &self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+    ) -> Balance {
+        if !self.transfer_policies.contains_key(sender_id)
+            || self.transfer_decisions.contains_key(&(sender_id.clone(), receiver_id.clone()))
+        {
+            return 0;
+        }
+        self.transfer_decision_storage_usage as Balance * env::storage_byte_cost()
+    }
+
+    pub(crate) fn measure_transfer_decision_storage_usage(&mut self) {
+        let initial_storage_usage = env::storage_usage();
+        let tmp_account_id = AccountId::new_unchecked("a".repeat(64));
+        let key = (tmp_account_id.clone(), tmp_account_id);
+        self.transfer_decisions.insert(&key, &(0, false, 0));
+        self.transfer_decision_storage_usage = env::storage_usage() - initial_storage_usage;
+        self.transfer_decisions.remove(&key);
+    }
+
+    fn internal_cached_transfer_decision(
+        &self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+    ) -> Option<bool> {
+        let (_, policy_nonce) = self.transfer_policies.get(sender_id)?;
+        match self.transfer_decisions.get(&(sender_id.clone(), receiver_id.clone())) {
+            Some((nonce, allowed, until))
+                if nonce == policy_nonce && until >= current_timestamp_sec() =>
+            {
+                Some(allowed)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(ONE_NEAR / 100).build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_transfer_policy(Some(TransferPolicy {
+            policy_id: accounts(4),
+            cache_ttl_seconds: 60,
+        }));
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        (context, contract)
+    }
+
+    fn check(contract: &mut Contract, allowed: bool) -> bool {
+        let (_, nonce) = contract.transfer_policies.get(&accounts(1)).unwrap();
+        contract.on_transfer_policy_checked(
+            accounts(1),
+            accounts(2),
+            10.into(),
+            None,
+            nonce,
+            0.into(),
+            Ok(allowed),
+        )
+    }
+
+    #[test]
+    fn test_approved_transfer_is_cached() {
+        let (mut context, mut contract) = setup();
+        let deposit = contract.transfer_decision_deposit_for(accounts(1), accounts(2)).0;
+        assert!(deposit > 0);
+        testing_env!(context.attached_deposit(deposit).build());
+        contract.ft_transfer(accounts(2), 10.into(), None);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 0);
+        assert!(check(&mut contract, true));
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 10);
+        testing_env!(context.attached_deposit(1).build());
+        contract.ft_transfer(accounts(2), 5.into(), None);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 15);
+        testing_env!(context.block_timestamp(61 * NANOS).build());
+        assert_eq!(contract.get_cached_transfer_decision(accounts(1), accounts(2)), None);
+        assert_eq!(contract.transfer_decision_deposit_for(accounts(1), accounts(2)).0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "for the priority fee and the storage")]
+    fn test_decision_storage_is_charged() {
+        let (_, mut contract) = setup();
+        contract.ft_transfer(accounts(2), 10.into(), None);
+    }
+
+    #[test]
+    fn test_failed_check_is_not_cached() {
+        let (_, mut contract) = setup();
+        let (_, nonce) = contract.transfer_policies.get(&accounts(1)).unwrap();
+        assert!(!contract.on_transfer_policy_checked(
+            accounts(1),
+            accounts(2),
+            10.into(),
+            None,
+            nonce,
+            ONE_NEAR.into(),
+            Err(PromiseError::Failed),
+        ));
+        assert_eq!(contract.get_cached_transfer_decision(accounts(1), accounts(2)), None);
+        assert_eq!(near_sdk::test_utils::get_created_receipts().len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "The transfer policy of @bob denies transfers to @charlie")]
+    fn test_denied_transfer_is_cached() {
+        let (_, mut contract) = setup();
+        assert!(!check(&mut contract, false));
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 0);
+        contract.ft_transfer(accounts(2), 5.into(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "has to approve transfers to @charlie first, use ft_transfer")]
+    fn test_other_methods_need_cached_approval() {
+        let (_, mut contract) = setup();
+        contract.ft_transfer_human(accounts(2), "0.000000000000000000000001".to_string(), None);
+    }
+
+    #[test]
+    fn test_policy_change_drops_cache() {
+        let (mut context, mut contract) = setup();
+        assert!(check(&mut contract, true));
+        testing_env!(context.attached_deposit(ONE_NEAR / 100).block_timestamp(NANOS).build());
+        contract.set_transfer_policy(Some(TransferPolicy {
+            policy_id: accounts(4),
+            cache_ttl_seconds: 60,
+        }));
+        assert_eq!(contract.get_cached_transfer_decision(accounts(1), accounts(2)), None);
+    }
+}
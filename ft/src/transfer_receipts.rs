@@ -0,0 +1,124 @@
+/*!
+Receipts for large transfers, e.g. OTC deals that both parties want to reference later. While a
+threshold is set, every transfer of at least that amount gets a receipt with a unique id, and a
+`transfer_receipt` event with the id follows its `ft_transfer` event. Receipts are never changed,
+but only the latest `receipt_capacity` are kept, since the contract pays for their storage.
+*/
+use crate::events::emit_event;
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+
+pub const DEFAULT_RECEIPT_CAPACITY: u64 = 1_000;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TransferReceipt {
+    pub sender_id: AccountId,
+    pub receiver_id: AccountId,
+    pub amount: U128,
+    pub memo: Option<String>,
+    /// Timestamp of the transfer in seconds.
+    pub timestamp: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Issues receipts for transfers of at least `threshold`, or stops issuing them when `None`.
+    pub fn set_receipt_threshold(&mut self, threshold: Option<U128>) {
+        self.assert_owner_action();
+        self.receipt_threshold = threshold;
+    }
+
+    pub fn get_receipt_threshold(&self) -> Option<U128> {
+        self.receipt_threshold
+    }
+
+    /// Sets the number of receipts kept. Lowering it evicts the oldest receipts on the next one
+    /// issued.
+    pub fn set_receipt_capacity(&mut self, capacity: u64) {
+        self.assert_owner_action();
+        assert!(capacity > 0, "The capacity should be a positive number");
+        self.receipt_capacity = capacity;
+    }
+
+    pub fn get_receipt_capacity(&self) -> u64 {
+        self.receipt_capacity
+    }
+
+    pub fn get_receipt(&self, id: u64) -> Option<TransferReceipt> {
+        self.receipts.get(&id)
+    }
+}
+
+impl Contract {
+    /// Issues a receipt for a transfer of `amount` if it reaches the threshold.
+    pub(crate) fn internal_issue_receipt(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Balance,
+        memo: Option<String>,
+    ) {
+        match self.receipt_threshold {
+            Some(threshold) if amount >= threshold.0 => {}
+            _ => return,
+        }
+        let id = self.next_receipt_id;
+        self.next_receipt_id += 1;
+        let receipt = TransferReceipt {
+            sender_id: sender_id.clone(),
+            receiver_id: receiver_id.clone(),
+            amount: amount.into(),
+            memo,
+            timestamp: current_timestamp_sec(),
+        };
+        self.receipts.insert(&id, &receipt);
+        while self.next_receipt_id - self.first_receipt_id > self.receipt_capacity {
+            self.receipts.remove(&self.first_receipt_id);
+            self.first_receipt_id += 1;
+        }
+        emit_event(
+            "transfer_receipt",
+            json!({
+                "receipt_id": id,
+                "sender_id": sender_id,
+                "receiver_id": receiver_id,
+                "amount": U128(amount),
+            }),
+        );
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_receipts_for_large_transfers() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_receipt_threshold(Some(1_000.into()));
+        contract.set_receipt_capacity(1);
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), 999.into(), None);
+        assert_eq!(contract.get_receipt(0), None);
+        contract.ft_transfer(accounts(2), 1_000.into(), Some("otc".to_string()));
+        let receipt = contract.get_receipt(0).unwrap();
+        assert_eq!(receipt.receiver_id, accounts(2));
+        assert_eq!(receipt.memo, Some("otc".to_string()));
+        contract.ft_transfer(accounts(2), 2_000.into(), None);
+        assert_eq!(contract.get_receipt(0), None);
+        assert_eq!(contract.get_receipt(1).unwrap().amount.0, 2_000);
+    }
+}
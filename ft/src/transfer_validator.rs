@@ -0,0 +1,155 @@
+/*!
+Programmable compliance through an external validator contract. While a validator is configured,
+transfers have to go through `ft_transfer_validated`, which asks the validator's
+`validate(sender_id, receiver_id, amount)` first and only moves the tokens in the callback if it
+returned `true`. `ft_transfer` and `ft_transfer_call` are rejected in the meantime.
+
+Nothing is locked while the validator is being asked, so the transfer is aborted if the sender no
+longer has the balance when the callback runs. A validated transfer takes two extra receipts and
+needs at least `GAS_FOR_VALIDATE + GAS_FOR_ON_TRANSFER_VALIDATED` of gas on top of the transfer.
+*/
+use crate::*;
+use near_sdk::{ext_contract, PromiseError};
+
+const GAS_FOR_VALIDATE: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_ON_TRANSFER_VALIDATED: Gas = Gas(15_000_000_000_000);
+
+/// Interface the validator contract is expected to implement.
+#[ext_contract(ext_transfer_validator)]
+#[allow(dead_code)]
+pub trait TransferValidator {
+    fn validate(&self, sender_id: AccountId, receiver_id: AccountId, amount: U128) -> bool;
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn set_transfer_validator(&mut self, validator_id: Option<AccountId>) {
+        self.assert_owner_action();
+        self.transfer_validator = validator_id;
+    }
+
+    pub fn get_transfer_validator(&self) -> Option<AccountId> {
+        self.transfer_validator.clone()
+    }
+
+    /// Transfers after the validator approved it, or right away if no validator is configured.
+    /// Resolves to whether the transfer happened.
+    #[payable]
+    pub fn ft_transfer_validated(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+    ) -> PromiseOrValue<bool> {
+        self.assert_priority_fee_attached(&[amount.0]);
+        assert_ok(check_memo(&memo));
+        self.assert_no_attestation_gate();
+        let sender_id = env::predecessor_account_id();
+        match self.transfer_validator.clone() {
+            Some(validator_id) => ext_transfer_validator::ext(validator_id)
+                .with_static_gas(GAS_FOR_VALIDATE)
+                .validate(sender_id.clone(), receiver_id.clone(), amount)
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(GAS_FOR_ON_TRANSFER_VALIDATED)
+                        .on_transfer_validated(sender_id, receiver_id, amount, memo),
+                )
+                .into(),
+            None => {
+                self.internal_transfer(&sender_id, &receiver_id, amount.0, memo);
+                PromiseOrValue::Value(true)
+            }
+        }
+    }
+
+    /// Completes the transfer if the validator approved it and the sender still has the balance.
+    #[private]
+    pub fn on_transfer_validated(
+        &mut self,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+        #[callback_result] approved: Result<bool, PromiseError>,
+    ) -> bool {
+        if approved != Ok(true) {
+            log!("The transfer from @{} to @{} was not approved", sender_id, receiver_id);
+            return false;
+        }
+        if self.token.accounts.get(&sender_id).unwrap_or(0) < amount.0 {
+            log!("The transfer from @{} was aborted, the balance is too low", sender_id);
+            return false;
+        }
+        self.internal_transfer(&sender_id, &receiver_id, amount.0, memo);
+        true
+    }
+}
+
+impl Contract {
+    pub(crate) fn assert_no_transfer_validator(&self) {
+        assert!(
+            self.transfer_validator.is_none(),
+            "Transfers must be validated, use ft_transfer_validated"
+        );
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_transfer_validator(Some(accounts(4)));
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context
+            .attached_deposit(0)
+            .predecessor_account_id(env::current_account_id())
+            .build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_approved_transfer() {
+        let (_, mut contract) = setup();
+        assert!(contract.on_transfer_validated(
+            accounts(1),
+            accounts(2),
+            10.into(),
+            None,
+            Ok(true)
+        ));
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 10);
+    }
+
+    #[test]
+    fn test_rejected_transfer() {
+        let (_, mut contract) = setup();
+        assert!(!contract.on_transfer_validated(
+            accounts(1),
+            accounts(2),
+            10.into(),
+            None,
+            Ok(false)
+        ));
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Transfers must be validated")]
+    fn test_plain_transfer_with_validator() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), 10.into(), None);
+    }
+}
@@ -0,0 +1,192 @@
+/*!
+Treasury held by the contract. While configured, `fee_share_bps` of every auto-LP fee goes to the
+treasury instead of the liquidity accumulator, next to the insurance fund's share. The treasury is
+held by the contract account, apart from the circulating supply, and is only spent through
+proposals: the owner proposes a spend with a reason, and can execute it once
+`TREASURY_SPEND_DELAY_SEC` passed, which leaves time to review every pending spend on-chain.
+Behind the multisig owner, both steps need the signers' threshold.
+
+A spend can't exceed the treasury, neither when proposed nor when executed.
+*/
+use crate::events::emit_event;
+use crate::math::{bps_of, BPS_DENOMINATOR};
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+
+/// Time between proposing a spend and executing it.
+pub const TREASURY_SPEND_DELAY_SEC: u64 = 24 * 60 * 60;
+/// Most pending spends at a time.
+pub const MAX_PENDING_TREASURY_SPENDS: u64 = 20;
+/// Longest accepted reason for a spend.
+pub const MAX_SPEND_REASON_LEN: usize = 256;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TreasurySpend {
+    pub receiver_id: AccountId,
+    pub amount: U128,
+    pub reason: String,
+    /// Timestamp (in seconds) from which the spend can be executed.
+    pub executable_at: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the share of the auto-LP fees going to the treasury, in basis points. Zero stops
+    /// feeding it, while its balance is kept.
+    pub fn set_treasury_fee_share(&mut self, fee_share_bps: u16) {
+        self.assert_owner_action();
+        let insurance_bps = self.insurance.as_ref().map_or(0, |insurance| insurance.fee_share_bps);
+        assert!(
+            fee_share_bps + insurance_bps <= BPS_DENOMINATOR,
+            "The treasury and insurance shares can't exceed {} bps",
+            BPS_DENOMINATOR
+        );
+        self.treasury_fee_share_bps = fee_share_bps;
+    }
+
+    pub fn get_treasury_fee_share(&self) -> u16 {
+        self.treasury_fee_share_bps
+    }
+
+    pub fn treasury_balance(&self) -> U128 {
+        self.treasury_balance.into()
+    }
+
+    /// Proposes paying `amount` out of the treasury to `receiver_id`. Returns the spend id.
+    pub fn propose_treasury_spend(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        reason: String,
+    ) -> u64 {
+        self.assert_owner_action();
+        assert!(
+            !reason.is_empty() && reason.len() <= MAX_SPEND_REASON_LEN,
+            "The reason should be between 1 and {} bytes long",
+            MAX_SPEND_REASON_LEN
+        );
+        assert!(amount.0 > 0, "The amount should be a positive number");
+        assert!(amount.0 <= self.treasury_balance, "The treasury is too low");
+        assert!(
+            self.treasury_spends.len() < MAX_PENDING_TREASURY_SPENDS,
+            "There can be at most {} pending spends",
+            MAX_PENDING_TREASURY_SPENDS
+        );
+        self.token.internal_unwrap_balance_of(&receiver_id);
+        let spend_id = self.next_treasury_spend_id;
+        self.next_treasury_spend_id += 1;
+        let spend = TreasurySpend {
+            receiver_id,
+            amount,
+            reason,
+            executable_at: current_timestamp_sec() + TREASURY_SPEND_DELAY_SEC,
+        };
+        emit_event("treasury_spend_proposed", json!({ "spend_id": spend_id, "spend": spend }));
+        self.treasury_spends.insert(&spend_id, &spend);
+        spend_id
+    }
+
+    /// Pays out a proposed spend once its delay passed.
+    pub fn execute_treasury_spend(&mut self, spend_id: u64) {
+        self.assert_owner_action();
+        let spend = self.treasury_spends.get(&spend_id).expect("Spend not found");
+        assert!(
+            current_timestamp_sec() >= spend.executable_at,
+            "The spend can be executed from {}",
+            spend.executable_at
+        );
+        assert!(spend.amount.0 <= self.treasury_balance, "The treasury is too low");
+        self.treasury_spends.remove(&spend_id);
+        self.treasury_balance -= spend.amount.0;
+        self.internal_move_balance(
+            &env::current_account_id(),
+            &spend.receiver_id,
+            spend.amount.0,
+            Some("treasury spend".to_string()),
+        );
+        emit_event("treasury_spend_executed", json!({ "spend_id": spend_id }));
+    }
+
+    pub fn cancel_treasury_spend(&mut self, spend_id: u64) {
+        self.assert_owner_action();
+        self.treasury_spends.remove(&spend_id).expect("Spend not found");
+        emit_event("treasury_spend_cancelled", json!({ "spend_id": spend_id }));
+    }
+
+    pub fn get_treasury_spends(
+        &self,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<(u64, TreasurySpend)> {
+        let from_index = from_index.unwrap_or(0) as usize;
+        let limit = limit.unwrap_or(u64::MAX) as usize;
+        self.treasury_spends.iter().skip(from_index).take(limit).collect()
+    }
+}
+
+impl Contract {
+    /// Moves the treasury's share of `fee`, already held by the contract account, to the treasury
+    /// and returns it.
+    pub(crate) fn internal_take_treasury_share(&mut self, fee: Balance) -> Balance {
+        let share = bps_of(fee, self.treasury_fee_share_bps);
+        self.treasury_balance += share;
+        share
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_auto_lp(accounts(4), (TOTAL_SUPPLY / 2).into(), 100);
+        contract.set_treasury_fee_share(2_000);
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), 10_000.into(), None);
+        testing_env!(context.attached_deposit(0).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_treasury_spend() {
+        let (mut context, mut contract) = setup();
+        assert_eq!(contract.treasury_balance().0, 20);
+        assert_eq!(contract.get_auto_lp().unwrap().accumulated.0, 80);
+        let spend_id = contract.propose_treasury_spend(accounts(2), 15.into(), "grant".to_string());
+        assert_eq!(contract.get_treasury_spends(None, None).len(), 1);
+        testing_env!(context.block_timestamp(TREASURY_SPEND_DELAY_SEC * NANOS).build());
+        contract.execute_treasury_spend(spend_id);
+        assert_eq!(contract.treasury_balance().0, 5);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 9_900 + 15);
+        assert!(contract.get_treasury_spends(None, None).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "The spend can be executed from")]
+    fn test_spend_before_delay() {
+        let (_, mut contract) = setup();
+        let spend_id = contract.propose_treasury_spend(accounts(2), 15.into(), "grant".to_string());
+        contract.execute_treasury_spend(spend_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "The treasury is too low")]
+    fn test_spend_above_treasury() {
+        let (_, mut contract) = setup();
+        contract.propose_treasury_spend(accounts(2), 21.into(), "grant".to_string());
+    }
+}
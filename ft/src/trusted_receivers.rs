@@ -0,0 +1,147 @@
+/*!
+Opt-in protection against sending to the wrong account (e.g. a typosquatted exchange deposit
+address). An account in strict mode can only send to receivers it has explicitly trusted, which
+makes it a send-only-to-approved policy for custody accounts. Unlike the owner's restrictions, the
+account manages the policy itself.
+*/
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// Turns strict mode on or off for the caller.
+    #[payable]
+    pub fn set_strict_receivers(&mut self, enabled: bool) {
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        let initial_storage_usage = env::storage_usage();
+        if enabled {
+            self.strict_receivers.insert(&account_id);
+        } else {
+            self.strict_receivers.remove(&account_id);
+        }
+        refund_deposit(initial_storage_usage);
+    }
+
+    /// Trusts `receiver_id` as a destination for the caller's transfers. Attached deposit must
+    /// cover the added storage, the rest is refunded.
+    #[payable]
+    pub fn add_trusted_receiver(&mut self, receiver_id: AccountId) {
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        let initial_storage_usage = env::storage_usage();
+        let mut receivers = self.trusted_receivers.get(&account_id).unwrap_or_else(|| {
+            UnorderedSet::new(StorageKey::TrustedReceiversPerAccount {
+                account_hash: env::sha256(account_id.as_bytes()),
+            })
+        });
+        receivers.insert(&receiver_id);
+        self.trusted_receivers.insert(&account_id, &receivers);
+        refund_deposit(initial_storage_usage);
+    }
+
+    #[payable]
+    pub fn remove_trusted_receiver(&mut self, receiver_id: AccountId) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        if let Some(mut receivers) = self.trusted_receivers.get(&account_id) {
+            receivers.remove(&receiver_id);
+            if receivers.is_empty() {
+                self.trusted_receivers.remove(&account_id);
+            } else {
+                self.trusted_receivers.insert(&account_id, &receivers);
+            }
+        }
+    }
+
+    pub fn is_strict_receivers(&self, account_id: AccountId) -> bool {
+        self.strict_receivers.contains(&account_id)
+    }
+
+    pub fn get_trusted_receivers(
+        &self,
+        account_id: AccountId,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<AccountId> {
+        let receivers = match self.trusted_receivers.get(&account_id) {
+            Some(receivers) => receivers,
+            None => return vec![],
+        };
+        let from_index = from_index.unwrap_or(0) as usize;
+        let limit = limit.unwrap_or(u64::MAX) as usize;
+        receivers.iter().skip(from_index).take(limit).collect()
+    }
+
+    pub fn is_trusted_receiver(&self, account_id: AccountId, receiver_id: AccountId) -> bool {
+        self.trusted_receivers
+            .get(&account_id)
+            .is_some_and(|receivers| receivers.contains(&receiver_id))
+    }
+}
+
+impl Contract {
+    /// Drops the receivers `account_id` trusts, e.g. when it closes.
+    pub(crate) fn internal_clear_trusted_receivers(&mut self, account_id: &AccountId) {
+        if let Some(mut receivers) = self.trusted_receivers.remove(account_id) {
+            receivers.clear();
+        }
+    }
+
+    pub(crate) fn check_trusted_receiver(
+        &self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+    ) -> Result<(), String> {
+        if self.strict_receivers.contains(sender_id)
+            && !self.is_trusted_receiver(sender_id.clone(), receiver_id.clone())
+        {
+            return Err(format!("The receiver {} is not trusted by the sender", receiver_id));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context
+            .attached_deposit(ONE_NEAR / 100)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.set_strict_receivers(true);
+        (context, contract)
+    }
+
+    #[test]
+    fn test_transfer_to_trusted_receiver() {
+        let (mut context, mut contract) = setup();
+        contract.add_trusted_receiver(accounts(2));
+        assert_eq!(contract.get_trusted_receivers(accounts(1), None, None), vec![accounts(2)]);
+        testing_env!(context.attached_deposit(1).build());
+        contract.ft_transfer(accounts(2), 10.into(), None);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "is not trusted by the sender")]
+    fn test_transfer_to_untrusted_receiver() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.attached_deposit(1).build());
+        contract.ft_transfer(accounts(2), 10.into(), None);
+    }
+}
@@ -0,0 +1,143 @@
+/*!
+Time-weighted average balances, e.g. for reward systems that shouldn't favour balances held just
+for the snapshot. After every balance change the new balance is recorded with its timestamp, in a
+history bounded to `balance_history_capacity` entries per account, so the average can be computed
+over any window the history still covers.
+
+Registration doesn't cover the history, so it's disabled by default and enabling it stores every
+account's history at the contract's expense.
+*/
+use crate::math::U256;
+use crate::*;
+
+pub const DEFAULT_BALANCE_HISTORY_CAPACITY: u64 = 0;
+/// Largest allowed history per account, to bound the gas every transfer spends on it.
+pub const MAX_BALANCE_HISTORY_CAPACITY: u64 = 100;
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the number of balance changes kept per account. Zero stops recording. Lowering it
+    /// evicts the extra entries on the account's next balance change.
+    pub fn set_balance_history_capacity(&mut self, capacity: u64) {
+        self.assert_owner_action();
+        assert!(
+            capacity <= MAX_BALANCE_HISTORY_CAPACITY,
+            "The capacity can't exceed {}",
+            MAX_BALANCE_HISTORY_CAPACITY
+        );
+        self.balance_history_capacity = capacity;
+    }
+
+    pub fn get_balance_history_capacity(&self) -> u64 {
+        self.balance_history_capacity
+    }
+
+    /// Average balance of `account_id` between `from_ts` and `to_ts`, in seconds, weighted by how
+    /// long each balance was held. The window must be in the past and covered by the history.
+    pub fn twab(&self, account_id: AccountId, from_ts: u64, to_ts: u64) -> U128 {
+        assert!(from_ts < to_ts, "The window should end after it starts");
+        assert!(to_ts <= current_timestamp_sec(), "The window can't end in the future");
+        let history = self.balance_history.get(&account_id).unwrap_or_default();
+        if let Some((first_ts, _)) = history.first() {
+            // Once the history is full, older entries may have been evicted.
+            assert!(
+                from_ts >= *first_ts || (history.len() as u64) < self.balance_history_capacity,
+                "The window starts before the recorded history"
+            );
+        }
+        let mut weighted_sum = U256::zero();
+        let mut balance: Balance = 0;
+        let mut ts = from_ts;
+        for (change_ts, new_balance) in history {
+            if change_ts > ts {
+                let end_ts = change_ts.min(to_ts);
+                weighted_sum += U256::from(balance) * U256::from(end_ts - ts);
+                ts = end_ts;
+            }
+            if change_ts >= to_ts {
+                break;
+            }
+            balance = new_balance;
+        }
+        weighted_sum += U256::from(balance) * U256::from(to_ts - ts);
+        (weighted_sum / U256::from(to_ts - from_ts)).as_u128().into()
+    }
+}
+
+impl Contract {
+    /// Records the current balance of `account_id`. Must be called after every change to it.
+    pub(crate) fn internal_record_balance(&mut self, account_id: &AccountId) {
+        self.internal_record_change(format!("balance:{}", account_id));
+        self.internal_update_tier(account_id);
+        self.internal_check_balance_alerts(account_id);
+        if self.balance_history_capacity == 0 || account_id == &env::current_account_id() {
+            return;
+        }
+        let balance = match self.token.accounts.get(account_id) {
+            Some(balance) => balance,
+            None => return,
+        };
+        let now = current_timestamp_sec();
+        let mut history = self.balance_history.get(account_id).unwrap_or_default();
+        match history.last_mut() {
+            Some(last) if last.0 == now => last.1 = balance,
+            _ => history.push((now, balance)),
+        }
+        let excess = history.len().saturating_sub(self.balance_history_capacity as usize);
+        history.drain(..excess);
+        self.balance_history.insert(account_id, &history);
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_balance_history_capacity(24);
+        testing_env!(context
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(10 * NANOS)
+            .build());
+        contract.ft_transfer(accounts(2), 100.into(), None);
+        testing_env!(context.block_timestamp(20 * NANOS).build());
+        contract.ft_transfer(accounts(2), 200.into(), None);
+        testing_env!(context.block_timestamp(40 * NANOS).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_twab() {
+        let (_, contract) = setup();
+        // 0 for 10s, 100 for 10s, 300 for 20s.
+        assert_eq!(contract.twab(accounts(2), 0, 40).0, (100 * 10 + 300 * 20) / 40);
+        assert_eq!(contract.twab(accounts(2), 15, 25).0, (100 * 5 + 300 * 5) / 10);
+        assert_eq!(contract.twab(accounts(1), 10, 20).0, TOTAL_SUPPLY - 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "The window starts before the recorded history")]
+    fn test_window_before_evicted_history() {
+        let (_, mut contract) = setup();
+        contract.set_balance_history_capacity(1);
+        testing_env!(get_context(accounts(1))
+            .attached_deposit(1)
+            .block_timestamp(50 * NANOS)
+            .build());
+        contract.ft_transfer(accounts(2), 1.into(), None);
+        contract.twab(accounts(2), 0, 50);
+    }
+}
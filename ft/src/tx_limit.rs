@@ -0,0 +1,175 @@
+/*!
+Anti-whale limit on the size of a single transfer, as a share of the current total supply so it
+keeps up with mints and burns. The owner and the accounts on the owner's exemption list can send
+any amount.
+
+Against bots, the number of transfers an account sends within a single block can be limited too.
+The same accounts are exempt from it.
+*/
+use crate::math::{bps_of, BPS_DENOMINATOR};
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the largest transfer in basis points of the total supply. Zero disables the limit.
+    pub fn set_max_tx_bps(&mut self, max_tx_bps: u16) {
+        self.assert_owner_action();
+        assert!(max_tx_bps <= BPS_DENOMINATOR, "The limit can't exceed {} bps", BPS_DENOMINATOR);
+        self.max_tx_bps = max_tx_bps;
+    }
+
+    pub fn get_max_tx_bps(&self) -> u16 {
+        self.max_tx_bps
+    }
+
+    /// Largest transfer allowed right now, if limited.
+    pub fn get_max_tx_amount(&self) -> Option<U128> {
+        if self.max_tx_bps == 0 {
+            return None;
+        }
+        Some(bps_of(self.token.total_supply, self.max_tx_bps).into())
+    }
+
+    /// Sets the most transfers an account can send within a block. Zero disables the limit.
+    pub fn set_max_transfers_per_block(&mut self, max_transfers_per_block: u32) {
+        self.assert_owner_action();
+        self.max_transfers_per_block = max_transfers_per_block;
+    }
+
+    pub fn get_max_transfers_per_block(&self) -> u32 {
+        self.max_transfers_per_block
+    }
+
+    /// Transfers `account_id` can still send within the current block, if limited.
+    pub fn remaining_transfers_in_block(&self, account_id: AccountId) -> Option<u32> {
+        if self.max_transfers_per_block == 0 || self.is_tx_limit_exempt(account_id.clone()) {
+            return None;
+        }
+        let sent = match self.block_transfer_counts.get(&account_id) {
+            Some((block_height, count)) if block_height == env::block_height() => count,
+            _ => 0,
+        };
+        Some(self.max_transfers_per_block.saturating_sub(sent))
+    }
+
+    pub fn add_tx_limit_exempt(&mut self, account_id: AccountId) {
+        self.assert_owner_action();
+        self.tx_limit_exempt.insert(&account_id);
+    }
+
+    pub fn remove_tx_limit_exempt(&mut self, account_id: AccountId) {
+        self.assert_owner_action();
+        self.tx_limit_exempt.remove(&account_id);
+    }
+
+    pub fn is_tx_limit_exempt(&self, account_id: AccountId) -> bool {
+        account_id == self.owner_id || self.tx_limit_exempt.contains(&account_id)
+    }
+}
+
+impl Contract {
+    pub(crate) fn check_within_tx_limit(
+        &self,
+        sender_id: &AccountId,
+        amount: Balance,
+    ) -> Result<(), String> {
+        match self.get_max_tx_amount() {
+            Some(max_tx_amount)
+                if amount > max_tx_amount.0 && !self.is_tx_limit_exempt(sender_id.clone()) =>
+            {
+                Err(format!("The transfer exceeds the limit of {}", max_tx_amount.0))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails if `sender_id` already sent as many transfers in the current block as it can.
+    pub(crate) fn check_block_transfer_count(&self, sender_id: &AccountId) -> Result<(), String> {
+        if self.remaining_transfers_in_block(sender_id.clone()) == Some(0) {
+            return Err(format!(
+                "The sender can't send more than {} transfers per block",
+                self.max_transfers_per_block
+            ));
+        }
+        Ok(())
+    }
+
+    /// Counts a transfer sent by `sender_id` in the current block. Must be checked with
+    /// `check_block_transfer_count` first.
+    pub(crate) fn internal_count_block_transfer(&mut self, sender_id: &AccountId) {
+        let remaining = match self.remaining_transfers_in_block(sender_id.clone()) {
+            Some(remaining) => remaining,
+            None => return,
+        };
+        let count = self.max_transfers_per_block - remaining + 1;
+        self.block_transfer_counts.insert(sender_id, &(env::block_height(), count));
+    }
+
+    /// Adds the storage of a block transfer count for the longest possible account ID to the
+    /// storage required to register an account.
+    pub(crate) fn measure_block_transfer_count_storage_usage(&mut self) {
+        let initial_storage_usage = env::storage_usage();
+        let tmp_account_id = AccountId::new_unchecked("a".repeat(64));
+        self.block_transfer_counts.insert(&tmp_account_id, &(0, 0));
+        self.token.account_storage_usage += env::storage_usage() - initial_storage_usage;
+        self.block_transfer_counts.remove(&tmp_account_id);
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.set_max_tx_bps(100);
+        for account_id in [accounts(2), accounts(3)] {
+            testing_env!(context
+                .attached_deposit(contract.storage_balance_bounds().min.into())
+                .predecessor_account_id(account_id)
+                .build());
+            contract.storage_deposit(None, None);
+        }
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.ft_transfer(accounts(2), (TOTAL_SUPPLY / 10).into(), None);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_transfer_at_limit() {
+        let (_, mut contract) = setup();
+        assert_eq!(contract.get_max_tx_amount(), Some((TOTAL_SUPPLY / 100).into()));
+        contract.ft_transfer(accounts(3), (TOTAL_SUPPLY / 100).into(), None);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, TOTAL_SUPPLY / 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "The transfer exceeds the limit")]
+    fn test_transfer_over_limit() {
+        let (_, mut contract) = setup();
+        contract.ft_transfer(accounts(3), (TOTAL_SUPPLY / 100 + 1).into(), None);
+    }
+    #[test]
+    #[should_panic(expected = "The sender can't send more than 2 transfers per block")]
+    fn test_transfers_per_block() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.set_max_transfers_per_block(2);
+        testing_env!(context.predecessor_account_id(accounts(2)).block_index(1).build());
+        contract.ft_transfer(accounts(3), 1.into(), None);
+        contract.ft_transfer(accounts(3), 1.into(), None);
+        assert_eq!(contract.remaining_transfers_in_block(accounts(2)), Some(0));
+        testing_env!(context.block_index(2).build());
+        assert_eq!(contract.remaining_transfers_in_block(accounts(2)), Some(2));
+        contract.ft_transfer(accounts(3), 1.into(), None);
+        contract.ft_transfer(accounts(3), 1.into(), None);
+        contract.ft_transfer(accounts(3), 1.into(), None);
+    }
+}
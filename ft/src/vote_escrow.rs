@@ -0,0 +1,150 @@
+/*!
+Vote-escrow for governance, in the style of Curve's veCRV. An account locks part of its balance
+until a chosen time, up to `MAX_VOTE_LOCK_SEC` ahead, and gets voting power proportional to the
+amount and the time left: a maximal lock gives one vote per token, decaying linearly to zero at
+the unlock time. The lock can be extended or topped up, but never shortened or reduced.
+
+The tokens stay on the account and keep earning rewards, but count as time-locked until the
+unlock time, so they can't be moved out. Each account has at most one vote lock.
+*/
+use crate::math::mul_div;
+use crate::*;
+use near_sdk::serde::{Deserialize, Serialize};
+
+/// Longest vote lock, four years.
+pub const MAX_VOTE_LOCK_SEC: u64 = 4 * 365 * 24 * 60 * 60;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct VoteLock {
+    pub amount: U128,
+    /// Timestamp (in seconds) the tokens unlock at.
+    pub unlock_ts: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Locks `amount` of the caller's balance for `duration` seconds in exchange for voting power.
+    /// Replaces an expired lock. Attached deposit must cover the added storage, the rest is
+    /// refunded.
+    #[payable]
+    pub fn lock_for_voting(&mut self, amount: U128, duration: u64) {
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        assert!(
+            self.internal_active_vote_lock(&account_id).is_none(),
+            "Extend or increase the existing lock instead"
+        );
+        assert!(amount.0 > 0, "The amount should be a positive number");
+        assert_vote_lock_duration(duration);
+        self.assert_unlocked(&account_id, amount.0);
+        let initial_storage_usage = env::storage_usage();
+        let lock = VoteLock { amount, unlock_ts: current_timestamp_sec() + duration };
+        self.vote_locks.insert(&account_id, &lock);
+        refund_deposit(initial_storage_usage);
+    }
+
+    /// Moves the unlock time of the caller's lock to `duration` seconds from now, which must be
+    /// later than the current one.
+    pub fn extend_lock(&mut self, duration: u64) {
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        let mut lock = self.internal_active_vote_lock(&account_id).expect("No active vote lock");
+        assert_vote_lock_duration(duration);
+        let unlock_ts = current_timestamp_sec() + duration;
+        assert!(unlock_ts > lock.unlock_ts, "The lock can only be extended");
+        lock.unlock_ts = unlock_ts;
+        self.vote_locks.insert(&account_id, &lock);
+    }
+
+    /// Adds `amount` of the caller's balance to its lock, keeping the unlock time.
+    pub fn increase_lock_amount(&mut self, amount: U128) {
+        let account_id = env::predecessor_account_id();
+        self.internal_record_activity(&account_id);
+        let mut lock = self.internal_active_vote_lock(&account_id).expect("No active vote lock");
+        assert!(amount.0 > 0, "The amount should be a positive number");
+        self.assert_unlocked(&account_id, amount.0);
+        lock.amount = (lock.amount.0 + amount.0).into();
+        self.vote_locks.insert(&account_id, &lock);
+    }
+
+    pub fn get_vote_lock(&self, account_id: AccountId) -> Option<VoteLock> {
+        self.vote_locks.get(&account_id)
+    }
+
+    /// Voting power of the own lock of `account_id` plus what's delegated to it, or zero if it
+    /// delegated its power away.
+    pub fn voting_power_of(&self, account_id: AccountId) -> U128 {
+        if self.delegates.contains_key(&account_id) {
+            return 0.into();
+        }
+        let delegators = self.delegators.get(&account_id).unwrap_or_default();
+        delegators
+            .iter()
+            .fold(self.internal_lock_voting_power(&account_id), |power, delegator_id| {
+                power + self.internal_lock_voting_power(delegator_id)
+            })
+            .into()
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_lock_voting_power(&self, account_id: &AccountId) -> Balance {
+        self.internal_active_vote_lock(account_id).map_or(0, |lock| {
+            let remaining = lock.unlock_ts - current_timestamp_sec();
+            mul_div(lock.amount.0, remaining as Balance, MAX_VOTE_LOCK_SEC as Balance)
+        })
+    }
+
+    /// The vote lock of `account_id`, if it hasn't expired yet.
+    pub(crate) fn internal_active_vote_lock(&self, account_id: &AccountId) -> Option<VoteLock> {
+        self.vote_locks.get(account_id).filter(|lock| lock.unlock_ts > current_timestamp_sec())
+    }
+}
+
+fn assert_vote_lock_duration(duration: u64) {
+    assert!(
+        duration > 0 && duration <= MAX_VOTE_LOCK_SEC,
+        "The duration should be between 1 and {} seconds",
+        MAX_VOTE_LOCK_SEC
+    );
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    use super::*;
+    use crate::tests::{get_context, NANOS, TOTAL_SUPPLY};
+
+    fn setup() -> (near_sdk::test_utils::VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(ONE_NEAR / 100).build());
+        let mut contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
+        contract.lock_for_voting(1_000.into(), MAX_VOTE_LOCK_SEC);
+        (context, contract)
+    }
+
+    #[test]
+    fn test_voting_power_decays() {
+        let (mut context, mut contract) = setup();
+        assert_eq!(contract.voting_power_of(accounts(1)).0, 1_000);
+        testing_env!(context.block_timestamp(MAX_VOTE_LOCK_SEC / 4 * NANOS).build());
+        assert_eq!(contract.voting_power_of(accounts(1)).0, 750);
+        contract.increase_lock_amount(1_000.into());
+        assert_eq!(contract.voting_power_of(accounts(1)).0, 1_500);
+        contract.extend_lock(MAX_VOTE_LOCK_SEC);
+        assert_eq!(contract.voting_power_of(accounts(1)).0, 2_000);
+        testing_env!(context.block_timestamp(2 * MAX_VOTE_LOCK_SEC * NANOS).build());
+        assert_eq!(contract.voting_power_of(accounts(1)).0, 0);
+        assert_eq!(contract.locked_balance_of(accounts(1)).0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "The amount exceeds the unlocked balance of")]
+    fn test_locked_tokens_cant_move() {
+        let (_, mut contract) = setup();
+        contract.internal_burn(&accounts(1), TOTAL_SUPPLY - 999, "test");
+    }
+}